@@ -8,6 +8,7 @@ use sha3::{Digest, Keccak256};
 use std::cmp::max;
 use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
 use std::process::exit;
 use std::string::String;
 use std::vec::Vec;
@@ -22,7 +23,7 @@ pub struct PassResult {
 
 pub type TypeIdentifier = String;
 
-#[derive(Clone, Default, Debug, PartialEq)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash)]
 pub struct LineInfo {
     pub line: u32,
     pub offset: usize,
@@ -299,6 +300,7 @@ impl Visitable for TopLevelDeclaration {
 
 #[derive(Clone, Default, Debug)]
 pub struct ContractDeclaration {
+    pub attributes: Vec<Attribute>,
     pub identifier: Identifier,
     pub contract_members: Vec<ContractMember>,
     pub conformances: Vec<Conformance>,
@@ -321,25 +323,6 @@ impl ContractDeclaration {
 
         return members;
     }
-
-    pub fn get_variable_declarations_without_dict(&self) -> Vec<VariableDeclaration> {
-        let members = self.contract_members.clone();
-        let members = members
-            .into_iter()
-            .filter_map(|c| match c {
-                ContractMember::VariableDeclaration(v) => {
-                    if v.clone().variable_type.is_dictionary_type() {
-                        None
-                    } else {
-                        Some(v)
-                    }
-                }
-                ContractMember::EventDeclaration(_) => None,
-            })
-            .collect();
-
-        return members;
-    }
 }
 
 impl Visitable for ContractDeclaration {
@@ -389,6 +372,7 @@ impl Visitable for ContractDeclaration {
 
 #[derive(Debug, Clone)]
 pub struct ContractBehaviourDeclaration {
+    pub attributes: Vec<Attribute>,
     pub identifier: Identifier,
     pub members: Vec<ContractBehaviourMember>,
     pub states: Vec<TypeState>,
@@ -399,6 +383,7 @@ pub struct ContractBehaviourDeclaration {
 impl Visitable for ContractBehaviourDeclaration {
     fn visit(&mut self, v: &mut dyn Visitor, ctx: &mut Context) -> VResult {
         ctx.ContractBehaviourDeclarationContext = Some(ContractBehaviourDeclarationContext {
+            attributes: self.attributes.clone(),
             identifier: self.identifier.clone(),
             caller: self.caller_binding.clone(),
             caller_protections: self.caller_protections.clone(),
@@ -409,6 +394,7 @@ impl Visitable for ContractBehaviourDeclaration {
             let caller_binding = self.caller_binding.clone();
             let caller_binding = caller_binding.unwrap();
             local_variables.push(VariableDeclaration {
+                attributes: vec![],
                 declaration_token: None,
                 identifier: caller_binding,
                 variable_type: Type::Address,
@@ -481,7 +467,9 @@ impl Visitable for ContractBehaviourDeclaration {
 
 #[derive(Debug, Clone)]
 pub struct AssetDeclaration {
+    pub attributes: Vec<Attribute>,
     pub identifier: Identifier,
+    pub conformances: Vec<Conformance>,
     pub members: Vec<AssetMember>,
 }
 
@@ -530,6 +518,12 @@ impl Visitable for AssetDeclaration {
             Err(e) => return Err(e),
         }
 
+        let result = self.conformances.visit(v, ctx);
+        match result {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+
         for member in &mut self.members {
             ctx.ScopeContext = Option::from(ScopeContext {
                 parameters: vec![],
@@ -581,6 +575,7 @@ impl Visitable for AssetMember {
 
 #[derive(Debug, Clone)]
 pub struct StructDeclaration {
+    pub attributes: Vec<Attribute>,
     pub identifier: Identifier,
     pub conformances: Vec<Conformance>,
     pub members: Vec<StructMember>,
@@ -627,6 +622,12 @@ impl Visitable for StructDeclaration {
             Err(e) => return Err(e),
         }
 
+        let result = self.conformances.visit(v, ctx);
+        match result {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+
         for member in &mut self.members {
             ctx.ScopeContext = Option::from(ScopeContext {
                 parameters: vec![],
@@ -649,6 +650,7 @@ impl Visitable for StructDeclaration {
 
 #[derive(Clone, Debug)]
 pub struct EnumDeclaration {
+    pub attributes: Vec<Attribute>,
     pub enum_token: std::string::String,
     pub identifier: Identifier,
     pub type_assigned: Option<Type>,
@@ -663,6 +665,7 @@ impl Visitable for EnumDeclaration {
 
 #[derive(Debug, Clone)]
 pub struct TraitDeclaration {
+    pub attributes: Vec<Attribute>,
     pub trait_kind: std::string::String,
     pub identifier: Identifier,
     pub members: Vec<TraitMember>,
@@ -670,6 +673,10 @@ pub struct TraitDeclaration {
 }
 
 impl TraitDeclaration {
+    pub fn is_external(&self) -> bool {
+        self.trait_kind == "external"
+    }
+
     pub fn get_module_address(&self) -> Option<String> {
         let modifiers = self.modifiers.clone();
         let mut modifiers: Vec<FunctionCall> = modifiers
@@ -914,6 +921,16 @@ impl Conformance {
 
 impl Visitable for Conformance {
     fn visit(&mut self, v: &mut dyn Visitor, ctx: &mut Context) -> VResult {
+        let result = v.start_conformance(self, ctx);
+        match result {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+        let result = v.finish_conformance(self, ctx);
+        match result {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
         Ok(())
     }
 }
@@ -993,6 +1010,10 @@ impl FunctionDeclaration {
         self.head.is_payable()
     }
 
+    pub fn is_non_reentrant(&self) -> bool {
+        self.head.is_non_reentrant()
+    }
+
     pub fn first_payable_param(&self) -> Option<Parameter> {
         if !self.is_payable() {
             return None;
@@ -1152,6 +1173,13 @@ impl FunctionSignatureDeclaration {
         return self.payable;
     }
 
+    // @nonReentrant functions take a lock for the duration of the call
+    pub fn is_non_reentrant(&self) -> bool {
+        self.attributes
+            .iter()
+            .any(|a| a.identifier_token == "nonReentrant")
+    }
+
     pub fn is_public(&self) -> bool {
         self.modifiers.contains(&"public".to_string())
     }
@@ -1393,6 +1421,7 @@ pub struct SpecialSignatureDeclaration {
     pub modifiers: Vec<std::string::String>,
     pub mutates: Vec<Identifier>,
     pub parameters: Vec<Parameter>,
+    pub line_info: LineInfo,
 }
 
 impl SpecialSignatureDeclaration {
@@ -1433,6 +1462,7 @@ pub enum Statement {
     ForStatement(ForStatement),
     IfStatement(IfStatement),
     DoCatchStatement(DoCatchStatement),
+    TargetStatement(TargetStatement),
 }
 
 impl Statement {
@@ -1459,6 +1489,7 @@ impl Visitable for Statement {
             Statement::ForStatement(f) => f.visit(v, ctx),
             Statement::IfStatement(i) => i.visit(v, ctx),
             Statement::DoCatchStatement(d) => d.visit(v, ctx),
+            Statement::TargetStatement(t) => t.statement.visit(v, ctx),
         };
         match result {
             Ok(_) => {}
@@ -1473,6 +1504,13 @@ impl Visitable for Statement {
     }
 }
 
+// A statement only compiled for the targets named by its attributes
+#[derive(Clone, Debug)]
+pub struct TargetStatement {
+    pub attributes: Vec<Attribute>,
+    pub statement: Box<Statement>,
+}
+
 #[derive(Clone, Debug)]
 pub struct DoCatchStatement {
     pub error: Expression,
@@ -1843,7 +1881,7 @@ impl Expression {
             Expression::BracketedExpression(b) => return b.expression.enclosing_type(),
             Expression::FunctionCall(f) => return f.identifier.enclosing_type,
             Expression::ExternalCall(e) => e.function_call.lhs_expression.enclosing_type(),
-            Expression::SubscriptExpression(_) => unimplemented!(),
+            Expression::SubscriptExpression(s) => s.base_expression.enclosing_type,
             _ => None,
         }
     }
@@ -1867,19 +1905,20 @@ impl Expression {
             Expression::Identifier(i) => i.line_info.clone(),
             Expression::BinaryExpression(b) => b.line_info.clone(),
             Expression::InoutExpression(i) => i.expression.get_line_info(),
-            Expression::ExternalCall(_) => unimplemented!(),
-            Expression::FunctionCall(_) => unimplemented!(),
-            Expression::VariableDeclaration(_) => unimplemented!(),
-            Expression::BracketedExpression(_) => unimplemented!(),
-            Expression::AttemptExpression(_) => unimplemented!(),
-            Expression::Literal(_) => unimplemented!(),
-            Expression::ArrayLiteral(_) => unimplemented!(),
-            Expression::DictionaryLiteral(_) => unimplemented!(),
-            Expression::SelfExpression => unimplemented!(),
-            Expression::SubscriptExpression(_) => unimplemented!(),
-            Expression::RangeExpression(_) => unimplemented!(),
+            Expression::ExternalCall(e) => e.function_call.line_info.clone(),
+            Expression::FunctionCall(f) => f.identifier.line_info.clone(),
+            Expression::VariableDeclaration(v) => v.identifier.line_info.clone(),
+            Expression::BracketedExpression(b) => b.expression.get_line_info(),
+            Expression::AttemptExpression(a) => a.function_call.identifier.line_info.clone(),
+            Expression::SubscriptExpression(s) => s.base_expression.line_info.clone(),
+            Expression::CastExpression(c) => c.expression.get_line_info(),
+            Expression::RangeExpression(r) => r.start_expression.get_line_info(),
+            // Literals do not record where they were parsed
+            Expression::Literal(_) => Default::default(),
+            Expression::ArrayLiteral(_) => Default::default(),
+            Expression::DictionaryLiteral(_) => Default::default(),
+            Expression::SelfExpression => Default::default(),
             Expression::RawAssembly(_, _) => unimplemented!(),
-            Expression::CastExpression(_) => unimplemented!(),
             Expression::Sequence(_) => unimplemented!(),
         }
     }
@@ -2092,7 +2131,7 @@ pub enum Literal {
     AddressLiteral(String),
     StringLiteral(String),
     IntLiteral(u64),
-    FloatLiteral(f64),
+    FloatLiteral(String),
 }
 
 impl Visitable for Literal {
@@ -2115,6 +2154,23 @@ impl AttemptExpression {
 
 impl Visitable for AttemptExpression {
     fn visit(&mut self, v: &mut dyn Visitor, ctx: &mut Context) -> VResult {
+        let result = v.start_attempt_expression(self, ctx);
+        match result {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+
+        let result = self.function_call.visit(v, ctx);
+        match result {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+
+        let result = v.finish_attempt_expression(self, ctx);
+        match result {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
         Ok(())
     }
 }
@@ -2136,9 +2192,11 @@ impl Visitable for ExternalCall {
 
         let old_is_external_call = ctx.IsExternalFunctionCall.clone();
         let old_external_call_context = ctx.ExternalCallContext.clone();
+        let old_is_l_value = ctx.IsLValue;
 
         ctx.IsExternalFunctionCall = true;
         ctx.ExternalCallContext = Option::from(self.clone());
+        ctx.IsLValue = false;
 
         let result = self.function_call.visit(v, ctx);
         match result {
@@ -2148,6 +2206,7 @@ impl Visitable for ExternalCall {
 
         ctx.IsExternalFunctionCall = old_is_external_call;
         ctx.ExternalCallContext = old_external_call_context;
+        ctx.IsLValue = old_is_l_value;
 
         let result = v.finish_external_call(self, ctx);
         match result {
@@ -2245,6 +2304,21 @@ impl BinOp {
         }
     }
 
+    pub fn is_arithmetic(&self) -> bool {
+        match self {
+            BinOp::Plus => true,
+            BinOp::OverflowingPlus => true,
+            BinOp::Minus => true,
+            BinOp::OverflowingMinus => true,
+            BinOp::Times => true,
+            BinOp::OverflowingTimes => true,
+            BinOp::Divide => true,
+            BinOp::Percent => true,
+            BinOp::Power => true,
+            _ => false,
+        }
+    }
+
     pub fn is_assignment(&self) -> bool {
         match self {
             BinOp::Equal => true,
@@ -2277,6 +2351,38 @@ impl BinOp {
     }
 }
 
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let token = match self {
+            BinOp::Plus => "+",
+            BinOp::OverflowingPlus => "&+",
+            BinOp::Minus => "-",
+            BinOp::OverflowingMinus => "&-",
+            BinOp::Times => "*",
+            BinOp::OverflowingTimes => "&*",
+            BinOp::Power => "**",
+            BinOp::Divide => "/",
+            BinOp::Percent => "%",
+            BinOp::Dot => ".",
+            BinOp::Equal => "=",
+            BinOp::PlusEqual => "+=",
+            BinOp::MinusEqual => "-=",
+            BinOp::TimesEqual => "*=",
+            BinOp::DivideEqual => "/=",
+            BinOp::DoubleEqual => "==",
+            BinOp::NotEqual => "!=",
+            BinOp::LessThan => "<",
+            BinOp::LessThanOrEqual => "<=",
+            BinOp::GreaterThan => ">",
+            BinOp::GreaterThanOrEqual => ">=",
+            BinOp::Or => "||",
+            BinOp::And => "&&",
+            BinOp::Implies => "==>",
+        };
+        write!(f, "{}", token)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct BinaryExpression {
     pub lhs_expression: Box<Expression>,
@@ -2317,6 +2423,11 @@ impl Visitable for BinaryExpression {
             ctx.IsLValue = false;
         }
 
+        // Only the target of an assignment is mutated, not the identifiers read on its right
+        if self.op.is_assignment() {
+            ctx.IsLValue = false;
+        }
+
         ctx.ExternalCallContext = old_context;
         ctx.IsEnclosing = false;
 
@@ -2396,6 +2507,19 @@ pub struct FunctionCall {
     pub mangled_identifier: Option<Identifier>,
 }
 
+impl FunctionCall {
+    // Initialising a Currency creates the coin of the target, and calls on a
+    // Currency receiver go to that coin
+    pub fn resolve_currency(&mut self, currency: &TypeIdentifier) {
+        if self.identifier.token == "Currency" {
+            self.identifier.token = currency.clone();
+        }
+        if self.identifier.enclosing_type.as_deref() == Some("Currency") {
+            self.identifier.enclosing_type = Some(currency.clone());
+        }
+    }
+}
+
 impl Visitable for FunctionCall {
     fn visit(&mut self, v: &mut dyn Visitor, ctx: &mut Context) -> VResult {
         let result = v.start_function_call(self, ctx);
@@ -2494,6 +2618,7 @@ impl Visitable for FunctionArgument {
 
 #[derive(Clone, Default, Debug)]
 pub struct EventDeclaration {
+    pub attributes: Vec<Attribute>,
     pub identifier: Identifier,
     pub parameter_list: Vec<Parameter>,
 }
@@ -2522,6 +2647,7 @@ impl Parameter {
     }
     pub fn as_variable_declaration(&self) -> VariableDeclaration {
         return VariableDeclaration {
+            attributes: vec![],
             declaration_token: None,
             identifier: self.identifier.clone(),
             variable_type: self.type_assignment.clone(),
@@ -2560,6 +2686,7 @@ impl Visitable for Parameter {
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct VariableDeclaration {
+    pub attributes: Vec<Attribute>,
     pub declaration_token: Option<String>,
     pub identifier: Identifier,
     pub variable_type: Type,
@@ -2641,6 +2768,7 @@ pub enum Type {
     RangeType(RangeType),
     FixedSizedArrayType(FixedSizedArrayType),
     DictionaryType(DictionaryType),
+    FixedPointType(FixedPointType),
     UserDefinedType(Identifier),
     Solidity(SolidityType),
     SelfType,
@@ -2668,6 +2796,14 @@ impl Type {
         }
     }
 
+    pub fn is_array_type(&self) -> bool {
+        match self {
+            Type::ArrayType(_) => true,
+            Type::FixedSizedArrayType(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn is_dictionary_type(&self) -> bool {
         match self {
             Type::DictionaryType(_) => true,
@@ -2675,6 +2811,13 @@ impl Type {
         }
     }
 
+    pub fn is_fixed_point_type(&self) -> bool {
+        match self {
+            Type::FixedPointType(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn is_currency_type(&self) -> bool {
         let mut currency_type = self.clone();
 
@@ -2686,13 +2829,40 @@ impl Type {
         let identifier = identifier.token.clone();
         println!("Is resource type");
         println!("{:?}", identifier.clone());
-        if identifier.eq("Wei") || identifier.eq("Libra") || identifier.eq("LibraCoin.T") {
+        if identifier.eq("Wei")
+            || identifier.eq("Libra")
+            || identifier.eq("LibraCoin.T")
+            || identifier.eq("Currency")
+        {
             return true;
         } else {
             return false;
         }
     }
 
+    pub fn substitute_type(&mut self, identifier: &str, replacement: &TypeIdentifier) {
+        match self {
+            Type::UserDefinedType(i) => {
+                if i.token == identifier {
+                    i.token = replacement.clone();
+                }
+            }
+            Type::InoutType(i) => i.key_type.substitute_type(identifier, replacement),
+            Type::ArrayType(a) => a.key_type.substitute_type(identifier, replacement),
+            Type::FixedSizedArrayType(a) => a.key_type.substitute_type(identifier, replacement),
+            Type::DictionaryType(d) => {
+                d.key_type.substitute_type(identifier, replacement);
+                d.value_type.substitute_type(identifier, replacement);
+            }
+            _ => {}
+        }
+    }
+
+    // Currency is a stdlib trait, each target resolves it to its own coin
+    pub fn resolve_currency(&mut self, currency: &TypeIdentifier) {
+        self.substitute_type("Currency", currency)
+    }
+
     pub fn is_currency_original_type(&self) -> bool {
         let identifier = match self.clone() {
             Type::UserDefinedType(i) => i,
@@ -2713,6 +2883,7 @@ impl Type {
             Type::Address => return false,
             Type::Bool => return false,
             Type::String => return false,
+            Type::FixedPointType(_) => return false,
             _ => return true,
         }
     }
@@ -2753,6 +2924,7 @@ impl Type {
             Type::RangeType(r) => r.key_type.is_built_in_type(),
             Type::FixedSizedArrayType(a) => a.key_type.is_built_in_type(),
             Type::DictionaryType(_) => unimplemented!(),
+            Type::FixedPointType(_) => true,
             Type::UserDefinedType(_) => false,
             Type::Bool => true,
             Type::Int => true,
@@ -2771,10 +2943,19 @@ impl Type {
                 let name = i.key_type.name();
                 format!("$inout{name}", name = name)
             }
-            Type::ArrayType(_) => unimplemented!(),
+            Type::ArrayType(a) => format!("Array_{key}", key = a.key_type.name()),
             Type::RangeType(_) => unimplemented!(),
-            Type::FixedSizedArrayType(_) => unimplemented!(),
-            Type::DictionaryType(_) => unimplemented!(),
+            Type::FixedSizedArrayType(a) => format!(
+                "FixedArray_{key}_{size}",
+                key = a.key_type.name(),
+                size = a.size
+            ),
+            Type::DictionaryType(d) => format!(
+                "Dictionary_{key}_{value}",
+                key = d.key_type.name(),
+                value = d.value_type.name()
+            ),
+            Type::FixedPointType(f) => format!("Fixed_{decimals}", decimals = f.decimals),
             Type::UserDefinedType(i) => i.token.clone(),
             Type::Bool => format!("Bool"),
             Type::Int => format!("Int"),
@@ -2909,11 +3090,42 @@ impl Type {
     }
 }
 
+// Types are shown the way they are written in source, for diagnostics
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::QuartzType(q) => write!(f, "{:?}", q),
+            Type::InoutType(i) => write!(f, "inout {}", i.key_type),
+            Type::ArrayType(a) => write!(f, "[{}]", a.key_type),
+            Type::RangeType(r) => write!(f, "Range<{}>", r.key_type),
+            Type::FixedSizedArrayType(a) => write!(f, "{}[{}]", a.key_type, a.size),
+            Type::DictionaryType(d) => write!(f, "[{}: {}]", d.key_type, d.value_type),
+            Type::FixedPointType(p) => write!(f, "Fixed<{}>", p.decimals),
+            Type::UserDefinedType(i) => write!(f, "{}", i.token),
+            Type::Solidity(s) => write!(f, "{:?}", s),
+            Type::SelfType => write!(f, "Self"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Int => write!(f, "Int"),
+            Type::String => write!(f, "String"),
+            Type::Address => write!(f, "Address"),
+            Type::Error => write!(f, "<unknown>"),
+        }
+    }
+}
+
 impl Visitable for Type {
     fn visit(&mut self, v: &mut dyn Visitor, ctx: &mut Context) -> VResult {
-        v.start_type(self, ctx);
+        let result = v.start_type(self, ctx);
+        match result {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
 
-        v.finish_type(self, ctx);
+        let result = v.finish_type(self, ctx);
+        match result {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
 
         Ok(())
     }
@@ -3012,6 +3224,99 @@ pub struct FixedSizedArrayType {
     pub size: u64,
 }
 
+// A decimal number with a fixed number of digits after the point, stored as
+// an integer scaled by 10^decimals
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixedPointType {
+    pub decimals: u64,
+}
+
+impl FixedPointType {
+    pub const MAX_DECIMALS: u64 = 18;
+
+    pub fn scale(&self) -> u64 {
+        10u64.pow(self.decimals as u32)
+    }
+
+    // The literal written with exactly this type's number of decimals, or
+    // None if it is not a number or has more decimals than this type
+    pub fn literal(&self, literal: &Literal) -> Option<Literal> {
+        let (whole, fraction) = match literal {
+            Literal::IntLiteral(i) => (i.to_string(), "".to_string()),
+            Literal::FloatLiteral(f) => {
+                let parts: Vec<&str> = f.split('.').collect();
+                (parts[0].to_string(), parts[1].to_string())
+            }
+            _ => return None,
+        };
+        if fraction.len() as u64 > self.decimals {
+            return None;
+        }
+        let padding = "0".repeat((self.decimals - fraction.len() as u64) as usize);
+        Some(Literal::FloatLiteral(format!(
+            "{whole}.{fraction}{padding}",
+            whole = whole,
+            fraction = fraction,
+            padding = padding
+        )))
+    }
+
+    // The scaled integer a decimal literal is stored as. The TypeChecker
+    // rejects literals that do not fit, see `fits`.
+    pub fn raw_value(literal: &str) -> u64 {
+        match literal.replace(".", "").parse::<u64>() {
+            Ok(value) => value,
+            Err(_) => panic!("Fixed point literal {} is too large", literal),
+        }
+    }
+
+    pub fn fits(literal: &str) -> bool {
+        literal.replace(".", "").parse::<u64>().is_ok()
+    }
+
+    pub fn decimals_of(literal: &str) -> u64 {
+        literal.split('.').nth(1).map_or(0, |f| f.len() as u64)
+    }
+
+    // The scale a product or quotient of two Fixed values has to be corrected
+    // by. Fixed values can be scaled by an Int, but otherwise only combine
+    // with Fixed values of the same precision.
+    pub fn operation_scale(op: &BinOp, lhs: &Type, rhs: &Type) -> Result<Option<u64>, String> {
+        match (lhs, rhs) {
+            (Type::FixedPointType(l), Type::FixedPointType(r)) => {
+                if l.decimals != r.decimals {
+                    return Err(format!(
+                        "cannot combine {} and {} values without a cast",
+                        lhs, rhs
+                    ));
+                }
+                match op {
+                    BinOp::Times => Ok(Some(l.scale())),
+                    BinOp::Divide => Ok(Some(l.scale())),
+                    BinOp::Power
+                    | BinOp::OverflowingPlus
+                    | BinOp::OverflowingMinus
+                    | BinOp::OverflowingTimes => {
+                        Err(format!("{} is not supported for {} values", op, lhs))
+                    }
+                    _ => Ok(None),
+                }
+            }
+            (Type::FixedPointType(_), Type::Int) | (Type::Int, Type::FixedPointType(_)) => {
+                match op {
+                    BinOp::Times => Ok(None),
+                    BinOp::Divide if lhs.is_fixed_point_type() => Ok(None),
+                    _ => Err(format!(
+                        "cannot combine {} and {} values with {} without a cast",
+                        lhs, rhs, op
+                    )),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct InoutType {
     pub key_type: Box<Type>,
@@ -3029,10 +3334,43 @@ pub struct TypeAnnotation {
     pub type_assigned: Type,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Attribute {
     pub at_token: std::string::String,
     pub identifier_token: std::string::String,
+    pub arguments: Vec<std::string::String>,
+    pub line_info: LineInfo,
+}
+
+impl PartialEq for Attribute {
+    fn eq(&self, other: &Self) -> bool {
+        self.at_token == other.at_token
+            && self.identifier_token == other.identifier_token
+            && self.arguments == other.arguments
+    }
+}
+
+impl Attribute {
+    pub fn is_target(&self) -> bool {
+        match self.identifier_token.as_str() {
+            "ether" | "libra" | "target" => true,
+            _ => false,
+        }
+    }
+
+    // @allow(...), @warn(...) and @deny(...) set the level of the lints they name
+    pub fn is_lint(&self) -> bool {
+        matches!(self.identifier_token.as_str(), "allow" | "warn" | "deny")
+    }
+
+    // Names of the targets selected by @ether, @libra or @target(...)
+    pub fn targets(&self) -> Vec<std::string::String> {
+        match self.identifier_token.as_str() {
+            "ether" | "libra" => vec![self.identifier_token.clone()],
+            "target" => self.arguments.clone(),
+            _ => vec![],
+        }
+    }
 }
 
 pub fn is_redeclaration(identifier1: &Identifier, identifier2: &Identifier) -> bool {
@@ -3068,10 +3406,6 @@ pub fn mangle(string: String) -> String {
     format!("_{}", string)
 }
 
-pub fn mangle_dictionary(string: String) -> String {
-    format!("_dictionary_{}", string)
-}
-
 pub fn mangle_function(string: String, t: &TypeIdentifier, is_contract: bool) -> String {
     let func_type = if is_contract {
         "".to_string()
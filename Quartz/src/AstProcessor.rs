@@ -1,6 +1,12 @@
 use super::context::*;
 use super::environment::*;
+use super::CallGraph::*;
+use super::CallerProtectionChecker;
+use super::InitialisationChecker::*;
+use super::Linter;
 use super::MoveCodeGen;
+use super::MutationChecker::*;
+use super::ResourceChecker::*;
 use super::SemanticAnalysis::*;
 use super::SolidityCodeGen;
 use super::TypeAssigner::*;
@@ -8,61 +14,249 @@ use super::TypeChecker::*;
 use super::AST::*;
 use crate::MoveCodeGen::MovePreProcessor;
 use crate::SolidityCodeGen::{generate, SolidityPreProcessor};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
 
-pub fn process_ast(mut module: Module, environment: Environment, target: Target) {
-    let type_assigner = &mut TypeAssigner {};
-    let semantic_analysis = &mut SemanticAnalysis {};
-    let type_checker = &mut TypeChecker {};
-    let solidity_preprocessor = &mut SolidityPreProcessor::SolidityPreProcessor {};
-    let move_preprocessor = &mut MovePreProcessor::MovePreProcessor {};
+pub fn process_ast(mut module: Module, environment: Environment, target: Target) -> VResult {
     let context = &mut Context {
         environment,
         ..Default::default()
     };
 
-    let result = module.visit(type_assigner, context);
+    check(&mut module, context)?;
+    preprocess(&mut module, context, &target)?;
 
-    match result {
-        Ok(_) => {}
-        Err(e) => return,
+    match target {
+        Target::MoveSource => MoveCodeGen::generate_source(module, context),
+        Target::Move => MoveCodeGen::generate(module, context),
+        Target::Yul => SolidityCodeGen::generate_yul(module, context),
+        Target::Evm => SolidityCodeGen::generate_bytecode(module, context),
+        Target::Ether => SolidityCodeGen::generate(module, context),
     }
+}
 
-    let result = module.visit(semantic_analysis, context);
+// Runs the passes every target shares, stopping at the first that fails
+fn check(module: &mut Module, context: &mut Context) -> VResult {
+    let type_assigner = &mut TypeAssigner {};
+    let semantic_analysis = &mut SemanticAnalysis {};
+    let type_checker = &mut TypeChecker::new();
+    let resource_checker = &mut ResourceChecker::new();
 
-    match result {
-        Ok(_) => {}
-        Err(e) => return,
-    }
+    module.visit(type_assigner, context)?;
+    module.visit(semantic_analysis, context)?;
+    module.visit(type_checker, context)?;
+    module.visit(resource_checker, context)?;
 
-    let result = module.visit(type_checker, context);
+    let initialisation_checker = &mut InitialisationChecker::new();
+    module.visit(initialisation_checker, context)?;
+    initialisation_checker.check(&context.environment)?;
 
-    match result {
-        Ok(_) => {}
-        Err(e) => return,
+    let call_graph = &mut CallGraph::new();
+    module.visit(call_graph, context)?;
+
+    if !context.environment.call_graph_format.is_empty() {
+        write_call_graph(call_graph, &context.environment.call_graph_format);
     }
 
-    if let Target::Move = target {
-        let result = module.visit(move_preprocessor, context);
+    CallerProtectionChecker::check(call_graph, &context.environment)?;
 
-        match result {
-            Ok(_) => {}
-            Err(e) => return,
-        }
+    let mutation_checker = &mut MutationChecker::new();
+    module.visit(mutation_checker, context)?;
+    mutation_checker.check(call_graph, &context.environment)?;
+
+    Linter::lint(module, context)?;
+    Linter::remove_unreachable_code(module, context)
+}
 
-        let result = MoveCodeGen::generate(module, context);
+// Lowers the checked module for the code generator of the target
+fn preprocess(module: &mut Module, context: &mut Context, target: &Target) -> VResult {
+    if target.is_move() {
+        let move_preprocessor = &mut MovePreProcessor::MovePreProcessor {};
+        module.visit(move_preprocessor, context)
     } else {
-        let result = module.visit(solidity_preprocessor, context);
+        let solidity_preprocessor = &mut SolidityPreProcessor::SolidityPreProcessor {};
+        module.visit(solidity_preprocessor, context)
+    }
+}
 
-        match result {
-            Ok(_) => {}
-            Err(e) => return,
-        }
+// Prints the call graph and writes it next to the generated code
+fn write_call_graph(call_graph: &CallGraph, format: &str) {
+    let graph = match format {
+        "dot" => call_graph.to_dot(),
+        _ => call_graph.to_json(),
+    };
+    print!("{}", graph);
+
+    let path = &format!("output/call_graph.{ext}", ext = format);
+    let path = Path::new(path);
+    let display = path.display();
 
-        let result = SolidityCodeGen::generate(module, context);
+    let mut file = match File::create(&path) {
+        Err(why) => panic!("couldn't create {}: {}", display, why),
+        Ok(file) => file,
+    };
+
+    match file.write_all(graph.as_bytes()) {
+        Err(why) => panic!("couldn't write to {}: {}", display, why),
+        Ok(_) => println!("successfully wrote to {}", display),
     }
 }
 
 pub enum Target {
     Move,
+    MoveSource,
     Ether,
+    Yul,
+    Evm,
+}
+
+impl Target {
+    pub fn is_move(&self) -> bool {
+        match self {
+            Target::Move => true,
+            Target::MoveSource => true,
+            _ => false,
+        }
+    }
+
+    // Code without target attributes is compiled for every target
+    pub fn is_selected_by(&self, attributes: &[Attribute]) -> bool {
+        let targets: Vec<String> = attributes.iter().flat_map(|a| a.targets()).collect();
+        targets.is_empty() || targets.iter().any(|t| self.is_named(t))
+    }
+
+    // @ether and @libra select every target of their chain
+    fn is_named(&self, name: &str) -> bool {
+        match name {
+            "ether" => !self.is_move(),
+            "libra" => self.is_move(),
+            "move" => match self {
+                Target::MoveSource => true,
+                _ => false,
+            },
+            "yul" => match self {
+                Target::Yul => true,
+                _ => false,
+            },
+            "evm" => match self {
+                Target::Evm => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    pub fn is_target_name(name: &str) -> bool {
+        match name {
+            "ether" | "libra" | "move" | "yul" | "evm" => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{check, preprocess, Target};
+    use crate::context::Context;
+    use crate::Linter::LintLevel;
+    use crate::MoveCodeGen;
+    use crate::Parser::parse_program;
+    use crate::SolidityCodeGen;
+    use crate::SolidityCodeGen::EVMAssembler;
+    use crate::AST::{CodeGen, Module};
+    use std::collections::HashSet;
+
+    // Parses a program with the standard library of the target and runs the
+    // shared passes, returning the message of the first pass that fails
+    pub fn checked(program: &str, target: &Target) -> Result<(Module, Context), String> {
+        checked_with_lint_levels(program, target, &[])
+    }
+
+    // A checked program with lint levels given as if on the command line
+    pub fn checked_with_lint_levels(
+        program: &str,
+        target: &Target,
+        lint_levels: &[(&str, LintLevel)],
+    ) -> Result<(Module, Context), String> {
+        let (module, mut environment) = parse_program(&crate::stdlib(target), program, target);
+        let mut module = module.ok_or_else(|| "Parse Error".to_string())?;
+        for (lint, level) in lint_levels {
+            environment.lint_levels.insert(lint.to_string(), *level);
+        }
+        let mut context = Context {
+            environment,
+            ..Default::default()
+        };
+        check(&mut module, &mut context).map_err(|e| e.to_string())?;
+        Ok((module, context))
+    }
+
+    // A checked program after the preprocessor of its target
+    pub fn preprocessed(program: &str, target: &Target) -> Result<(Module, Context), String> {
+        let (mut module, mut context) = checked(program, target)?;
+        preprocess(&mut module, &mut context, target).map_err(|e| e.to_string())?;
+        Ok((module, context))
+    }
+
+    // The code generated for every contract of a program, in the order the
+    // contracts are generated
+    pub fn compile(program: &str, target: Target) -> Result<String, String> {
+        let (module, context) = checked(program, &target)?;
+        generate(module, context, target)
+    }
+
+    // The code generated for every contract of a checked program
+    pub fn generate(
+        mut module: Module,
+        mut context: Context,
+        target: Target,
+    ) -> Result<String, String> {
+        preprocess(&mut module, &mut context, &target).map_err(|e| e.to_string())?;
+
+        let mut code = vec![];
+        match target {
+            Target::Move | Target::MoveSource => {
+                let contracts =
+                    MoveCodeGen::contracts(&module, &context).map_err(|e| e.to_string())?;
+                let coin = context.environment.coin_module.clone();
+                let mut linked = HashSet::new();
+                for contract in contracts {
+                    let mut ir = CodeGen {
+                        code: "".to_string(),
+                        indent_level: 0,
+                        indent_size: 2,
+                    };
+                    ir.add(contract.generate());
+                    if let Target::MoveSource = target {
+                        code.push(MoveCodeGen::MoveSource::translate(
+                            &ir.code,
+                            &coin,
+                            &mut linked,
+                        ));
+                    } else {
+                        code.push(ir.code);
+                    }
+                }
+            }
+            Target::Ether => {
+                for contract in SolidityCodeGen::contracts(&module, &context) {
+                    code.push(contract.generate());
+                }
+            }
+            Target::Yul => {
+                for contract in SolidityCodeGen::contracts(&module, &context) {
+                    code.push(contract.generate_yul_object());
+                }
+            }
+            Target::Evm => {
+                for contract in SolidityCodeGen::contracts(&module, &context) {
+                    let (_, runtime) = EVMAssembler::assemble(&contract.yul_object())?;
+                    code.push(hex::encode(runtime));
+                }
+            }
+        }
+        Ok(code.join("\n"))
+    }
 }
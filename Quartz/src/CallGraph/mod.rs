@@ -0,0 +1,605 @@
+use super::context::*;
+use super::environment::*;
+use super::visitor::*;
+use super::AST::*;
+use std::fmt;
+
+// A function or special declaration, identified by its enclosing type, its name
+// and where it is declared, so that overloads are kept apart
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FunctionKey {
+    pub enclosing: TypeIdentifier,
+    pub name: String,
+    pub line_info: LineInfo,
+}
+
+impl FunctionKey {
+    pub fn function(enclosing: &TypeIdentifier, declaration: &FunctionDeclaration) -> FunctionKey {
+        FunctionKey {
+            enclosing: enclosing.clone(),
+            name: declaration.head.identifier.token.clone(),
+            line_info: declaration.head.identifier.line_info.clone(),
+        }
+    }
+
+    // Specials have no identifier of their own, so their line tells
+    // initialisers apart
+    pub fn special(enclosing: &TypeIdentifier, head: &SpecialSignatureDeclaration) -> FunctionKey {
+        FunctionKey {
+            enclosing: enclosing.clone(),
+            name: head.special_token.clone(),
+            line_info: head.line_info.clone(),
+        }
+    }
+}
+
+impl fmt::Display for FunctionKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.enclosing, self.name)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CallGraphFunction {
+    pub key: FunctionKey,
+    pub caller_protections: Vec<CallerProtection>,
+    pub is_public: bool,
+    pub is_special: bool,
+    // Functions of traits that are declared without a body
+    pub is_signature: bool,
+    // Functions that implement a signature of a trait the type conforms to
+    pub implements_signature: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct CallSite {
+    pub caller: FunctionKey,
+    pub callee: FunctionKey,
+    pub line_info: LineInfo,
+    // Calls made with try! or try?
+    pub is_attempt: bool,
+    // Calls made with call to a function of an external trait
+    pub is_external: bool,
+}
+
+// The functions of the contract behaviours, structs, assets and traits of a
+// module and the calls between them, resolved with
+// Environment::match_function_call
+#[derive(Debug, Default)]
+pub struct CallGraph {
+    pub functions: Vec<CallGraphFunction>,
+    pub calls: Vec<CallSite>,
+    // Set by an attempt expression until its function call is visited
+    is_attempt: bool,
+}
+
+impl CallGraph {
+    pub fn new() -> CallGraph {
+        Default::default()
+    }
+
+    pub fn function(&self, key: &FunctionKey) -> Option<&CallGraphFunction> {
+        self.functions.iter().find(|f| &f.key == key)
+    }
+
+    pub fn calls_to(&self, key: &FunctionKey) -> Vec<&CallSite> {
+        self.calls.iter().filter(|c| &c.callee == key).collect()
+    }
+
+    pub fn calls_from(&self, key: &FunctionKey) -> Vec<&CallSite> {
+        self.calls.iter().filter(|c| &c.caller == key).collect()
+    }
+
+    // The functions a function may call, directly or through others
+    pub fn reachable_from(&self, key: &FunctionKey) -> Vec<FunctionKey> {
+        let mut reached: Vec<FunctionKey> = vec![];
+        let mut worklist = vec![key.clone()];
+        while let Some(current) = worklist.pop() {
+            for call in self.calls_from(&current) {
+                if !reached.contains(&call.callee) {
+                    reached.push(call.callee.clone());
+                    worklist.push(call.callee.clone());
+                }
+            }
+        }
+        reached
+    }
+
+    // The groups of functions that call each other, directly or through
+    // others, in the order they are declared
+    pub fn recursive_functions(&self) -> Vec<Vec<FunctionKey>> {
+        let reachable: Vec<(FunctionKey, Vec<FunctionKey>)> = self
+            .functions
+            .iter()
+            .map(|f| (f.key.clone(), self.reachable_from(&f.key)))
+            .collect();
+
+        let mut cycles: Vec<Vec<FunctionKey>> = vec![];
+        for (key, reached) in &reachable {
+            if !reached.contains(key) || cycles.iter().any(|c| c.contains(key)) {
+                continue;
+            }
+            let cycle = reachable
+                .iter()
+                .filter(|(other, other_reached)| {
+                    other == key || (reached.contains(other) && other_reached.contains(key))
+                })
+                .map(|(other, _)| other.clone())
+                .collect();
+            cycles.push(cycle);
+        }
+        cycles
+    }
+
+    // Private functions that no public function, initialiser or fallback may
+    // call, leaving out the standard library
+    pub fn unreachable_private_functions(&self) -> Vec<&CallGraphFunction> {
+        let mut reached: Vec<FunctionKey> = vec![];
+        for function in &self.functions {
+            if function.is_public
+                || function.is_special
+                || function.is_signature
+                || function.implements_signature
+            {
+                reached.push(function.key.clone());
+                reached.extend(self.reachable_from(&function.key));
+            }
+        }
+
+        self.functions
+            .iter()
+            .filter(|f| !reached.contains(&f.key) && !Environment::is_stdlib_type(&f.key.enclosing))
+            .collect()
+    }
+
+    // The functions of the program and the standard library functions it calls
+    fn exported_functions(&self) -> Vec<&CallGraphFunction> {
+        self.functions
+            .iter()
+            .filter(|f| {
+                !Environment::is_stdlib_type(&f.key.enclosing)
+                    || self
+                        .calls_to(&f.key)
+                        .iter()
+                        .any(|c| !Environment::is_stdlib_type(&c.caller.enclosing))
+            })
+            .collect()
+    }
+
+    // Initialisers of external traits are not declared, so their calls are left out
+    fn exported_calls(&self) -> Vec<&CallSite> {
+        self.calls
+            .iter()
+            .filter(|c| !Environment::is_stdlib_type(&c.caller.enclosing) && self.function(&c.callee).is_some())
+            .collect()
+    }
+
+    pub fn to_dot(&self) -> String {
+        let recursive: Vec<FunctionKey> = self.recursive_functions().concat();
+        let unreachable: Vec<FunctionKey> = self
+            .unreachable_private_functions()
+            .iter()
+            .map(|f| f.key.clone())
+            .collect();
+
+        let mut lines = vec![
+            "digraph CallGraph {".to_string(),
+            "  node [shape=box];".to_string(),
+        ];
+        for function in self.exported_functions() {
+            let mut attributes = vec![format!("label=\"{}\"", function.key)];
+            if function.is_public || function.is_special {
+                attributes.push("style=bold".to_string());
+            }
+            if unreachable.contains(&function.key) {
+                attributes.push("style=dashed".to_string());
+                attributes.push("color=gray".to_string());
+            }
+            if recursive.contains(&function.key) {
+                attributes.push("color=red".to_string());
+            }
+            lines.push(format!(
+                "  \"{}\" [{}];",
+                node_id(&function.key),
+                attributes.join(", ")
+            ));
+        }
+        for call in self.exported_calls() {
+            let mut attributes = vec![format!("label=\"{}\"", call.line_info.line)];
+            if call.is_attempt {
+                attributes.push("style=dashed".to_string());
+            }
+            if call.is_external {
+                attributes.push("style=dotted".to_string());
+            }
+            lines.push(format!(
+                "  \"{}\" -> \"{}\" [{}];",
+                node_id(&call.caller),
+                node_id(&call.callee),
+                attributes.join(", ")
+            ));
+        }
+        lines.push("}".to_string());
+        lines.join("\n") + "\n"
+    }
+
+    pub fn to_json(&self) -> String {
+        let recursion = self.recursive_functions();
+        let recursive: Vec<FunctionKey> = recursion.concat();
+        let unreachable: Vec<FunctionKey> = self
+            .unreachable_private_functions()
+            .iter()
+            .map(|f| f.key.clone())
+            .collect();
+
+        let functions: Vec<String> = self
+            .exported_functions()
+            .iter()
+            .map(|f| {
+                format!(
+                    "    {{ \"id\": \"{id}\", \"type\": \"{enclosing}\", \"name\": \"{name}\", \"line\": {line}, \"public\": {public}, \"special\": {special}, \"recursive\": {recursive}, \"reachable\": {reachable} }}",
+                    id = node_id(&f.key),
+                    enclosing = f.key.enclosing,
+                    name = f.key.name,
+                    line = f.key.line_info.line,
+                    public = f.is_public,
+                    special = f.is_special,
+                    recursive = recursive.contains(&f.key),
+                    reachable = !unreachable.contains(&f.key)
+                )
+            })
+            .collect();
+
+        let calls: Vec<String> = self
+            .exported_calls()
+            .iter()
+            .map(|c| {
+                format!(
+                    "    {{ \"caller\": \"{caller}\", \"callee\": \"{callee}\", \"line\": {line}, \"attempt\": {attempt}, \"external\": {external} }}",
+                    caller = node_id(&c.caller),
+                    callee = node_id(&c.callee),
+                    line = c.line_info.line,
+                    attempt = c.is_attempt,
+                    external = c.is_external
+                )
+            })
+            .collect();
+
+        let recursion: Vec<String> = recursion
+            .iter()
+            .map(|cycle| {
+                let ids: Vec<String> = cycle
+                    .iter()
+                    .map(|k| format!("\"{}\"", node_id(k)))
+                    .collect();
+                format!("    [{}]", ids.join(", "))
+            })
+            .collect();
+
+        let unreachable: Vec<String> = unreachable
+            .iter()
+            .map(|k| format!("    \"{}\"", node_id(k)))
+            .collect();
+
+        format!(
+            "{{\n  \"functions\": [\n{functions}\n  ],\n  \"calls\": [\n{calls}\n  ],\n  \"recursion\": [\n{recursion}\n  ],\n  \"unreachable\": [\n{unreachable}\n  ]\n}}\n",
+            functions = functions.join(",\n"),
+            calls = calls.join(",\n"),
+            recursion = recursion.join(",\n"),
+            unreachable = unreachable.join(",\n")
+        )
+    }
+
+    fn add_call(&mut self, call: &FunctionCall, is_attempt: bool, ctx: &Context) {
+        if Environment::is_runtime_function_call(call) {
+            return;
+        }
+
+        // Arguments of an external call are internal calls of their own
+        let external_call = match &ctx.ExternalCallContext {
+            Some(external) if ctx.IsExternalFunctionCall => {
+                Some(&*external.function_call.rhs_expression)
+            }
+            _ => None,
+        };
+        let is_external = match external_call {
+            Some(Expression::FunctionCall(f)) => {
+                f.identifier.line_info == call.identifier.line_info
+            }
+            _ => false,
+        };
+
+        let caller = match caller_key(ctx) {
+            Some(caller) => caller,
+            None => return,
+        };
+
+        let enclosing = match &call.identifier.enclosing_type {
+            Some(enclosing) => enclosing.clone(),
+            None => enclosing_type(ctx).unwrap_or_default(),
+        };
+        let caller_protections = match &ctx.ContractBehaviourDeclarationContext {
+            Some(behaviour) => behaviour.caller_protections.clone(),
+            None => vec![],
+        };
+        let scope = ctx.ScopeContext.clone().unwrap_or_default();
+
+        let result = ctx.environment.match_function_call(
+            call.clone(),
+            &enclosing,
+            caller_protections,
+            scope,
+        );
+        let callee = match result {
+            FunctionCallMatchResult::MatchedFunction(f) => {
+                FunctionKey::function(&enclosing, &f.declaration)
+            }
+            FunctionCallMatchResult::MatchedFunctionWithoutCaller(c) => {
+                match c.candidates.first() {
+                    Some(CallableInformation::FunctionInformation(f)) => {
+                        FunctionKey::function(&enclosing, &f.declaration)
+                    }
+                    _ => return,
+                }
+            }
+            FunctionCallMatchResult::MatchedGlobalFunction(f) => {
+                FunctionKey::function(&"Quartz_Global".to_string(), &f.declaration)
+            }
+            FunctionCallMatchResult::MatchedInitializer(s) => {
+                FunctionKey::special(&call.identifier.token, &s.declaration.head)
+            }
+            FunctionCallMatchResult::MatchedFallback(_) => return,
+            // Arguments of external calls are typed in the external trait, so
+            // a function that is not overloaded is matched by name
+            FunctionCallMatchResult::Failure(_) if is_external => {
+                let functions = ctx
+                    .environment
+                    .types
+                    .get(&enclosing)
+                    .and_then(|t| t.functions.get(&call.identifier.token));
+                match functions {
+                    Some(functions) if functions.len() == 1 => {
+                        FunctionKey::function(&enclosing, &functions[0].declaration)
+                    }
+                    _ => return,
+                }
+            }
+            FunctionCallMatchResult::Failure(_) => return,
+        };
+
+        self.calls.push(CallSite {
+            caller,
+            callee,
+            line_info: call.identifier.line_info.clone(),
+            is_attempt,
+            is_external,
+        });
+    }
+}
+
+// Overloads share a name, so nodes are told apart by the line they are declared on
+fn node_id(key: &FunctionKey) -> String {
+    format!("{}:{}", key, key.line_info.line)
+}
+
+// Default implementations and signatures of traits belong to the trait
+fn enclosing_type(ctx: &Context) -> Option<TypeIdentifier> {
+    if let Some(enclosing) = ctx.enclosing_type_identifier() {
+        return Some(enclosing.token);
+    }
+    ctx.TraitDeclarationContext
+        .as_ref()
+        .map(|t| t.identifier.token.clone())
+}
+
+fn caller_key(ctx: &Context) -> Option<FunctionKey> {
+    let enclosing = enclosing_type(ctx)?;
+    if let Some(function) = &ctx.FunctionDeclarationContext {
+        return Some(FunctionKey::function(&enclosing, &function.declaration));
+    }
+    if let Some(special) = &ctx.SpecialDeclarationContext {
+        return Some(FunctionKey::special(&enclosing, &special.declaration.head));
+    }
+    None
+}
+
+fn implements_signature(
+    enclosing: &TypeIdentifier,
+    declaration: &FunctionDeclaration,
+    ctx: &Context,
+) -> bool {
+    let conformances = match ctx.environment.types.get(enclosing) {
+        Some(t) => &t.conformances,
+        None => return false,
+    };
+    conformances.iter().any(|c| {
+        c.functions
+            .get(&declaration.head.identifier.token)
+            .is_some_and(|functions| functions.iter().any(|f| f.is_signature))
+    })
+}
+
+fn declared_caller_protections(ctx: &Context) -> Vec<CallerProtection> {
+    match &ctx.ContractBehaviourDeclarationContext {
+        Some(behaviour) => behaviour.caller_protections.clone(),
+        None => vec![],
+    }
+}
+
+impl Visitor for CallGraph {
+    fn start_function_declaration(
+        &mut self,
+        _t: &mut FunctionDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        if let Some(enclosing) = enclosing_type(_ctx) {
+            self.functions.push(CallGraphFunction {
+                key: FunctionKey::function(&enclosing, _t),
+                caller_protections: declared_caller_protections(_ctx),
+                is_public: _t.is_public(),
+                is_special: false,
+                is_signature: false,
+                implements_signature: implements_signature(&enclosing, _t, _ctx),
+            });
+        }
+        Ok(())
+    }
+
+    fn start_function_signature_declaration(
+        &mut self,
+        _t: &mut FunctionSignatureDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        if let Some(t) = &_ctx.TraitDeclarationContext {
+            let key = FunctionKey {
+                enclosing: t.identifier.token.clone(),
+                name: _t.identifier.token.clone(),
+                line_info: _t.identifier.line_info.clone(),
+            };
+            // The heads of default implementations are visited too
+            if self.function(&key).is_some() {
+                return Ok(());
+            }
+            self.functions.push(CallGraphFunction {
+                key,
+                caller_protections: vec![],
+                is_public: _t.is_public(),
+                is_special: false,
+                is_signature: true,
+                implements_signature: false,
+            });
+        }
+        Ok(())
+    }
+
+    fn start_special_declaration(
+        &mut self,
+        _t: &mut SpecialDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        if let Some(enclosing) = enclosing_type(_ctx) {
+            self.functions.push(CallGraphFunction {
+                key: FunctionKey::special(&enclosing, &_t.head),
+                caller_protections: declared_caller_protections(_ctx),
+                is_public: _t.is_public(),
+                is_special: true,
+                is_signature: false,
+                implements_signature: false,
+            });
+        }
+        Ok(())
+    }
+
+    fn finish_module(&mut self, _t: &mut Module, _ctx: &mut Context) -> VResult {
+        // Default implementations are matched through the types conforming to
+        // the trait, so their calls are moved to the trait's function
+        let functions = &self.functions;
+        for call in &mut self.calls {
+            if functions.iter().any(|f| f.key == call.callee) {
+                continue;
+            }
+            let declared = functions.iter().find(|f| {
+                !f.is_special
+                    && f.key.name == call.callee.name
+                    && f.key.line_info == call.callee.line_info
+            });
+            if let Some(declared) = declared {
+                call.callee = declared.key.clone();
+            }
+        }
+        Ok(())
+    }
+
+    fn start_function_call(&mut self, _t: &mut FunctionCall, _ctx: &mut Context) -> VResult {
+        let is_attempt = std::mem::take(&mut self.is_attempt);
+        self.add_call(_t, is_attempt, _ctx);
+        Ok(())
+    }
+
+    fn start_attempt_expression(
+        &mut self,
+        _t: &mut AttemptExpression,
+        _ctx: &mut Context,
+    ) -> VResult {
+        self.is_attempt = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::CallGraph;
+    use crate::AstProcessor::tests::checked;
+    use crate::AstProcessor::Target;
+    use crate::AST::Visitable;
+
+    const PROGRAM: &str = "
+        struct Point {
+          var x: Int = 0
+
+          init() {}
+
+          init(x: Int) {
+            self.x = x
+          }
+        }
+        contract Parity {
+          var last: Int = 0
+        }
+        Parity :: caller <- (any) {
+          public init() {}
+
+          public func isEven(n: Int) -> Bool {
+            let p: Point = Point(x: n)
+            return even(n: p.x)
+          }
+
+          func even(n: Int) -> Bool {
+            if n == 0 {
+              return true
+            }
+            return odd(n: n - 1)
+          }
+
+          func odd(n: Int) -> Bool {
+            if n == 0 {
+              return false
+            }
+            return even(n: n - 1)
+          }
+
+          func unused() -> Int {
+            return 0
+          }
+        }";
+
+    fn graph(program: &str) -> CallGraph {
+        let (mut module, mut context) = checked(program, &Target::Ether).unwrap();
+        let graph = &mut CallGraph::new();
+        module.visit(graph, &mut context).unwrap();
+        std::mem::take(graph)
+    }
+
+    #[test]
+    fn test_call_graph() {
+        let json = graph(PROGRAM).to_json();
+        // Initialisers are told apart by the line they are declared on
+        assert!(json.contains("{ \"caller\": \"Parity.isEven:17\", \"callee\": \"Point.init:7\", \"line\": 18"));
+        assert!(!json.contains("Point.init:0"));
+        assert!(json.contains("{ \"caller\": \"Parity.isEven:17\", \"callee\": \"Parity.even:22\", \"line\": 19"));
+        assert!(json.contains("\"recursion\": [\n    [\"Parity.even:22\", \"Parity.odd:29\"]\n  ]"));
+        assert!(json.contains("\"unreachable\": [\n    \"Parity.unused:36\"\n  ]"));
+    }
+
+    #[test]
+    fn test_call_graph_dot() {
+        let dot = graph(PROGRAM).to_dot();
+        assert!(dot.contains("\"Parity.isEven:17\" [label=\"Parity.isEven\", style=bold];"));
+        assert!(dot.contains("\"Parity.odd:29\" [label=\"Parity.odd\", color=red];"));
+        assert!(dot.contains(
+            "\"Parity.unused:36\" [label=\"Parity.unused\", style=dashed, color=gray];"
+        ));
+        assert!(dot.contains("\"Parity.odd:29\" -> \"Parity.even:22\" [label=\"33\"];"));
+    }
+}
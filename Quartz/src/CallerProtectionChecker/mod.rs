@@ -0,0 +1,197 @@
+use super::environment::*;
+use super::CallGraph::*;
+use super::AST::*;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+// Proves that every internal call between contract functions is made only by
+// callers the callee accepts. The protections a function may run under are the
+// ones of its behaviour block when it can be called from outside, and those of
+// its callers otherwise. Calls that cannot be proven are errors.
+pub fn check(graph: &CallGraph, environment: &Environment) -> VResult {
+    let is_contract_function = |key: &FunctionKey| environment.is_contract_declared(&key.enclosing);
+
+    // The call through which a function was first reached with a protection,
+    // or None for an entry point
+    let mut reached: HashMap<(FunctionKey, String), Option<usize>> = HashMap::new();
+    let mut worklist: Vec<(FunctionKey, CallerProtection)> = vec![];
+
+    for function in &graph.functions {
+        if !is_contract_function(&function.key) {
+            continue;
+        }
+
+        let is_called_internally = graph
+            .calls_to(&function.key)
+            .iter()
+            .any(|c| is_contract_function(&c.caller));
+        if function.is_public || function.is_special || !is_called_internally {
+            for protection in &function.caller_protections {
+                let state = (function.key.clone(), protection.name());
+                if let Entry::Vacant(entry) = reached.entry(state) {
+                    entry.insert(None);
+                    worklist.push((function.key.clone(), protection.clone()));
+                }
+            }
+        }
+    }
+
+    let mut violations: Vec<(usize, CallerProtection)> = vec![];
+    while let Some((key, protection)) = worklist.pop() {
+        for (index, call) in graph.calls.iter().enumerate() {
+            if call.caller != key || !is_contract_function(&call.callee) {
+                continue;
+            }
+            let callee = match graph.function(&call.callee) {
+                Some(callee) => callee,
+                None => continue,
+            };
+
+            let is_accepted = environment.compatible_caller_protections(
+                vec![protection.clone()],
+                callee.caller_protections.clone(),
+            );
+            if is_accepted {
+                let state = (call.callee.clone(), protection.name());
+                if let Entry::Vacant(entry) = reached.entry(state) {
+                    entry.insert(Some(index));
+                    worklist.push((call.callee.clone(), protection.clone()));
+                }
+            } else if !violations
+                .iter()
+                .any(|(i, p)| *i == index && p.name() == protection.name())
+            {
+                violations.push((index, protection.clone()));
+            }
+        }
+    }
+
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    violations.sort_by_key(|(index, _)| *index);
+    let mut messages = vec![];
+    for (index, protection) in violations {
+        let call = &graph.calls[index];
+        let required = graph
+            .function(&call.callee)
+            .map(|f| protection_names(&f.caller_protections))
+            .unwrap_or_default();
+        let mut lines = vec![format!(
+            "Caller protection error on line {}: {} may be called by ({}) but calls {} which requires ({})",
+            call.line_info.line,
+            call.caller,
+            protection.name(),
+            call.callee,
+            required
+        )];
+
+        for line in call_chain(graph, &reached, &call.caller, &protection) {
+            lines.push(format!("    {}", line));
+        }
+        lines.push(format!(
+            "    {} calls {} on line {}",
+            call.caller, call.callee, call.line_info.line
+        ));
+
+        let message = lines.join("\n");
+        println!("{}", message);
+        messages.push(message);
+    }
+    Err(Box::from(messages.join("\n")))
+}
+
+// Follows the calls back to the entry point the protection came from
+fn call_chain(
+    graph: &CallGraph,
+    reached: &HashMap<(FunctionKey, String), Option<usize>>,
+    function: &FunctionKey,
+    protection: &CallerProtection,
+) -> Vec<String> {
+    let mut chain = vec![];
+    let mut current = function.clone();
+    while let Some(Some(index)) = reached.get(&(current.clone(), protection.name())) {
+        let call = &graph.calls[*index];
+        chain.push(format!(
+            "{} calls {} on line {}",
+            call.caller, call.callee, call.line_info.line
+        ));
+        current = call.caller.clone();
+    }
+    chain.push(format!(
+        "{} is callable by ({})",
+        current,
+        protection.name()
+    ));
+    chain.reverse();
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::AstProcessor::tests::checked;
+    use crate::AstProcessor::Target;
+
+    // A vault whose owner may reset it, with the bodies of a function anyone
+    // may call and of one only the owner may call
+    fn vault(bump: &str, reset_twice: &str) -> String {
+        format!(
+            "
+            contract Vault {{
+              var owner: Address = 0x0000000000000000000000000000000000000001
+              var total: Int = 0
+            }}
+            Vault :: caller <- (any) {{
+              public init() {{}}
+
+              public func bump() mutates (total) {{
+                {}
+              }}
+
+              func add() mutates (total) {{
+                total += 1
+              }}
+            }}
+            Vault :: (owner) {{
+              public func reset() mutates (total) {{
+                total = 0
+              }}
+
+              public func resetTwice() mutates (total) {{
+                {}
+              }}
+            }}
+            ",
+            bump, reset_twice
+        )
+    }
+
+    #[test]
+    fn test_rejected_call() {
+        let error = checked(&vault("reset()", "add()"), &Target::Ether)
+            .err()
+            .unwrap();
+        let lines: Vec<&str> = error.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "Caller protection error on line 10: Vault.bump may be called by (any) but calls Vault.reset which requires (owner)",
+                "    Vault.bump is callable by (any)",
+                "    Vault.bump calls Vault.reset on line 10",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compatible_caller() {
+        // Owners may call what anyone may
+        assert!(checked(&vault("add()", "add()"), &Target::Ether).is_ok());
+    }
+
+    #[test]
+    fn test_same_block() {
+        assert!(checked(&vault("add()", "reset()"), &Target::Ether).is_ok());
+    }
+}
@@ -0,0 +1,591 @@
+use super::context::*;
+use super::environment::*;
+use super::visitor::*;
+use super::AST::*;
+use std::collections::HashMap;
+
+// Checks that every initialiser of a contract, struct or asset assigns each
+// property without a default value on every path, and that no property is read,
+// directly or by a function of the type it calls, before it has been assigned.
+pub struct InitialisationChecker {
+    functions: Vec<(TypeIdentifier, FunctionDeclaration)>,
+    initialisers: Vec<(TypeIdentifier, SpecialDeclaration)>,
+}
+
+impl InitialisationChecker {
+    pub fn new() -> InitialisationChecker {
+        InitialisationChecker {
+            functions: vec![],
+            initialisers: vec![],
+        }
+    }
+
+    pub fn check(&self, environment: &Environment) -> VResult {
+        // The properties each function assigns on every path, including through
+        // the calls it makes on the same type. Overloads share a name, so only
+        // what all of them assign counts.
+        let mut assigns: HashMap<(TypeIdentifier, String), Vec<String>> = HashMap::new();
+        let no_reads = HashMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            let mut next: HashMap<(TypeIdentifier, String), Vec<String>> = HashMap::new();
+            for (enclosing, function) in &self.functions {
+                let mut flow =
+                    InitialisationFlow::new(environment, enclosing.clone(), &no_reads, &assigns);
+                flow.function(&function.head.parameters, &function.body);
+                let assigned = flow.assigned_on_exit.unwrap_or_default();
+
+                let key = (enclosing.clone(), function.head.identifier.token.clone());
+                match next.get_mut(&key) {
+                    Some(properties) => properties.retain(|p| assigned.contains(p)),
+                    None => {
+                        next.insert(key, assigned);
+                    }
+                }
+            }
+            for (key, properties) in &next {
+                let previous = assigns.get(key).map_or(0, |p| p.len());
+                if properties.len() != previous {
+                    changed = true;
+                }
+            }
+            assigns = next;
+        }
+
+        // The properties each function reads before assigning them itself,
+        // propagated through the calls it makes on the same type until nothing
+        // changes
+        let mut reads: HashMap<(TypeIdentifier, String), Vec<String>> = HashMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (enclosing, function) in &self.functions {
+                let mut flow =
+                    InitialisationFlow::new(environment, enclosing.clone(), &reads, &assigns);
+                flow.function(&function.head.parameters, &function.body);
+                let flow_reads = flow.reads;
+
+                let key = (enclosing.clone(), function.head.identifier.token.clone());
+                let function_reads = reads.entry(key).or_default();
+                for (property, _, _) in flow_reads {
+                    if !function_reads.contains(&property) {
+                        function_reads.push(property);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        let mut diagnostics = vec![];
+        for (enclosing, initialiser) in &self.initialisers {
+            if Environment::is_stdlib_type(enclosing)
+                || !(environment.is_contract_declared(enclosing)
+                    || environment.is_struct_declared(enclosing)
+                    || environment.is_asset_declared(enclosing))
+            {
+                continue;
+            }
+
+            let mut flow =
+                InitialisationFlow::new(environment, enclosing.clone(), &reads, &assigns);
+            flow.function(&initialiser.head.parameters, &initialiser.body);
+
+            let location = format!("{}.{}", enclosing, initialiser.head.special_token);
+            for (property, line_info, callee) in &flow.reads {
+                let diagnostic = match callee {
+                    Some(callee) => format!(
+                        "Property {} is read by the call to {} on line {} before it is assigned in {}",
+                        property, callee, line_info.line, location
+                    ),
+                    None => format!(
+                        "Property {} is read on line {} before it is assigned in {}",
+                        property, line_info.line, location
+                    ),
+                };
+                if !diagnostics.contains(&diagnostic) {
+                    diagnostics.push(diagnostic);
+                }
+            }
+
+            for property in &flow.unassigned {
+                let diagnostic = format!(
+                    "Property {} is not assigned on every path through {} on line {}",
+                    property, location, initialiser.head.line_info.line
+                );
+                if !diagnostics.contains(&diagnostic) {
+                    diagnostics.push(diagnostic);
+                }
+            }
+        }
+
+        if diagnostics.is_empty() {
+            return Ok(());
+        }
+
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic);
+        }
+        Err(Box::from(diagnostics.join("\n")))
+    }
+}
+
+impl Visitor for InitialisationChecker {
+    fn start_function_declaration(
+        &mut self,
+        _t: &mut FunctionDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        if let Some(enclosing) = _ctx.enclosing_type_identifier() {
+            self.functions.push((enclosing.token, _t.clone()));
+        }
+        Ok(())
+    }
+
+    fn start_special_declaration(
+        &mut self,
+        _t: &mut SpecialDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        if !_t.is_init() {
+            return Ok(());
+        }
+
+        if let Some(enclosing) = _ctx.enclosing_type_identifier() {
+            self.initialisers.push((enclosing.token, _t.clone()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct FlowState {
+    // The properties assigned on every path reaching this point
+    assigned: Vec<String>,
+    reachable: bool,
+}
+
+impl FlowState {
+    fn join(self, other: FlowState) -> FlowState {
+        if !self.reachable {
+            return other;
+        }
+        if !other.reachable {
+            return self;
+        }
+
+        let assigned = self
+            .assigned
+            .into_iter()
+            .filter(|p| other.assigned.contains(p))
+            .collect();
+        FlowState {
+            assigned,
+            reachable: true,
+        }
+    }
+}
+
+struct InitialisationFlow<'a> {
+    environment: &'a Environment,
+    enclosing: TypeIdentifier,
+    function_reads: &'a HashMap<(TypeIdentifier, String), Vec<String>>,
+    function_assigns: &'a HashMap<(TypeIdentifier, String), Vec<String>>,
+    locals: Vec<String>,
+    state: FlowState,
+    // The properties assigned on every path that leaves the function
+    assigned_on_exit: Option<Vec<String>>,
+    // Properties read before they were assigned, with the function call that
+    // read them if it was not the body itself
+    reads: Vec<(String, LineInfo, Option<String>)>,
+    // Properties without a default that some path leaves unassigned
+    unassigned: Vec<String>,
+}
+
+impl<'a> InitialisationFlow<'a> {
+    fn new(
+        environment: &'a Environment,
+        enclosing: TypeIdentifier,
+        function_reads: &'a HashMap<(TypeIdentifier, String), Vec<String>>,
+        function_assigns: &'a HashMap<(TypeIdentifier, String), Vec<String>>,
+    ) -> InitialisationFlow<'a> {
+        InitialisationFlow {
+            environment,
+            enclosing,
+            function_reads,
+            function_assigns,
+            locals: vec![],
+            state: FlowState {
+                assigned: vec![],
+                reachable: true,
+            },
+            assigned_on_exit: None,
+            reads: vec![],
+            unassigned: vec![],
+        }
+    }
+
+    fn function(&mut self, parameters: &[Parameter], body: &[Statement]) {
+        for parameter in parameters {
+            self.locals.push(parameter.identifier.token.clone());
+        }
+
+        self.block(body);
+        if self.state.reachable {
+            self.exit();
+        }
+    }
+
+    fn exit(&mut self) {
+        let assigned = &self.state.assigned;
+        match &mut self.assigned_on_exit {
+            Some(properties) => properties.retain(|p| assigned.contains(p)),
+            None => self.assigned_on_exit = Some(assigned.clone()),
+        }
+
+        let properties = self.environment.property_declarations(&self.enclosing);
+        for property in properties {
+            if let Property::VariableDeclaration(v) = property {
+                let token = v.identifier.token.clone();
+                if !has_default(&v)
+                    && !self.state.assigned.contains(&token)
+                    && !self.unassigned.contains(&token)
+                {
+                    self.unassigned.push(token);
+                }
+            }
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        let depth = self.locals.len();
+        for statement in statements {
+            if !self.state.reachable {
+                break;
+            }
+            self.statement(statement);
+        }
+        self.locals.truncate(depth);
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::ReturnStatement(r) => {
+                if let Some(expression) = &r.expression {
+                    self.expression(expression);
+                }
+                self.exit();
+                self.state.reachable = false;
+            }
+            Statement::Expression(e) => self.expression(e),
+            Statement::BecomeStatement(b) => self.expression(&b.expression),
+            Statement::EmitStatement(e) => self.arguments(&e.function_call.arguments),
+            Statement::ForStatement(f) => {
+                self.expression(&f.iterable);
+
+                // The body may not run at all
+                let entry = self.state.clone();
+                self.locals.push(f.variable.identifier.token.clone());
+                self.block(&f.body);
+                self.locals.pop();
+                let body_state = self.state.clone();
+                self.state = entry.join(body_state);
+            }
+            Statement::IfStatement(i) => {
+                self.expression(&i.condition);
+
+                let entry = self.state.clone();
+                self.block(&i.body);
+                let then_state = self.state.clone();
+                self.state = entry;
+                self.block(&i.else_body);
+                let else_state = self.state.clone();
+                self.state = then_state.join(else_state);
+            }
+            Statement::DoCatchStatement(d) => {
+                let entry = self.state.clone();
+                self.block(&d.do_body);
+                let do_state = self.state.clone();
+                self.state = entry.join(do_state.clone());
+                self.block(&d.catch_body);
+                let catch_state = self.state.clone();
+                self.state = do_state.join(catch_state);
+            }
+            Statement::TargetStatement(t) => self.statement(&t.statement),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(i) => {
+                if self.is_property(i) {
+                    self.read(&i.token, &i.line_info, None);
+                }
+            }
+            Expression::BinaryExpression(b) => match b.op {
+                BinOp::Dot => match &*b.lhs_expression {
+                    Expression::SelfExpression => self.self_member(&b.rhs_expression),
+                    lhs => {
+                        self.expression(lhs);
+                        self.member(&b.rhs_expression);
+                    }
+                },
+                BinOp::Equal => self.assignment(&b.lhs_expression, &b.rhs_expression),
+                _ => {
+                    self.expression(&b.lhs_expression);
+                    self.expression(&b.rhs_expression);
+                }
+            },
+            Expression::InoutExpression(i) => self.expression(&i.expression),
+            Expression::ExternalCall(e) => {
+                self.arguments(&e.arguments);
+                self.expression(&Expression::BinaryExpression(e.function_call.clone()));
+            }
+            Expression::FunctionCall(f) => self.call(f),
+            Expression::VariableDeclaration(v) => {
+                if let Some(expression) = &v.expression {
+                    self.expression(expression);
+                }
+                self.locals.push(v.identifier.token.clone());
+            }
+            Expression::BracketedExpression(b) => self.expression(&b.expression),
+            Expression::AttemptExpression(a) => self.call(&a.function_call),
+            Expression::ArrayLiteral(a) => {
+                for element in &a.elements {
+                    self.expression(element);
+                }
+            }
+            Expression::DictionaryLiteral(d) => {
+                for (key, value) in &d.elements {
+                    self.expression(key);
+                    self.expression(value);
+                }
+            }
+            Expression::SubscriptExpression(s) => {
+                if self.is_property(&s.base_expression) {
+                    let base = &s.base_expression;
+                    self.read(&base.token, &base.line_info, None);
+                }
+                self.expression(&s.index_expression);
+            }
+            Expression::RangeExpression(r) => {
+                self.expression(&r.start_expression);
+                self.expression(&r.end_expression);
+            }
+            Expression::CastExpression(c) => self.expression(&c.expression),
+            Expression::Sequence(s) => {
+                for expression in s {
+                    self.expression(expression);
+                }
+            }
+            Expression::Literal(_) | Expression::SelfExpression | Expression::RawAssembly(_, _) => {
+            }
+        }
+    }
+
+    // The right hand side of self.
+    fn self_member(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(i) => self.read(&i.token, &i.line_info, None),
+            Expression::FunctionCall(f) => self.call(f),
+            Expression::BinaryExpression(b) if b.op == BinOp::Dot => {
+                self.self_member(&b.lhs_expression);
+                self.member(&b.rhs_expression);
+            }
+            expression => self.expression(expression),
+        }
+    }
+
+    // The right hand side of a dot on any other value, whose fields and methods
+    // are not properties of the enclosing type
+    fn member(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(_) => {}
+            Expression::FunctionCall(f) => self.arguments(&f.arguments),
+            Expression::BinaryExpression(b) if b.op == BinOp::Dot => {
+                self.member(&b.lhs_expression);
+                self.member(&b.rhs_expression);
+            }
+            expression => self.expression(expression),
+        }
+    }
+
+    fn assignment(&mut self, lhs: &Expression, rhs: &Expression) {
+        self.expression(rhs);
+
+        match lhs {
+            Expression::VariableDeclaration(v) => {
+                self.locals.push(v.identifier.token.clone());
+            }
+            Expression::Identifier(i) if self.is_property(i) => self.assign(&i.token),
+            Expression::Identifier(_) => {}
+            Expression::BinaryExpression(b) if b.op == BinOp::Dot => {
+                match (&*b.lhs_expression, &*b.rhs_expression) {
+                    (Expression::SelfExpression, Expression::Identifier(i)) => {
+                        self.assign(&i.token)
+                    }
+                    _ => self.expression(lhs),
+                }
+            }
+            _ => self.expression(lhs),
+        }
+    }
+
+    // Calls to functions of the enclosing type read what those functions read
+    fn call(&mut self, call: &FunctionCall) {
+        self.arguments(&call.arguments);
+
+        let is_enclosing = match &call.identifier.enclosing_type {
+            Some(enclosing) => enclosing == &self.enclosing,
+            None => true,
+        };
+        if !is_enclosing {
+            return;
+        }
+
+        let key = (self.enclosing.clone(), call.identifier.token.clone());
+        if let Some(properties) = self.function_reads.get(&key) {
+            for property in properties.clone() {
+                self.read(
+                    &property,
+                    &call.identifier.line_info,
+                    Some(call.identifier.token.clone()),
+                );
+            }
+        }
+        if let Some(properties) = self.function_assigns.get(&key) {
+            for property in properties.clone() {
+                self.assign(&property);
+            }
+        }
+    }
+
+    fn arguments(&mut self, arguments: &[FunctionArgument]) {
+        for argument in arguments {
+            self.expression(&argument.expression);
+        }
+    }
+
+    fn is_property(&self, identifier: &Identifier) -> bool {
+        match &identifier.enclosing_type {
+            Some(enclosing) => enclosing == &self.enclosing,
+            None => {
+                !self.locals.contains(&identifier.token)
+                    && self
+                        .environment
+                        .is_property_defined(identifier.token.clone(), &self.enclosing)
+            }
+        }
+    }
+
+    fn read(&mut self, property: &str, line_info: &LineInfo, callee: Option<String>) {
+        if self.state.assigned.iter().any(|p| p == property) {
+            return;
+        }
+
+        let has_default = self
+            .environment
+            .property_declarations(&self.enclosing)
+            .into_iter()
+            .any(|p| match p {
+                Property::VariableDeclaration(v) => {
+                    v.identifier.token == property && has_default(&v)
+                }
+                _ => p.get_identifier().token == property && p.get_value().is_some(),
+            });
+        if has_default
+            || !self
+                .environment
+                .is_property_defined(property.to_string(), &self.enclosing)
+        {
+            return;
+        }
+
+        if !self.reads.iter().any(|(p, _, _)| p == property) {
+            self.reads
+                .push((property.to_string(), line_info.clone(), callee));
+        }
+    }
+
+    fn assign(&mut self, property: &str) {
+        if !self.state.assigned.iter().any(|p| p == property) {
+            self.state.assigned.push(property.to_string());
+        }
+    }
+}
+
+// Fixed-size arrays start out filled with zeros
+fn has_default(declaration: &VariableDeclaration) -> bool {
+    declaration.expression.is_some()
+        || matches!(declaration.variable_type, Type::FixedSizedArrayType(_))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::AstProcessor::tests::checked;
+    use crate::AstProcessor::Target;
+
+    #[test]
+    fn test_assigned_by_call() {
+        let program = "
+            contract Pair {
+              var a: Int
+              var b: Int
+              var scores: Int[2]
+            }
+            Pair :: caller <- (any) {
+              public init() {
+                setA()
+                b = getA()
+              }
+
+              func setA() mutates (a) {
+                a = 1
+              }
+
+              func getA() -> Int {
+                return a
+              }
+            }";
+        assert!(checked(program, &Target::Ether).is_ok());
+        assert!(checked(program, &Target::Move).is_ok());
+    }
+
+    #[test]
+    fn test_initialisation_errors() {
+        let program = "
+            contract Pair {
+              var a: Int
+              var b: Int
+            }
+            Pair :: caller <- (any) {
+              public init(flag: Bool) {
+                b = getA()
+                if flag {
+                  setA()
+                }
+              }
+
+              func setA() mutates (a) {
+                if a == 0 {
+                  return
+                }
+                a = 1
+              }
+
+              func getA() -> Int {
+                return a
+              }
+            }";
+        let error = checked(program, &Target::Ether).err().unwrap();
+        let errors: Vec<&str> = error.lines().collect();
+        assert_eq!(
+            errors,
+            vec![
+                "Property a is read by the call to getA on line 8 before it is assigned in Pair.init",
+                "Property a is not assigned on every path through Pair.init on line 7",
+            ]
+        );
+    }
+}
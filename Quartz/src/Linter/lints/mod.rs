@@ -0,0 +1,1325 @@
+use super::{Lint, LintDiagnostic, LintLevel};
+use crate::context::*;
+use crate::environment::*;
+use crate::visitor::*;
+use crate::AST::*;
+use std::collections::{BTreeSet, HashMap};
+
+// Names starting with an underscore are meant to be unused
+fn is_ignored(identifier: &Identifier) -> bool {
+    identifier.token.starts_with('_')
+}
+
+#[derive(Clone, Debug)]
+struct Binding {
+    identifier: Identifier,
+    is_parameter: bool,
+    is_read: bool,
+}
+
+// Follows the parameters and local variables of a function body: which of them
+// are read, which declarations hide another, and which other names are read
+struct BindingWalker<'a> {
+    environment: &'a Environment,
+    enclosing: TypeIdentifier,
+    bindings: Vec<Binding>,
+    // Indices into bindings of the declarations in scope
+    scope: Vec<usize>,
+    shadowing: Vec<(Identifier, String)>,
+    // Names read that are not bound in the body, and names read after a dot
+    free_reads: Vec<String>,
+    member_reads: Vec<String>,
+    // Raw assembly may read anything
+    has_assembly: bool,
+}
+
+impl<'a> BindingWalker<'a> {
+    fn new(environment: &'a Environment, enclosing: TypeIdentifier) -> BindingWalker<'a> {
+        BindingWalker {
+            environment,
+            enclosing,
+            bindings: vec![],
+            scope: vec![],
+            shadowing: vec![],
+            free_reads: vec![],
+            member_reads: vec![],
+            has_assembly: false,
+        }
+    }
+
+    fn function(&mut self, parameters: &[Parameter], body: &[Statement]) {
+        for parameter in parameters {
+            self.declare(&parameter.identifier, true);
+        }
+        self.block(body);
+    }
+
+    fn unread(&self, is_parameter: bool) -> Vec<&Identifier> {
+        if self.has_assembly {
+            return vec![];
+        }
+
+        self.bindings
+            .iter()
+            .filter(|b| b.is_parameter == is_parameter && !b.is_read && !is_ignored(&b.identifier))
+            .map(|b| &b.identifier)
+            .collect()
+    }
+
+    fn declare(&mut self, identifier: &Identifier, is_parameter: bool) {
+        let hidden = self
+            .scope
+            .iter()
+            .rev()
+            .map(|i| &self.bindings[*i])
+            .find(|b| b.identifier.token == identifier.token);
+
+        match hidden {
+            Some(hidden) => {
+                let line = hidden.identifier.line_info.line;
+                self.shadowing.push((
+                    identifier.clone(),
+                    format!("the declaration on line {}", line),
+                ));
+            }
+            // Parameters named after the property they initialise are idiomatic
+            None => {
+                if !is_parameter
+                    && self
+                        .environment
+                        .is_property_defined(identifier.token.clone(), &self.enclosing)
+                {
+                    self.shadowing.push((
+                        identifier.clone(),
+                        format!("the property {}", identifier.token),
+                    ));
+                }
+            }
+        }
+
+        self.scope.push(self.bindings.len());
+        self.bindings.push(Binding {
+            identifier: identifier.clone(),
+            is_parameter,
+            is_read: false,
+        });
+    }
+
+    fn read(&mut self, identifier: &Identifier) {
+        if identifier.enclosing_type.is_some() {
+            self.free_reads.push(identifier.token.clone());
+            return;
+        }
+
+        let bindings = &self.bindings;
+        let index = self
+            .scope
+            .iter()
+            .rev()
+            .find(|i| bindings[**i].identifier.token == identifier.token)
+            .cloned();
+        match index {
+            Some(index) => self.bindings[index].is_read = true,
+            None => self.free_reads.push(identifier.token.clone()),
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        let depth = self.scope.len();
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.scope.truncate(depth);
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::ReturnStatement(r) => {
+                if let Some(expression) = &r.expression {
+                    self.expression(expression);
+                }
+            }
+            Statement::Expression(e) => self.expression(e),
+            Statement::BecomeStatement(b) => self.expression(&b.expression),
+            Statement::EmitStatement(e) => self.arguments(&e.function_call.arguments),
+            Statement::ForStatement(f) => {
+                self.expression(&f.iterable);
+
+                let depth = self.scope.len();
+                self.declare(&f.variable.identifier, false);
+                self.block(&f.body);
+                self.scope.truncate(depth);
+            }
+            Statement::IfStatement(i) => {
+                self.expression(&i.condition);
+                self.block(&i.body);
+                self.block(&i.else_body);
+            }
+            Statement::DoCatchStatement(d) => {
+                self.block(&d.do_body);
+                self.block(&d.catch_body);
+            }
+            Statement::TargetStatement(t) => self.statement(&t.statement),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(i) => self.read(i),
+            Expression::BinaryExpression(b) => match b.op {
+                BinOp::Dot => {
+                    match &*b.lhs_expression {
+                        Expression::SelfExpression => {}
+                        lhs => self.expression(lhs),
+                    }
+                    self.member(&b.rhs_expression);
+                }
+                BinOp::Equal => {
+                    self.expression(&b.rhs_expression);
+                    match &*b.lhs_expression {
+                        Expression::VariableDeclaration(v) => self.declare(&v.identifier, false),
+                        // Assigning to a variable does not read it
+                        Expression::Identifier(_) => {}
+                        lhs => self.expression(lhs),
+                    }
+                }
+                _ => {
+                    self.expression(&b.lhs_expression);
+                    self.expression(&b.rhs_expression);
+                }
+            },
+            Expression::InoutExpression(i) => self.expression(&i.expression),
+            Expression::ExternalCall(e) => {
+                self.arguments(&e.arguments);
+                self.expression(&Expression::BinaryExpression(e.function_call.clone()));
+            }
+            Expression::FunctionCall(f) => self.arguments(&f.arguments),
+            Expression::VariableDeclaration(v) => {
+                if let Some(expression) = &v.expression {
+                    self.expression(expression);
+                }
+                self.declare(&v.identifier, false);
+            }
+            Expression::BracketedExpression(b) => self.expression(&b.expression),
+            Expression::AttemptExpression(a) => self.arguments(&a.function_call.arguments),
+            Expression::ArrayLiteral(a) => {
+                for element in &a.elements {
+                    self.expression(element);
+                }
+            }
+            Expression::DictionaryLiteral(d) => {
+                for (key, value) in &d.elements {
+                    self.expression(key);
+                    self.expression(value);
+                }
+            }
+            Expression::SubscriptExpression(s) => {
+                self.read(&s.base_expression);
+                self.expression(&s.index_expression);
+            }
+            Expression::RangeExpression(r) => {
+                self.expression(&r.start_expression);
+                self.expression(&r.end_expression);
+            }
+            Expression::CastExpression(c) => self.expression(&c.expression),
+            Expression::Sequence(s) => {
+                for expression in s {
+                    self.expression(expression);
+                }
+            }
+            Expression::RawAssembly(_, _) => self.has_assembly = true,
+            Expression::Literal(_) | Expression::SelfExpression => {}
+        }
+    }
+
+    // The right hand side of a dot, which names a field or method of the value
+    // on its left
+    fn member(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(i) => self.member_reads.push(i.token.clone()),
+            Expression::FunctionCall(f) => self.arguments(&f.arguments),
+            Expression::BinaryExpression(b) if b.op == BinOp::Dot => {
+                self.member(&b.lhs_expression);
+                self.member(&b.rhs_expression);
+            }
+            expression => self.expression(expression),
+        }
+    }
+
+    fn arguments(&mut self, arguments: &[FunctionArgument]) {
+        for argument in arguments {
+            self.expression(&argument.expression);
+        }
+    }
+}
+
+// The bindings of a function or initialiser of a type outside the standard
+// library
+fn function_bindings<'a>(
+    ctx: &'a Context,
+    parameters: &[Parameter],
+    body: &[Statement],
+) -> Option<BindingWalker<'a>> {
+    let enclosing = ctx.enclosing_type_identifier()?.token;
+    if Environment::is_stdlib_type(&enclosing) {
+        return None;
+    }
+
+    let mut walker = BindingWalker::new(&ctx.environment, enclosing);
+    walker.function(parameters, body);
+    Some(walker)
+}
+
+// A lint that looks at one function or initialiser at a time
+pub trait FunctionLint {
+    fn name(&self) -> &'static str;
+
+    fn default_level(&self) -> LintLevel;
+
+    fn check(
+        &self,
+        parameters: &[Parameter],
+        body: &[Statement],
+        attributes: &[Attribute],
+        ctx: &Context,
+    ) -> Vec<LintDiagnostic>;
+}
+
+// Runs a FunctionLint over every function and initialiser of the module
+pub struct FunctionLints<L: FunctionLint> {
+    lint: L,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl<L: FunctionLint> FunctionLints<L> {
+    pub fn new(lint: L) -> FunctionLints<L> {
+        FunctionLints {
+            lint,
+            diagnostics: vec![],
+        }
+    }
+}
+
+impl<L: FunctionLint> Lint for FunctionLints<L> {
+    fn name(&self) -> &'static str {
+        self.lint.name()
+    }
+
+    fn default_level(&self) -> LintLevel {
+        self.lint.default_level()
+    }
+
+    fn diagnostics(&mut self) -> Vec<LintDiagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+impl<L: FunctionLint> Visitor for FunctionLints<L> {
+    fn start_function_declaration(
+        &mut self,
+        _t: &mut FunctionDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        let diagnostics = self
+            .lint
+            .check(&_t.head.parameters, &_t.body, &_t.head.attributes, _ctx);
+        self.diagnostics.extend(diagnostics);
+        Ok(())
+    }
+
+    fn start_special_declaration(
+        &mut self,
+        _t: &mut SpecialDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        let diagnostics = self
+            .lint
+            .check(&_t.head.parameters, &_t.body, &_t.head.attributes, _ctx);
+        self.diagnostics.extend(diagnostics);
+        Ok(())
+    }
+}
+
+// Local variables that are declared but never read
+pub struct UnusedVariables {}
+
+impl FunctionLint for UnusedVariables {
+    fn name(&self) -> &'static str {
+        "unused_variables"
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+
+    fn check(
+        &self,
+        parameters: &[Parameter],
+        body: &[Statement],
+        attributes: &[Attribute],
+        ctx: &Context,
+    ) -> Vec<LintDiagnostic> {
+        let walker = match function_bindings(ctx, parameters, body) {
+            Some(walker) => walker,
+            None => return vec![],
+        };
+        walker
+            .unread(false)
+            .into_iter()
+            .map(|identifier| {
+                LintDiagnostic::new(
+                    identifier.line_info.clone(),
+                    format!("Variable {} is never read", identifier.token),
+                    attributes,
+                    ctx,
+                )
+            })
+            .collect()
+    }
+}
+
+// Parameters that the body never reads
+pub struct UnusedParameters {}
+
+impl FunctionLint for UnusedParameters {
+    fn name(&self) -> &'static str {
+        "unused_parameters"
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+
+    fn check(
+        &self,
+        parameters: &[Parameter],
+        body: &[Statement],
+        attributes: &[Attribute],
+        ctx: &Context,
+    ) -> Vec<LintDiagnostic> {
+        let walker = match function_bindings(ctx, parameters, body) {
+            Some(walker) => walker,
+            None => return vec![],
+        };
+        walker
+            .unread(true)
+            .into_iter()
+            .map(|identifier| {
+                LintDiagnostic::new(
+                    identifier.line_info.clone(),
+                    format!("Parameter {} is never read", identifier.token),
+                    attributes,
+                    ctx,
+                )
+            })
+            .collect()
+    }
+}
+
+// Local variables hiding a parameter, another local variable or a property
+pub struct Shadowing {}
+
+impl FunctionLint for Shadowing {
+    fn name(&self) -> &'static str {
+        "shadowing"
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+
+    fn check(
+        &self,
+        parameters: &[Parameter],
+        body: &[Statement],
+        attributes: &[Attribute],
+        ctx: &Context,
+    ) -> Vec<LintDiagnostic> {
+        let walker = match function_bindings(ctx, parameters, body) {
+            Some(walker) => walker,
+            None => return vec![],
+        };
+        walker
+            .shadowing
+            .iter()
+            .map(|(identifier, hidden)| {
+                LintDiagnostic::new(
+                    identifier.line_info.clone(),
+                    format!("Variable {} shadows {}", identifier.token, hidden),
+                    attributes,
+                    ctx,
+                )
+            })
+            .collect()
+    }
+}
+
+// Statements following a return or become in the same block
+pub struct UnreachableCode {}
+
+impl UnreachableCode {
+    fn block(
+        &self,
+        statements: &[Statement],
+        attributes: &[Attribute],
+        ctx: &Context,
+        diagnostics: &mut Vec<LintDiagnostic>,
+    ) {
+        for (index, statement) in statements.iter().enumerate() {
+            let statement = match statement {
+                Statement::TargetStatement(t) => &*t.statement,
+                statement => statement,
+            };
+
+            let exit = match statement {
+                Statement::ReturnStatement(r) => Some(("return", r.line_info.clone())),
+                Statement::BecomeStatement(b) => Some(("become", b.line_info.clone())),
+                Statement::IfStatement(i) => {
+                    self.block(&i.body, attributes, ctx, diagnostics);
+                    self.block(&i.else_body, attributes, ctx, diagnostics);
+                    None
+                }
+                Statement::ForStatement(f) => {
+                    self.block(&f.body, attributes, ctx, diagnostics);
+                    None
+                }
+                Statement::DoCatchStatement(d) => {
+                    self.block(&d.do_body, attributes, ctx, diagnostics);
+                    self.block(&d.catch_body, attributes, ctx, diagnostics);
+                    None
+                }
+                _ => None,
+            };
+
+            if let Some((keyword, line_info)) = exit {
+                if index + 1 < statements.len() {
+                    diagnostics.push(LintDiagnostic::new(
+                        line_info,
+                        format!("Statements after this {} are unreachable", keyword),
+                        attributes,
+                        ctx,
+                    ));
+                }
+                return;
+            }
+        }
+    }
+}
+
+impl FunctionLint for UnreachableCode {
+    fn name(&self) -> &'static str {
+        "unreachable_code"
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+
+    fn check(
+        &self,
+        _parameters: &[Parameter],
+        body: &[Statement],
+        attributes: &[Attribute],
+        ctx: &Context,
+    ) -> Vec<LintDiagnostic> {
+        let mut diagnostics = vec![];
+        self.block(body, attributes, ctx, &mut diagnostics);
+        diagnostics
+    }
+}
+
+// Removes the statements the unreachable_code lint reports once the lints have
+// run, as the code generators would otherwise run them after the return
+pub struct UnreachableCodeRemover {}
+
+impl UnreachableCodeRemover {
+    fn block(statements: &mut Vec<Statement>) {
+        let exit = statements.iter().position(|statement| {
+            let statement = match statement {
+                Statement::TargetStatement(t) => &*t.statement,
+                statement => statement,
+            };
+            matches!(
+                statement,
+                Statement::ReturnStatement(_) | Statement::BecomeStatement(_)
+            )
+        });
+        if let Some(index) = exit {
+            statements.truncate(index + 1);
+        }
+    }
+}
+
+impl Visitor for UnreachableCodeRemover {
+    fn start_function_declaration(
+        &mut self,
+        _t: &mut FunctionDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        Self::block(&mut _t.body);
+        Ok(())
+    }
+
+    fn start_special_declaration(
+        &mut self,
+        _t: &mut SpecialDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        Self::block(&mut _t.body);
+        Ok(())
+    }
+
+    fn start_if_statement(&mut self, _t: &mut IfStatement, _ctx: &mut Context) -> VResult {
+        Self::block(&mut _t.body);
+        Self::block(&mut _t.else_body);
+        Ok(())
+    }
+
+    fn start_for_statement(&mut self, _t: &mut ForStatement, _ctx: &mut Context) -> VResult {
+        Self::block(&mut _t.body);
+        Ok(())
+    }
+
+    fn start_do_catch_statement(
+        &mut self,
+        _t: &mut DoCatchStatement,
+        _ctx: &mut Context,
+    ) -> VResult {
+        Self::block(&mut _t.do_body);
+        Self::block(&mut _t.catch_body);
+        Ok(())
+    }
+}
+
+// Properties that are assigned but never read. A property counts as read when
+// its name is read in the code of its type, or after a dot anywhere, since the
+// type of the value on the left is not known here.
+pub struct UnusedProperties {
+    properties: Vec<(TypeIdentifier, VariableDeclaration)>,
+    reads: Vec<(TypeIdentifier, String)>,
+    member_reads: Vec<String>,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl UnusedProperties {
+    pub fn new() -> UnusedProperties {
+        UnusedProperties {
+            properties: vec![],
+            reads: vec![],
+            member_reads: vec![],
+            diagnostics: vec![],
+        }
+    }
+
+    fn add_properties(
+        &mut self,
+        enclosing: &Identifier,
+        properties: Vec<VariableDeclaration>,
+        environment: &Environment,
+    ) {
+        for property in properties {
+            if let Some(expression) = &property.expression {
+                let mut walker = BindingWalker::new(environment, enclosing.token.clone());
+                walker.expression(expression);
+                self.add_reads(&enclosing.token, walker);
+            }
+            self.properties.push((enclosing.token.clone(), property));
+        }
+    }
+
+    fn add_reads(&mut self, enclosing: &TypeIdentifier, walker: BindingWalker) {
+        for name in walker.free_reads {
+            self.reads.push((enclosing.clone(), name));
+        }
+        self.member_reads.extend(walker.member_reads);
+    }
+
+    fn add_function_reads(&mut self, parameters: &[Parameter], body: &[Statement], ctx: &Context) {
+        if let Some(walker) = function_bindings(ctx, parameters, body) {
+            let enclosing = walker.enclosing.clone();
+            self.add_reads(&enclosing, walker);
+        }
+    }
+}
+
+impl Lint for UnusedProperties {
+    fn name(&self) -> &'static str {
+        "unused_properties"
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+
+    fn diagnostics(&mut self) -> Vec<LintDiagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+impl Visitor for UnusedProperties {
+    fn start_contract_declaration(
+        &mut self,
+        _t: &mut ContractDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        self.add_properties(
+            &_t.identifier,
+            _t.get_variable_declarations(),
+            &_ctx.environment,
+        );
+        Ok(())
+    }
+
+    fn start_struct_declaration(
+        &mut self,
+        _t: &mut StructDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        self.add_properties(
+            &_t.identifier,
+            _t.get_variable_declarations(),
+            &_ctx.environment,
+        );
+        Ok(())
+    }
+
+    fn start_asset_declaration(
+        &mut self,
+        _t: &mut AssetDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        self.add_properties(
+            &_t.identifier,
+            _t.get_variable_declarations(),
+            &_ctx.environment,
+        );
+        Ok(())
+    }
+
+    // Caller protections read the property they name
+    fn start_contract_behaviour_declaration(
+        &mut self,
+        _t: &mut ContractBehaviourDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        for protection in &_t.caller_protections {
+            self.reads.push((
+                _t.identifier.token.clone(),
+                protection.identifier.token.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn start_function_declaration(
+        &mut self,
+        _t: &mut FunctionDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        self.add_function_reads(&_t.head.parameters, &_t.body, _ctx);
+        Ok(())
+    }
+
+    fn start_special_declaration(
+        &mut self,
+        _t: &mut SpecialDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        self.add_function_reads(&_t.head.parameters, &_t.body, _ctx);
+        Ok(())
+    }
+
+    fn finish_module(&mut self, _t: &mut Module, _ctx: &mut Context) -> VResult {
+        for (enclosing, property) in &self.properties {
+            let token = &property.identifier.token;
+            let is_read = self.member_reads.contains(token)
+                || self.reads.iter().any(|(t, n)| t == enclosing && n == token);
+            if is_read || Environment::is_stdlib_type(enclosing) || is_ignored(&property.identifier)
+            {
+                continue;
+            }
+
+            self.diagnostics.push(LintDiagnostic {
+                line_info: property.identifier.line_info.clone(),
+                message: format!("Property {} of {} is never read", token, enclosing),
+                enclosing: Some(enclosing.clone()),
+                attributes: property.attributes.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+// Storage writes, becomes and Wei transfers that may run after an external call
+// on some path, where a reentrant call can see the contract half updated.
+// Functions marked @nonReentrant hold a lock, which only helps when every
+// public function that can observe the change holds it too.
+pub struct Reentrancy {
+    functions: Vec<(TypeIdentifier, FunctionDeclaration, Vec<Attribute>)>,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+// Whether a function may make an external call, whether it may change
+// storage, and which properties it uses, directly or through the functions
+// it calls
+#[derive(Clone, Default, PartialEq)]
+struct CallSummary {
+    calls_externally: bool,
+    has_effects: bool,
+    accesses: BTreeSet<String>,
+}
+
+impl Reentrancy {
+    pub fn new() -> Reentrancy {
+        Reentrancy {
+            functions: vec![],
+            diagnostics: vec![],
+        }
+    }
+}
+
+impl Lint for Reentrancy {
+    fn name(&self) -> &'static str {
+        "reentrancy"
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+
+    fn diagnostics(&mut self) -> Vec<LintDiagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+impl Visitor for Reentrancy {
+    fn start_function_declaration(
+        &mut self,
+        _t: &mut FunctionDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        let enclosing = match _ctx.enclosing_type_identifier() {
+            Some(enclosing) => enclosing.token,
+            None => return Ok(()),
+        };
+
+        // Functions are checked once the summaries of every callee are known
+        let attributes =
+            LintDiagnostic::new(Default::default(), String::new(), &_t.head.attributes, _ctx)
+                .attributes;
+        self.functions.push((enclosing, _t.clone(), attributes));
+        Ok(())
+    }
+
+    fn finish_module(&mut self, _t: &mut Module, _ctx: &mut Context) -> VResult {
+        let mut summaries: HashMap<(TypeIdentifier, String), CallSummary> = HashMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (enclosing, function, _) in &self.functions {
+                let mut flow = ReentrancyFlow::new(&_ctx.environment, enclosing, &summaries);
+                flow.function(function);
+                let key = (enclosing.clone(), function.head.identifier.token.clone());
+                let summary = summaries.get(&key).cloned().unwrap_or_default();
+                let merged = CallSummary {
+                    calls_externally: summary.calls_externally || flow.calls_externally,
+                    has_effects: summary.has_effects || flow.has_effects,
+                    accesses: summary.accesses.union(&flow.accesses).cloned().collect(),
+                };
+                if merged != summary {
+                    summaries.insert(key, merged);
+                    changed = true;
+                }
+            }
+        }
+
+        for (enclosing, function, attributes) in &self.functions {
+            if Environment::is_stdlib_type(enclosing) {
+                continue;
+            }
+
+            let mut flow = ReentrancyFlow::new(&_ctx.environment, enclosing, &summaries);
+            flow.function(function);
+            for (line_info, mut message, property) in flow.findings {
+                if function.is_non_reentrant() {
+                    // A change of the whole state can be seen by every function
+                    let observer = self.functions.iter().find(|(e, f, _)| {
+                        e == enclosing
+                            && f.is_public()
+                            && !f.is_non_reentrant()
+                            && property.as_ref().is_none_or(|p| {
+                                let key = (e.clone(), f.head.identifier.token.clone());
+                                summaries
+                                    .get(&key)
+                                    .is_some_and(|s| s.accesses.contains(p))
+                            })
+                    });
+                    match observer {
+                        Some((_, observer, _)) => {
+                            message = format!(
+                                "{}, @nonReentrant does not lock {} which can observe it",
+                                message, observer.head.identifier.token
+                            );
+                        }
+                        None => continue,
+                    }
+                }
+                self.diagnostics.push(LintDiagnostic {
+                    line_info,
+                    message,
+                    enclosing: Some(enclosing.clone()),
+                    attributes: attributes.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+// The external call the current path may have made
+#[derive(Clone)]
+struct CallState {
+    external_call: Option<LineInfo>,
+    reachable: bool,
+}
+
+impl CallState {
+    // Paths that returned do not continue past the join
+    fn join(self, other: CallState) -> CallState {
+        if !self.reachable {
+            return other;
+        }
+        if !other.reachable {
+            return self;
+        }
+        CallState {
+            external_call: self.external_call.or(other.external_call),
+            reachable: true,
+        }
+    }
+}
+
+struct ReentrancyFlow<'a> {
+    environment: &'a Environment,
+    enclosing: TypeIdentifier,
+    summaries: &'a HashMap<(TypeIdentifier, String), CallSummary>,
+    locals: Vec<String>,
+    state: CallState,
+    calls_externally: bool,
+    has_effects: bool,
+    accesses: BTreeSet<String>,
+    // The property each finding changes, or None when it is the whole state
+    findings: Vec<(LineInfo, String, Option<String>)>,
+}
+
+impl<'a> ReentrancyFlow<'a> {
+    fn new(
+        environment: &'a Environment,
+        enclosing: &TypeIdentifier,
+        summaries: &'a HashMap<(TypeIdentifier, String), CallSummary>,
+    ) -> ReentrancyFlow<'a> {
+        ReentrancyFlow {
+            environment,
+            enclosing: enclosing.clone(),
+            summaries,
+            locals: vec![],
+            state: CallState {
+                external_call: None,
+                reachable: true,
+            },
+            calls_externally: false,
+            has_effects: false,
+            accesses: BTreeSet::new(),
+            findings: vec![],
+        }
+    }
+
+    fn function(&mut self, function: &FunctionDeclaration) {
+        for parameter in &function.head.parameters {
+            self.locals.push(parameter.identifier.token.clone());
+        }
+        self.block(&function.body);
+    }
+
+    fn effect(&mut self, line_info: LineInfo, description: String, property: Option<String>) {
+        self.has_effects = true;
+        if !self.state.reachable {
+            return;
+        }
+        if let Some(call) = &self.state.external_call {
+            self.findings.push((
+                line_info,
+                format!(
+                    "{} after the external call on line {}, follow checks-effects-interactions and change storage before the call",
+                    description, call.line
+                ),
+                property,
+            ));
+        }
+    }
+
+    fn external_call(&mut self, line_info: LineInfo) {
+        self.calls_externally = true;
+        if self.state.reachable && self.state.external_call.is_none() {
+            self.state.external_call = Some(line_info);
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::ReturnStatement(r) => {
+                if let Some(expression) = &r.expression {
+                    self.expression(expression);
+                }
+                self.state.reachable = false;
+            }
+            Statement::BecomeStatement(b) => {
+                self.expression(&b.expression);
+                self.effect(
+                    b.line_info.clone(),
+                    "The state is changed by become".to_string(),
+                    None,
+                );
+                self.state.reachable = false;
+            }
+            Statement::Expression(e) => self.expression(e),
+            Statement::EmitStatement(e) => self.arguments(&e.function_call.arguments),
+            Statement::ForStatement(f) => {
+                self.locals.push(f.variable.identifier.token.clone());
+                self.expression(&f.iterable);
+                // A second pass over the body sees the calls of the first
+                let entry = self.state.clone();
+                self.block(&f.body);
+                self.state = entry.clone().join(self.state.clone());
+                self.block(&f.body);
+                self.state = entry.join(self.state.clone());
+            }
+            Statement::IfStatement(i) => {
+                self.expression(&i.condition);
+                let entry = self.state.clone();
+                self.block(&i.body);
+                let body = std::mem::replace(&mut self.state, entry);
+                self.block(&i.else_body);
+                self.state = body.join(self.state.clone());
+            }
+            Statement::DoCatchStatement(d) => {
+                let entry = self.state.clone();
+                self.block(&d.do_body);
+                let body = self.state.clone();
+                self.state = entry.join(body.clone());
+                self.block(&d.catch_body);
+                self.state = body.join(self.state.clone());
+            }
+            Statement::TargetStatement(t) => self.statement(&t.statement),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::BinaryExpression(b) => {
+                if b.op.is_assignment() {
+                    self.expression(&b.rhs_expression);
+                    if let Some(property) = self.property_root(&b.lhs_expression) {
+                        let line_info = b.lhs_expression.get_line_info();
+                        self.accesses.insert(property.clone());
+                        self.effect(
+                            line_info,
+                            format!("Property {} is written", property),
+                            Some(property),
+                        );
+                    }
+                    return;
+                }
+
+                if let BinOp::Dot = b.op {
+                    if let Expression::FunctionCall(f) = &*b.rhs_expression {
+                        self.dot_call(&b.lhs_expression, f);
+                        return;
+                    }
+                    if let Expression::SelfExpression = &*b.lhs_expression {
+                        self.expression(&b.rhs_expression);
+                        return;
+                    }
+                }
+                self.expression(&b.lhs_expression);
+                self.expression(&b.rhs_expression);
+            }
+            Expression::InoutExpression(i) => {
+                // Passing a property by reference lets the callee change it
+                if let Some(property) = self.property_root(&i.expression) {
+                    let line_info = i.expression.get_line_info();
+                    self.effect(
+                        line_info,
+                        format!("Property {} is passed inout", property),
+                        Some(property.clone()),
+                    );
+                }
+                self.expression(&i.expression);
+            }
+            Expression::ExternalCall(e) => {
+                self.arguments(&e.arguments);
+                self.expression(&e.function_call.lhs_expression);
+                if let Expression::FunctionCall(f) = &*e.function_call.rhs_expression {
+                    self.arguments(&f.arguments);
+                }
+                self.external_call(e.function_call.line_info.clone());
+            }
+            Expression::FunctionCall(f) => {
+                let enclosing = self.enclosing.clone();
+                self.call(f, &enclosing);
+            }
+            Expression::VariableDeclaration(v) => {
+                self.locals.push(v.identifier.token.clone());
+                if let Some(expression) = &v.expression {
+                    self.expression(expression);
+                }
+            }
+            Expression::BracketedExpression(b) => self.expression(&b.expression),
+            Expression::AttemptExpression(a) => {
+                let enclosing = self.enclosing.clone();
+                self.call(&a.function_call, &enclosing);
+            }
+            Expression::ArrayLiteral(a) => {
+                for element in &a.elements {
+                    self.expression(element);
+                }
+            }
+            Expression::SubscriptExpression(s) => {
+                self.expression(&Expression::Identifier(s.base_expression.clone()));
+                self.expression(&s.index_expression);
+            }
+            Expression::RangeExpression(r) => {
+                self.expression(&r.start_expression);
+                self.expression(&r.end_expression);
+            }
+            Expression::CastExpression(c) => self.expression(&c.expression),
+            Expression::Sequence(expressions) => {
+                for expression in expressions {
+                    self.expression(expression);
+                }
+            }
+            Expression::Identifier(i) => {
+                if !self.locals.contains(&i.token) {
+                    if let Some(property) = self.property(&i.token) {
+                        self.accesses.insert(property);
+                    }
+                }
+            }
+            Expression::Literal(_)
+            | Expression::DictionaryLiteral(_)
+            | Expression::SelfExpression
+            | Expression::RawAssembly(_, _) => {}
+        }
+    }
+
+    // A call on self runs in the enclosing type, and a call on a property in
+    // the type of the property, where it changes that property
+    fn dot_call(&mut self, receiver: &Expression, call: &FunctionCall) {
+        if let Expression::SelfExpression = receiver {
+            let enclosing = self.enclosing.clone();
+            self.call(call, &enclosing);
+            return;
+        }
+
+        self.expression(receiver);
+        let property = match self.property_root(receiver) {
+            Some(property) => property,
+            None => {
+                self.arguments(&call.arguments);
+                return;
+            }
+        };
+
+        let property_type = self.environment.get_property_type(
+            property.clone(),
+            &self.enclosing,
+            Default::default(),
+        );
+        let property_type = match property_type {
+            Type::UserDefinedType(i) => i.token,
+            _ => {
+                self.arguments(&call.arguments);
+                return;
+            }
+        };
+
+        self.arguments(&call.arguments);
+        let summary = self.summary(&property_type, &call.identifier.token);
+        if summary.has_effects {
+            self.effect(
+                call.identifier.line_info.clone(),
+                format!(
+                    "Property {} is changed by the call to {}",
+                    property, call.identifier.token
+                ),
+                Some(property.clone()),
+            );
+        }
+        if summary.calls_externally {
+            self.external_call(call.identifier.line_info.clone());
+        }
+    }
+
+    fn call(&mut self, call: &FunctionCall, enclosing: &TypeIdentifier) {
+        self.arguments(&call.arguments);
+
+        let name = &call.identifier.token;
+        let line_info = call.identifier.line_info.clone();
+        if name == "send"
+            && !self
+                .summaries
+                .contains_key(&(enclosing.clone(), name.clone()))
+        {
+            self.effect(line_info, "Wei is sent".to_string(), None);
+            return;
+        }
+
+        let summary = self.summary(enclosing, name);
+        if *enclosing == self.enclosing {
+            self.accesses.extend(summary.accesses.iter().cloned());
+        }
+        if summary.has_effects {
+            self.effect(
+                line_info.clone(),
+                format!("The call to {} changes storage", name),
+                None,
+            );
+        }
+        if summary.calls_externally {
+            self.external_call(line_info);
+        }
+    }
+
+    fn summary(&self, enclosing: &TypeIdentifier, name: &str) -> CallSummary {
+        self.summaries
+            .get(&(enclosing.clone(), name.to_string()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn arguments(&mut self, arguments: &[FunctionArgument]) {
+        for argument in arguments {
+            self.expression(&argument.expression);
+        }
+    }
+
+    // The property of the enclosing type that an lvalue such as self.a.b[i]
+    // belongs to
+    fn property_root(&self, expression: &Expression) -> Option<String> {
+        match expression {
+            Expression::Identifier(i) => {
+                if self.locals.contains(&i.token) {
+                    return None;
+                }
+                self.property(&i.token)
+            }
+            Expression::BinaryExpression(b) if b.op == BinOp::Dot => match &*b.lhs_expression {
+                Expression::SelfExpression => self.self_property_root(&b.rhs_expression),
+                lhs => self.property_root(lhs),
+            },
+            Expression::SubscriptExpression(s) => {
+                self.property_root(&Expression::Identifier(s.base_expression.clone()))
+            }
+            Expression::BracketedExpression(b) => self.property_root(&b.expression),
+            Expression::InoutExpression(i) => self.property_root(&i.expression),
+            _ => None,
+        }
+    }
+
+    // The right hand side of self., which names a property even when a local
+    // variable hides it
+    fn self_property_root(&self, expression: &Expression) -> Option<String> {
+        match expression {
+            Expression::Identifier(i) => self.property(&i.token),
+            Expression::BinaryExpression(b) if b.op == BinOp::Dot => {
+                self.self_property_root(&b.lhs_expression)
+            }
+            Expression::SubscriptExpression(s) => self.property(&s.base_expression.token),
+            _ => None,
+        }
+    }
+
+    fn property(&self, name: &str) -> Option<String> {
+        if self
+            .environment
+            .is_property_defined(name.to_string(), &self.enclosing)
+        {
+            return Some(name.to_string());
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::AstProcessor::tests::checked;
+    use crate::AstProcessor::Target;
+
+    // A bank whose withdraw pays out through an external call, with the
+    // attributes of withdraw and the body of its balance check
+    fn bank(attributes: &str, withdraw: &str) -> String {
+        format!(
+            "
+            contract Bank {{
+              var balance: Int = 0
+            }}
+            external trait Wallet {{
+              func receive(amount: uint256) -> uint256
+            }}
+            Bank :: caller <- (any) {{
+              public init() {{}}
+
+              @deny(reentrancy) {}
+              public func withdraw(to: Address, amount: Int) mutates (balance) {{
+                let wallet: Wallet = Wallet(address: to)
+                {}
+              }}
+
+              public func getBalance() -> Int {{
+                return balance
+              }}
+            }}
+            ",
+            attributes, withdraw
+        )
+    }
+
+    const CALL: &str = "call wallet.receive(amount: cast amount to uint256)";
+
+    #[test]
+    fn test_effects_after_call() {
+        let program = bank("", &format!("{}\n balance -= amount", CALL));
+        let error = checked(&program, &Target::Ether).err().unwrap();
+        assert!(error.contains(
+            "Error on line 15: Property balance is written after the external call on line 14"
+        ));
+        assert!(error.contains("[reentrancy]"));
+    }
+
+    #[test]
+    fn test_effects_before_call() {
+        let program = bank("", &format!("balance -= amount\n {}", CALL));
+        assert!(checked(&program, &Target::Ether).is_ok());
+    }
+
+    #[test]
+    fn test_non_reentrant() {
+        // getBalance can see the balance before it is written
+        let program = bank("@nonReentrant", &format!("{}\n balance -= amount", CALL));
+        let error = checked(&program, &Target::Ether).err().unwrap();
+        assert!(error.contains("@nonReentrant does not lock getBalance which can observe it"));
+
+        let locked = program.replace(
+            "public func getBalance",
+            "@nonReentrant public func getBalance",
+        );
+        assert!(checked(&locked, &Target::Ether).is_ok());
+    }
+}
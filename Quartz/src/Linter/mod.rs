@@ -0,0 +1,395 @@
+use super::context::*;
+use super::visitor::*;
+use super::AST::*;
+use std::collections::HashMap;
+
+mod lints;
+use self::lints::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl LintLevel {
+    // The level named by an @allow, @warn or @deny attribute, or by the
+    // --allow, --warn and --deny flags
+    pub fn from_name(name: &str) -> Option<LintLevel> {
+        match name {
+            "allow" => Some(LintLevel::Allow),
+            "warn" => Some(LintLevel::Warn),
+            "deny" => Some(LintLevel::Deny),
+            _ => None,
+        }
+    }
+}
+
+// A finding of a lint, with the attributes of the declarations it was found in
+pub struct LintDiagnostic {
+    pub line_info: LineInfo,
+    pub message: String,
+    pub enclosing: Option<TypeIdentifier>,
+    pub attributes: Vec<Attribute>,
+}
+
+impl LintDiagnostic {
+    // A finding in a function or initialiser with the given attributes, which
+    // come after those of the contract behaviour block being visited
+    pub fn new(
+        line_info: LineInfo,
+        message: String,
+        attributes: &[Attribute],
+        ctx: &Context,
+    ) -> LintDiagnostic {
+        let mut attributes = attributes.to_vec();
+        if let Some(behaviour) = &ctx.ContractBehaviourDeclarationContext {
+            attributes = [behaviour.attributes.clone(), attributes].concat();
+        }
+
+        LintDiagnostic {
+            line_info,
+            message,
+            enclosing: ctx.enclosing_type_identifier().map(|i| i.token),
+            attributes,
+        }
+    }
+}
+
+// A check whose findings are reported as warnings, or as errors when it is
+// denied. Lints are visitors and are run by lint() in the order of
+// registered_lints().
+pub trait Lint: Visitor {
+    fn name(&self) -> &'static str;
+
+    fn default_level(&self) -> LintLevel;
+
+    fn diagnostics(&mut self) -> Vec<LintDiagnostic>;
+}
+
+pub fn registered_lints() -> Vec<Box<dyn Lint>> {
+    vec![
+        Box::new(FunctionLints::new(UnusedVariables {})),
+        Box::new(FunctionLints::new(UnusedParameters {})),
+        Box::new(FunctionLints::new(Shadowing {})),
+        Box::new(FunctionLints::new(UnreachableCode {})),
+        Box::new(UnusedProperties::new()),
+        Box::new(Reentrancy::new()),
+    ]
+}
+
+pub fn is_lint_name(name: &str) -> bool {
+    registered_lints().iter().any(|l| l.name() == name)
+}
+
+// Runs every registered lint over the module. The level of a finding is set by
+// the innermost @allow, @warn or @deny attribute naming the lint, then by the
+// command line, then by the lint itself.
+pub fn lint(module: &mut Module, ctx: &mut Context) -> VResult {
+    let type_attributes = type_attributes(module);
+    let mut unknown = vec![];
+    for attribute in lint_attributes(module) {
+        for name in &attribute.arguments {
+            if !is_lint_name(name) {
+                let message = format!(
+                    "Lint error on line {}: unknown lint {} in @{}",
+                    attribute.line_info.line, name, attribute.identifier_token
+                );
+                println!("{}", message);
+                unknown.push(message);
+            }
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(Box::from(unknown.join("\n")));
+    }
+
+    let mut denied = vec![];
+    for mut lint in registered_lints() {
+        let result = module.visit(lint.as_mut(), ctx);
+
+        match result {
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+
+        let mut diagnostics = lint.diagnostics();
+        diagnostics.sort_by_key(|d| d.line_info.line);
+        for diagnostic in diagnostics {
+            let enclosing_attributes = match &diagnostic.enclosing {
+                Some(enclosing) => type_attributes.get(enclosing).cloned().unwrap_or_default(),
+                None => vec![],
+            };
+
+            let level = attribute_level(&diagnostic.attributes, lint.name())
+                .or_else(|| attribute_level(&enclosing_attributes, lint.name()))
+                .or_else(|| ctx.environment.lint_levels.get(lint.name()).cloned())
+                .unwrap_or_else(|| lint.default_level());
+
+            match level {
+                LintLevel::Allow => {}
+                LintLevel::Warn => println!(
+                    "Warning on line {}: {} [{}]",
+                    diagnostic.line_info.line,
+                    diagnostic.message,
+                    lint.name()
+                ),
+                LintLevel::Deny => {
+                    let message = format!(
+                        "Error on line {}: {} [{}]",
+                        diagnostic.line_info.line,
+                        diagnostic.message,
+                        lint.name()
+                    );
+                    println!("{}", message);
+                    denied.push(message);
+                }
+            }
+        }
+    }
+
+    if !denied.is_empty() {
+        return Err(Box::from(denied.join("\n")));
+    }
+    Ok(())
+}
+
+// Statements after a return or become are reported by the unreachable_code
+// lint rather than rejected, and are dropped before code is generated
+pub fn remove_unreachable_code(module: &mut Module, ctx: &mut Context) -> VResult {
+    module.visit(&mut UnreachableCodeRemover {}, ctx)
+}
+
+// The last attribute naming the lint wins
+fn attribute_level(attributes: &[Attribute], lint: &str) -> Option<LintLevel> {
+    attributes
+        .iter()
+        .rev()
+        .find(|a| a.is_lint() && a.arguments.iter().any(|n| n == lint))
+        .and_then(|a| LintLevel::from_name(&a.identifier_token))
+}
+
+fn type_attributes(module: &Module) -> HashMap<TypeIdentifier, Vec<Attribute>> {
+    let mut attributes: HashMap<TypeIdentifier, Vec<Attribute>> = HashMap::new();
+    for declaration in &module.declarations {
+        let (identifier, declaration_attributes) = match declaration {
+            TopLevelDeclaration::ContractDeclaration(c) => (&c.identifier, &c.attributes),
+            TopLevelDeclaration::StructDeclaration(s) => (&s.identifier, &s.attributes),
+            TopLevelDeclaration::AssetDeclaration(a) => (&a.identifier, &a.attributes),
+            TopLevelDeclaration::EnumDeclaration(e) => (&e.identifier, &e.attributes),
+            TopLevelDeclaration::TraitDeclaration(t) => (&t.identifier, &t.attributes),
+            TopLevelDeclaration::ContractBehaviourDeclaration(_) => continue,
+        };
+        attributes
+            .entry(identifier.token.clone())
+            .or_default()
+            .extend(declaration_attributes.clone());
+    }
+    attributes
+}
+
+// Every lint attribute of the module, so that misspelt lints are reported even
+// when the lint finds nothing
+fn lint_attributes(module: &Module) -> Vec<Attribute> {
+    let mut attributes = vec![];
+    for declaration in &module.declarations {
+        match declaration {
+            TopLevelDeclaration::ContractDeclaration(c) => {
+                attributes.extend(c.attributes.clone());
+                for member in &c.contract_members {
+                    match member {
+                        ContractMember::VariableDeclaration(v) => {
+                            attributes.extend(v.attributes.clone())
+                        }
+                        ContractMember::EventDeclaration(e) => {
+                            attributes.extend(e.attributes.clone())
+                        }
+                    }
+                }
+            }
+            TopLevelDeclaration::ContractBehaviourDeclaration(c) => {
+                attributes.extend(c.attributes.clone());
+                for member in &c.members {
+                    match member {
+                        ContractBehaviourMember::FunctionDeclaration(f) => {
+                            attributes.extend(f.head.attributes.clone())
+                        }
+                        ContractBehaviourMember::SpecialDeclaration(s) => {
+                            attributes.extend(s.head.attributes.clone())
+                        }
+                        ContractBehaviourMember::FunctionSignatureDeclaration(f) => {
+                            attributes.extend(f.attributes.clone())
+                        }
+                        ContractBehaviourMember::SpecialSignatureDeclaration(s) => {
+                            attributes.extend(s.attributes.clone())
+                        }
+                    }
+                }
+            }
+            TopLevelDeclaration::StructDeclaration(s) => {
+                attributes.extend(s.attributes.clone());
+                for member in &s.members {
+                    match member {
+                        StructMember::VariableDeclaration(v) => {
+                            attributes.extend(v.attributes.clone())
+                        }
+                        StructMember::FunctionDeclaration(f) => {
+                            attributes.extend(f.head.attributes.clone())
+                        }
+                        StructMember::SpecialDeclaration(s) => {
+                            attributes.extend(s.head.attributes.clone())
+                        }
+                    }
+                }
+            }
+            TopLevelDeclaration::AssetDeclaration(a) => {
+                attributes.extend(a.attributes.clone());
+                for member in &a.members {
+                    match member {
+                        AssetMember::VariableDeclaration(v) => {
+                            attributes.extend(v.attributes.clone())
+                        }
+                        AssetMember::FunctionDeclaration(f) => {
+                            attributes.extend(f.head.attributes.clone())
+                        }
+                        AssetMember::SpecialDeclaration(s) => {
+                            attributes.extend(s.head.attributes.clone())
+                        }
+                    }
+                }
+            }
+            TopLevelDeclaration::EnumDeclaration(e) => attributes.extend(e.attributes.clone()),
+            TopLevelDeclaration::TraitDeclaration(t) => attributes.extend(t.attributes.clone()),
+        }
+    }
+    attributes.into_iter().filter(|a| a.is_lint()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::LintLevel;
+    use crate::AstProcessor::tests::{checked, checked_with_lint_levels, compile};
+    use crate::AstProcessor::Target;
+
+    fn program(attribute: &str) -> String {
+        format!(
+            "
+            contract Counter {{
+              var total: Int = 0
+            }}
+            Counter :: caller <- (any) {{
+              public init() {{}}
+
+              {}
+              public func get() -> Int mutates (total) {{
+                return total
+                total = 2
+              }}
+            }}
+            ",
+            attribute
+        )
+    }
+
+    #[test]
+    fn test_unreachable_code() {
+        let error = checked(&program("@deny(unreachable_code)"), &Target::Ether)
+            .err()
+            .unwrap();
+        assert!(error.contains("Error on line 10"));
+        assert!(error.contains("[unreachable_code]"));
+
+        let output = compile(&program(""), Target::Ether).unwrap();
+        assert!(output.contains("sstore(add(0, 0), 0)"));
+        assert!(!output.contains("sstore(add(0, 0), 2)"));
+    }
+
+    // A counter whose get has an unused parameter, an unused variable and a
+    // variable hiding a property, leaving both properties never read
+    fn findings(contract_attribute: &str, get_attribute: &str) -> String {
+        format!(
+            "
+            {}
+            contract Counter {{
+              var total: Int = 0
+              var spare: Int = 0
+            }}
+            Counter :: caller <- (any) {{
+              public init() {{}}
+
+              {}
+              public func get(step: Int) -> Int {{
+                let total: Int = 1
+                let extra: Int = 2
+                return total
+              }}
+            }}
+            ",
+            contract_attribute, get_attribute
+        )
+    }
+
+    fn denied(contract_attribute: &str, get_attribute: &str) -> String {
+        checked(&findings(contract_attribute, get_attribute), &Target::Ether)
+            .err()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_unused_variables() {
+        assert_eq!(
+            denied("", "@deny(unused_variables)"),
+            "Error on line 13: Variable extra is never read [unused_variables]"
+        );
+    }
+
+    #[test]
+    fn test_unused_parameters() {
+        assert_eq!(
+            denied("", "@deny(unused_parameters)"),
+            "Error on line 11: Parameter step is never read [unused_parameters]"
+        );
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let error = denied("", "@deny(shadowing)");
+        assert!(error.starts_with("Error on line 12: Variable total shadows"));
+        assert!(error.ends_with("[shadowing]"));
+        assert_eq!(error.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_unused_properties() {
+        assert_eq!(
+            denied("@deny(unused_properties)", ""),
+            "Error on line 4: Property total of Counter is never read [unused_properties]\n\
+             Error on line 5: Property spare of Counter is never read [unused_properties]"
+        );
+    }
+
+    #[test]
+    fn test_attribute_overrides_command_line() {
+        let program = findings("", "@allow(unused_variables)");
+        let levels = [("unused_variables", LintLevel::Deny)];
+        assert!(checked_with_lint_levels(&program, &Target::Ether, &levels).is_ok());
+
+        let program = findings("", "@deny(unused_variables)");
+        let levels = [("unused_variables", LintLevel::Allow)];
+        assert!(checked_with_lint_levels(&program, &Target::Ether, &levels).is_err());
+
+        let program = findings("", "");
+        let levels = [("unused_variables", LintLevel::Deny)];
+        assert!(checked_with_lint_levels(&program, &Target::Ether, &levels).is_err());
+    }
+
+    #[test]
+    fn test_unknown_lint() {
+        assert_eq!(
+            denied("", "@deny(unused_things)"),
+            "Lint error on line 10: unknown lint unused_things in @deny"
+        );
+    }
+}
@@ -2,13 +2,21 @@
 use crate::context::*;
 use crate::environment::*;
 use crate::visitor::Visitor;
-use crate::MoveCodeGen::{FunctionContext, MoveExpression, MoveIRBlock, MoveStatement};
+use crate::SolidityCodeGen::SolidityPreProcessor::default_assignments;
+use crate::MoveCodeGen::{
+    FunctionContext, MoveExpression, MoveIRBlock, MoveRuntimeFunction, MoveRuntimeTypes,
+};
 use crate::AST::*;
 use std::env::var;
 
 pub(crate) struct MovePreProcessor {}
 
 impl Visitor for MovePreProcessor {
+    fn start_module(&mut self, _t: &mut Module, _ctx: &mut Context) -> VResult {
+        _ctx.environment.resolve_currency(&"Libra".to_string());
+        Ok(())
+    }
+
     fn start_contract_behaviour_declaration(
         &mut self,
         _t: &mut ContractBehaviourDeclaration,
@@ -114,6 +122,8 @@ impl Visitor for MovePreProcessor {
         _t: &mut VariableDeclaration,
         _ctx: &mut Context,
     ) -> VResult {
+        _t.variable_type.resolve_currency(&"Libra".to_string());
+        check_element_types(&_t.variable_type, &_t.identifier.line_info, &_ctx.environment)?;
         if _ctx.in_function_or_special() {
             if _ctx.scope_context().is_some() {
                 let context_ref = _ctx.ScopeContext.as_mut().unwrap();
@@ -148,11 +158,7 @@ impl Visitor for MovePreProcessor {
             .token
             .clone();
 
-        let mangled_name = mangle_function_move(
-            _t.head.identifier.token.clone(),
-            &enclosing_identifier,
-            false,
-        );
+        let mangled_name = mangle_function_declaration(_t, &enclosing_identifier, _ctx);
         _t.mangledIdentifier = Some(mangled_name);
 
         if _t.is_payable() {
@@ -187,6 +193,7 @@ impl Visitor for MovePreProcessor {
             _t.head.parameters = parameters;
 
             let lhs = VariableDeclaration {
+                attributes: vec![],
                 declaration_token: None,
                 identifier: Identifier {
                     token: "amount".to_string(),
@@ -383,6 +390,7 @@ impl Visitor for MovePreProcessor {
         } else {
 
             let variable_declaration = VariableDeclaration {
+                attributes: vec![],
                 declaration_token: None,
                 identifier: Identifier {
                     token: "ret".to_string(),
@@ -411,32 +419,13 @@ impl Visitor for MovePreProcessor {
         _t: &mut SpecialDeclaration,
         _ctx: &mut Context,
     ) -> VResult {
-        let members = _t.body.clone();
-
-        let members = members
-            .into_iter()
-            .filter_map(|m| {
-                if let Statement::Expression(e) = m.clone() {
-                    if let Expression::BinaryExpression(b) = e {
-                        if let BinOp::Equal = b.op.clone() {
-                            if let Expression::DictionaryLiteral(_) = *b.rhs_expression {
-                                None
-                            } else {
-                                Some(m)
-                            }
-                        } else {
-                            Some(m)
-                        }
-                    } else {
-                        Some(m)
-                    }
-                } else {
-                    Some(m)
-                }
-            })
-            .collect();
+        if _ctx.ContractBehaviourDeclarationContext.is_some() && _t.is_init() {
+            let default_assignments = default_assignments(_ctx);
+            for d in default_assignments.into_iter().rev() {
+                _t.body.insert(0, d);
+            }
+        }
 
-        _t.body = members;
         if _ctx.ContractBehaviourDeclarationContext.is_some() {
             let b_ctx = _ctx.ContractBehaviourDeclarationContext.clone();
             let b_ctx = b_ctx.unwrap();
@@ -617,7 +606,15 @@ impl Visitor for MovePreProcessor {
         Ok(())
     }
 
+    fn start_statement(&mut self, _t: &mut Statement, _ctx: &mut Context) -> VResult {
+        // Property accesses leave their receivers on the trail, as only calls
+        // consume it
+        _ctx.FunctionCallReceiverTrail = vec![];
+        Ok(())
+    }
+
     fn start_function_call(&mut self, _t: &mut FunctionCall, _ctx: &mut Context) -> VResult {
+        _t.resolve_currency(&"Libra".to_string());
         let mut receiver_trail = _ctx.FunctionCallReceiverTrail.clone();
 
 
@@ -723,7 +720,8 @@ impl Visitor for MovePreProcessor {
                         ),
                     };
 
-                    if !result_type.is_inout_type() {
+                    if _ctx.IsExternalFunctionCall && is_contract_reference(&result_type, _ctx) {
+                    } else if !result_type.is_inout_type() {
                         let inout = InoutExpression {
                             ampersand_token: "".to_string(),
                             expression: Box::new(expression.clone()),
@@ -758,6 +756,7 @@ impl Visitor for MovePreProcessor {
         let mut borrow_local = false;
         let function_argument = _t.clone();
         let mut expression = function_argument.expression.clone();
+        let is_inout = matches!(expression, Expression::InoutExpression(_));
         if let Expression::InoutExpression(i) = function_argument.expression.clone() {
             expression = *i.expression.clone();
 
@@ -804,6 +803,16 @@ impl Visitor for MovePreProcessor {
                 if ident.enclosing_type.is_some() {
                     let ident_enclosing = ident.enclosing_type.clone();
                     expression = pre_assign(expression, _ctx, borrow_local, true);
+                } else if is_inout {
+                    // Coins held in locals are borrowed when passed by reference,
+                    // rather than moved into the callee
+                    let local_type = _ctx
+                        .ScopeContext
+                        .as_ref()
+                        .and_then(|s| s.type_for(ident.token.clone()));
+                    if local_type.is_some_and(|t| t.is_currency_type()) {
+                        expression = function_argument.expression.clone();
+                    }
                 }
             }
             Expression::BinaryExpression(b) => {
@@ -822,14 +831,55 @@ impl Visitor for MovePreProcessor {
         Ok(())
     }
 
+    fn start_parameter(&mut self, _t: &mut Parameter, _ctx: &mut Context) -> VResult {
+        check_element_types(&_t.type_assignment, &_t.line_info, &_ctx.environment)
+    }
+
     fn start_type(&mut self, _t: &mut Type, _ctx: &mut Context) -> VResult {
-        if _t.is_external_contract(_ctx.environment.clone()) {
+        _t.resolve_currency(&"Libra".to_string());
+        if _t.is_external_contract(_ctx.environment.clone()) || is_contract_reference(_t, _ctx) {
             *_t = Type::Address
         }
         Ok(())
     }
 }
 
+// Dictionaries are searched by key, so their keys must be comparable values
+// with a default, while their values may also be structs or coins
+fn check_element_types(t: &Type, line_info: &LineInfo, environment: &Environment) -> VResult {
+    let dictionary = match t {
+        Type::DictionaryType(d) => d,
+        Type::FixedSizedArrayType(a) => {
+            return check_element_types(&a.key_type, line_info, environment)
+        }
+        Type::ArrayType(a) => return check_element_types(&a.key_type, line_info, environment),
+        Type::InoutType(i) => return check_element_types(&i.key_type, line_info, environment),
+        _ => return Ok(()),
+    };
+    let value_type = &*dictionary.value_type;
+    let supported_value = match value_type {
+        Type::UserDefinedType(i) => {
+            value_type.is_currency_type()
+                || (environment.is_struct_declared(&i.token)
+                    && !value_type.is_external_module(environment.clone()))
+        }
+        _ => MoveRuntimeTypes::default_element(value_type).is_some(),
+    };
+    let unsupported = if MoveRuntimeTypes::default_element(&dictionary.key_type).is_none() {
+        format!("keyed by {}", dictionary.key_type.name())
+    } else if !supported_value {
+        format!("of {}", value_type.name())
+    } else {
+        return Ok(());
+    };
+    let message = format!(
+        "Type error on line {}: dictionaries {} are not supported on the libra target",
+        line_info.line, unsupported
+    );
+    println!("{}", message);
+    Err(Box::from(message))
+}
+
 pub fn convert_default_parameter_functions(
     base: FunctionDeclaration,
     t: &TypeIdentifier,
@@ -1033,6 +1083,7 @@ pub fn generate_contract_wrapper(
     let original_parameter = original_parameter;
 
     let self_declaration = VariableDeclaration {
+        attributes: vec![],
         declaration_token: None,
         identifier: Identifier {
             token: "self".to_string(),
@@ -1084,6 +1135,7 @@ pub fn generate_contract_wrapper(
         wrapper.body.insert(
             0,
             Statement::Expression(Expression::VariableDeclaration(VariableDeclaration {
+                attributes: vec![],
                 declaration_token: None,
                 identifier: Identifier {
                     token: mangle(caller.token.clone()),
@@ -1145,6 +1197,19 @@ pub fn generate_contract_wrapper(
         wrapper.body.push(assertion)
     }
 
+    // The lock is taken once the caller is checked and released before returning
+    let mut unlock = vec![];
+    if function.is_non_reentrant() {
+        let lock = |runtime: MoveRuntimeFunction| {
+            Statement::Expression(Expression::RawAssembly(
+                format!("{}(copy(this))", runtime.mangle_runtime()),
+                None,
+            ))
+        };
+        wrapper.body.push(lock(MoveRuntimeFunction::Lock));
+        unlock.push(lock(MoveRuntimeFunction::Unlock));
+    }
+
     let arguments = function
         .head
         .parameters
@@ -1170,7 +1235,8 @@ pub fn generate_contract_wrapper(
     if function.is_void() {
         wrapper
             .body
-            .push(Statement::Expression(function_call.clone()))
+            .push(Statement::Expression(function_call.clone()));
+        wrapper.body.append(&mut unlock);
     }
 
     wrapper
@@ -1183,6 +1249,7 @@ pub fn generate_contract_wrapper(
                     Some(function_call.clone())
                 }
             },
+            cleanup: unlock,
             ..Default::default()
         }));
 
@@ -1248,7 +1315,9 @@ pub fn pre_assign(
         scope.clone(),
     );
 
-    if expression_type.is_external_contract(ctx.environment.clone()) {
+    if expression_type.is_external_contract(ctx.environment.clone())
+        || is_contract_reference(&expression_type, ctx)
+    {
         expression_type = Type::Address
     }
 
@@ -1294,6 +1363,7 @@ pub fn pre_assign(
         })
         .collect();
     let mut declaration = VariableDeclaration {
+        attributes: vec![],
         declaration_token: None,
         identifier: Default::default(),
         variable_type: Type::Bool,
@@ -1303,6 +1373,7 @@ pub fn pre_assign(
         temp_identifier = scope.fresh_identifier(expression.clone().get_line_info());
         declaration = if expression_type.is_built_in_type() || borrow {
             VariableDeclaration {
+                attributes: vec![],
                 declaration_token: None,
                 identifier: temp_identifier.clone(),
                 variable_type: expression_type,
@@ -1310,6 +1381,7 @@ pub fn pre_assign(
             }
         } else {
             let var = VariableDeclaration {
+                attributes: vec![],
                 declaration_token: None,
                 identifier: temp_identifier.clone(),
                 variable_type: Type::InoutType(InoutType {
@@ -1484,20 +1556,11 @@ pub fn mangle_function_call_name(function_call: &FunctionCall, ctx: &Context) ->
         );
 
         match match_result {
-            FunctionCallMatchResult::MatchedFunction(fi) => {
-                let declaration = fi.declaration;
-                let param_types = declaration.head.parameters;
-                let param_types: Vec<Type> = param_types
-                    .clone()
-                    .into_iter()
-                    .map(|p| p.type_assignment)
-                    .collect();
-                Some(mangle_function_move(
-                    declaration.head.identifier.token,
-                    &enclosing_type,
-                    false,
-                ))
-            }
+            FunctionCallMatchResult::MatchedFunction(fi) => Some(mangle_function_declaration(
+                &fi.declaration,
+                &enclosing_type,
+                ctx,
+            )),
             FunctionCallMatchResult::MatchedFunctionWithoutCaller(c) => {
                 if c.candidates.len() != 1 {
                     panic!("Unable to find function declaration")
@@ -1506,18 +1569,10 @@ pub fn mangle_function_call_name(function_call: &FunctionCall, ctx: &Context) ->
                 let candidate = c.candidates.clone().remove(0);
 
                 if let CallableInformation::FunctionInformation(fi) = candidate {
-                    let declaration = fi.declaration;
-                    let param_types = declaration.head.parameters;
-                    let param_types: Vec<Type> = param_types
-                        .clone()
-                        .into_iter()
-                        .map(|p| p.type_assignment)
-                        .collect();
-
-                    return Some(mangle_function_move(
-                        declaration.head.identifier.token,
+                    return Some(mangle_function_declaration(
+                        &fi.declaration,
                         &enclosing_type,
-                        false,
+                        ctx,
                     ));
                 } else {
                     panic!("Non-function CallableInformation where function expected")
@@ -1532,15 +1587,11 @@ pub fn mangle_function_call_name(function_call: &FunctionCall, ctx: &Context) ->
                 ))
             }
             FunctionCallMatchResult::MatchedFallback(_) => unimplemented!(),
-            FunctionCallMatchResult::MatchedGlobalFunction(fi) => {
-                let declaration = fi.declaration;
-
-                Some(mangle_function_move(
-                    declaration.head.identifier.token,
-                    &"Quartz_Global".to_string(),
-                    false,
-                ))
-            }
+            FunctionCallMatchResult::MatchedGlobalFunction(fi) => Some(mangle_function_declaration(
+                &fi.declaration,
+                &"Quartz_Global".to_string(),
+                ctx,
+            )),
             FunctionCallMatchResult::Failure(lol) => None,
         }
     } else {
@@ -1550,7 +1601,45 @@ pub fn mangle_function_call_name(function_call: &FunctionCall, ctx: &Context) ->
     }
 }
 
+// Move has no overloading, so overloads are told apart by their labels
+fn mangle_function_declaration(
+    declaration: &FunctionDeclaration,
+    enclosing: &TypeIdentifier,
+    ctx: &Context,
+) -> String {
+    let name = declaration.head.identifier.token.clone();
+    let overloads = ctx
+        .environment
+        .types
+        .get(enclosing)
+        .and_then(|t| t.functions.get(&name))
+        .map_or(0, |f| f.len());
+    if overloads < 2 {
+        return mangle_function_move(name, enclosing, false);
+    }
+
+    let labels: Vec<String> = declaration
+        .head
+        .parameters
+        .iter()
+        .map(|p| p.identifier.token.clone())
+        .collect();
+    mangle_function_move(
+        format!("{}_{}", name, labels.join("_")),
+        enclosing,
+        false,
+    )
+}
+
 pub fn is_global_function_call(function_call: FunctionCall, ctx: &Context) -> bool {
+    // Calls on a receiver, such as funds.send(to: a, amount: x), belong to its type
+    // even when a global function accepts the same arguments
+    if let Some(receiver) = ctx.FunctionCallReceiverTrail.last() {
+        if *receiver != Expression::SelfExpression {
+            return false;
+        }
+    }
+
     let enclosing = ctx.enclosing_type_identifier().clone();
     let enclosing = enclosing.unwrap();
     let enclosing = enclosing.token.clone();
@@ -1576,6 +1665,22 @@ pub fn is_global_function_call(function_call: FunctionCall, ctx: &Context) -> bo
     return false;
 }
 
+// A value whose type is another contract of the same build. Such values are
+// stored as the address the contract was published at.
+pub fn is_contract_reference(t: &Type, ctx: &Context) -> bool {
+    let mut internal_type = t.clone();
+    if let Type::InoutType(i) = internal_type {
+        internal_type = *i.key_type;
+    }
+
+    if let Type::UserDefinedType(u) = internal_type {
+        let enclosing = ctx.enclosing_type_identifier();
+        let enclosing = enclosing.unwrap_or_default();
+        return ctx.environment.is_contract_declared(&u.token) && u.token != enclosing.token;
+    }
+    false
+}
+
 pub fn construct_expression(expressions: Vec<Expression>) -> Expression {
     let mut expression = expressions.clone();
     if expression.len() > 1 {
@@ -0,0 +1,593 @@
+use std::collections::HashSet;
+
+// Renders the Move IR produced by `MoveContract` as Move source. The IR is
+// re-tokenised rather than regenerated so that the runtime functions, which
+// are written as IR text, go through the same translation as generated code.
+
+pub const DEFAULT_COIN_MODULE: &str = "0x1::LibraCoin::T";
+pub const DEFAULT_ACCOUNT_MODULE: &str = "0x1::LibraAccount";
+
+// The coin resource that Libra maps to, and the account module that coins are
+// deposited through
+#[derive(Debug, Clone)]
+pub struct CoinModule {
+    pub address: String,
+    pub module: String,
+    pub resource: String,
+    pub account_address: String,
+    pub account_module: String,
+}
+
+impl CoinModule {
+    pub fn from(coin: &str, account: &str) -> Result<CoinModule, String> {
+        let coin = if coin.is_empty() { DEFAULT_COIN_MODULE } else { coin };
+        let account = if account.is_empty() {
+            DEFAULT_ACCOUNT_MODULE
+        } else {
+            account
+        };
+        let coin_parts: Vec<&str> = coin.split("::").collect();
+        if coin_parts.len() != 3 || coin_parts.iter().any(|p| p.is_empty()) {
+            return Err(format!(
+                "Coin module {} must have the form <address>::<module>::<type>",
+                coin
+            ));
+        }
+        let account_parts: Vec<&str> = account.split("::").collect();
+        if account_parts.len() != 2 || account_parts.iter().any(|p| p.is_empty()) {
+            return Err(format!(
+                "Account module {} must have the form <address>::<module>",
+                account
+            ));
+        }
+        Ok(CoinModule {
+            address: coin_parts[0].to_string(),
+            module: coin_parts[1].to_string(),
+            resource: coin_parts[2].to_string(),
+            account_address: account_parts[0].to_string(),
+            account_module: account_parts[1].to_string(),
+        })
+    }
+}
+
+impl Default for CoinModule {
+    fn default() -> Self {
+        CoinModule::from(DEFAULT_COIN_MODULE, DEFAULT_ACCOUNT_MODULE).unwrap()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    space: String,
+    text: String,
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Import(String, String),
+    Struct(bool, String, Vec<Token>),
+    Function(Function),
+}
+
+#[derive(Debug, Clone)]
+struct Function {
+    space: String,
+    public: bool,
+    name: String,
+    parameters: Vec<Token>,
+    signature: Vec<Token>,
+    body: Vec<Token>,
+}
+
+// `linked` holds the public functions of previously translated modules that
+// take the signer, as `Module.function`, and is extended with this module's
+// own, so that modules translated later pass `account` on when calling them
+pub fn translate(ir: &str, coin: &CoinModule, linked: &mut HashSet<String>) -> String {
+    let tokens = tokenise(ir);
+    if tokens.len() < 3 || tokens[0].text != "module" {
+        panic!("Expected a Move IR module")
+    }
+    let name = tokens[1].text.clone();
+    let items = items(&tokens[3..tokens.len() - 1]);
+
+    let functions: Vec<Function> = items
+        .iter()
+        .filter_map(|i| match i {
+            Item::Function(f) => Some(f.clone()),
+            _ => None,
+        })
+        .collect();
+    let signers = signer_functions(&functions, linked);
+
+    let mut translator = Translator {
+        coin: coin.clone(),
+        modules: vec![],
+        signers,
+        linked: linked.clone(),
+    };
+
+    let mut code = format!("module quartz::{} {{\n", name);
+    code.push_str("  use std::signer;\n  use std::vector;\n");
+    code.push_str(&format!("  use {}::{};\n", coin.address, coin.module));
+    code.push_str(&format!(
+        "  use {}::{};\n",
+        coin.account_address, coin.account_module
+    ));
+    for item in &items {
+        if let Item::Import(address, module) = item {
+            if module == "LibraCoin" || module == "LibraAccount" || module == "Vector" {
+                continue;
+            }
+            translator.modules.push(module.clone());
+            let address = if address == "{{default}}" {
+                "quartz".to_string()
+            } else {
+                address.clone()
+            };
+            code.push_str(&format!("  use {}::{};\n", address, module));
+        }
+    }
+
+    for item in items {
+        match item {
+            Item::Import(_, _) => {}
+            Item::Struct(resource, name, body) => {
+                let abilities = if !resource {
+                    "copy, drop, store"
+                } else if name == "T" {
+                    "key"
+                } else {
+                    "store"
+                };
+                code.push_str(&format!(
+                    "\n  struct {} has {} {{{}\n",
+                    name,
+                    abilities,
+                    translator.translate(&body)
+                ));
+            }
+            Item::Function(f) => {
+                let public = if f.public { "public " } else { "" };
+                let mut parameters = translator.translate(&f.parameters);
+                if translator.signers.contains(&f.name) {
+                    parameters = if parameters.trim().is_empty() {
+                        "account: &signer".to_string()
+                    } else {
+                        format!("account: &signer, {}", parameters.trim_start())
+                    };
+                }
+                code.push_str(&format!(
+                    "{}{}fun {}({}){} {{{}",
+                    f.space,
+                    public,
+                    f.name,
+                    parameters,
+                    translator.translate(&f.signature),
+                    translator.translate(&f.body)
+                ));
+            }
+        }
+    }
+    code.push_str("\n}\n");
+
+    for function in functions {
+        if function.public && translator.signers.contains(&function.name) {
+            linked.insert(format!("{}.{}", name, function.name));
+        }
+    }
+    code
+}
+
+fn tokenise(code: &str) -> Vec<Token> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut tokens = vec![];
+    let mut space = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            space.push(c);
+            i += 1;
+            continue;
+        } else if c == '/' && i + 1 < chars.len() && chars[i + 1] == '/' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            space.extend(&chars[start..i]);
+            continue;
+        } else if c.is_ascii_alphanumeric() || c == '_' || c == '$' {
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+            {
+                i += 1;
+            }
+        } else if i + 1 < chars.len()
+            && ["::", "==", "!=", "<=", ">=", "&&", "||"]
+                .contains(&&*chars[i..i + 2].iter().collect::<String>())
+        {
+            i += 2;
+        } else {
+            i += 1;
+        }
+        tokens.push(Token {
+            space: space.clone(),
+            text: chars[start..i].iter().collect(),
+        });
+        space = String::new();
+    }
+    tokens
+}
+
+fn is_identifier(token: &str) -> bool {
+    token
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+}
+
+// Index of the token closing the bracket opened at `start`
+fn closing(tokens: &[Token], start: usize) -> usize {
+    let open = tokens[start].text.clone();
+    let close = match open.as_str() {
+        "(" => ")",
+        "{" => "}",
+        _ => panic!("Unexpected bracket {}", open),
+    };
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        if token.text == open {
+            depth += 1;
+        } else if token.text == close {
+            depth -= 1;
+            if depth == 0 {
+                return i;
+            }
+        }
+    }
+    panic!("Unbalanced {} in Move IR", open)
+}
+
+fn items(tokens: &[Token]) -> Vec<Item> {
+    let mut items = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i].text.as_str() {
+            "import" => {
+                let mut end = i + 1;
+                while tokens[end].text != ";" {
+                    end += 1;
+                }
+                let address: Vec<String> = tokens[i + 1..end - 2]
+                    .iter()
+                    .map(|t| t.text.clone())
+                    .collect();
+                let module = tokens[end - 1].text.clone();
+                items.push(Item::Import(address.join(""), module));
+                i = end + 1;
+            }
+            "resource" | "struct" => {
+                let close = closing(tokens, i + 2);
+                items.push(Item::Struct(
+                    tokens[i].text == "resource",
+                    tokens[i + 1].text.clone(),
+                    tokens[i + 3..=close].to_vec(),
+                ));
+                i = close + 1;
+            }
+            _ => {
+                let space = tokens[i].space.clone();
+                let public = tokens[i].text == "public";
+                if public {
+                    i += 1;
+                }
+                let name = tokens[i].text.clone();
+                let parameters_close = closing(tokens, i + 1);
+                let parameters = tokens[i + 2..parameters_close].to_vec();
+
+                let mut body_open = parameters_close + 1;
+                while tokens[body_open].text != "{" {
+                    body_open += 1;
+                }
+                let body_close = closing(tokens, body_open);
+                items.push(Item::Function(Function {
+                    space,
+                    public,
+                    name,
+                    parameters,
+                    signature: tokens[parameters_close + 1..body_open].to_vec(),
+                    body: tokens[body_open + 1..=body_close].to_vec(),
+                }));
+                i = body_close + 1;
+            }
+        }
+    }
+    items
+}
+
+// Functions that need the transaction signer, either directly or through a
+// function they call
+fn signer_functions(functions: &Vec<Function>, linked: &HashSet<String>) -> HashSet<String> {
+    let mut signers = HashSet::new();
+    loop {
+        let mut changed = false;
+        for function in functions {
+            if signers.contains(&function.name) {
+                continue;
+            }
+            let body = &function.body;
+            let needs_signer = body.iter().enumerate().any(|(i, t)| {
+                let member = body.get(i + 2).map(|m| m.text.clone()).unwrap_or_default();
+                let is_call = body.get(i + 1).map(|d| d.text.as_str()) == Some(".");
+                t.text == "get_txn_sender"
+                    || t.text == "move_to_sender"
+                    || (is_call && t.text == "Self" && signers.contains(&member))
+                    || (is_call && linked.contains(&format!("{}.{}", t.text, member)))
+            });
+            if needs_signer {
+                signers.insert(function.name.clone());
+                changed = true;
+            }
+        }
+        if !changed {
+            return signers;
+        }
+    }
+}
+
+struct Translator {
+    coin: CoinModule,
+    modules: Vec<String>,
+    signers: HashSet<String>,
+    linked: HashSet<String>,
+}
+
+impl Translator {
+    fn translate(&self, tokens: &[Token]) -> String {
+        let mut code = String::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            let text = token.text.as_str();
+            let next = tokens.get(i + 1).map(|t| t.text.as_str());
+            code.push_str(&token.space);
+
+            if (text == "copy" || text == "move")
+                && next == Some("(")
+                && i + 3 < tokens.len()
+                && tokens[i + 3].text == ")"
+                && is_identifier(&tokens[i + 2].text)
+            {
+                let transfer = format!("{} {}", text, self.identifier(&tokens[i + 2].text));
+                if tokens.get(i + 4).map(|t| t.text.as_str()) == Some(".") {
+                    code.push_str(&format!("({})", transfer));
+                } else {
+                    code.push_str(&transfer);
+                }
+                i += 4;
+            } else if text == "Self" && next == Some(".") && i + 2 < tokens.len() {
+                let name = tokens[i + 2].text.clone();
+                code.push_str(&self.identifier(&name));
+                i += 3;
+                if self.signers.contains(&name) {
+                    i = self.pass_signer(&mut code, tokens, i);
+                }
+            } else if text == "get_txn_sender" && next == Some("(") {
+                code.push_str("signer::address_of(account)");
+                i += 3;
+            } else if text == "move_to_sender" {
+                code.push_str("move_to");
+                i += 1;
+                while tokens[i].text != "(" {
+                    code.push_str(&tokens[i].space);
+                    code.push_str(&tokens[i].text);
+                    i += 1;
+                }
+                code.push_str("(account, ");
+                i += 1;
+            } else if text == "assert" && next == Some("(") {
+                code.push_str("assert!");
+                i += 1;
+            } else if text == "bytearray" {
+                code.push_str("vector<u8>");
+                i += 1;
+            } else if text == "h" && next.is_some_and(|n| n.starts_with('"')) {
+                code.push_str("x");
+                i += 1;
+            } else if text.starts_with("0x") {
+                code.push_str(&format!("@{}", text));
+                i += 1;
+            } else if next == Some(".") && i + 2 < tokens.len() && self.is_module(text) {
+                let member = tokens[i + 2].text.clone();
+                code.push_str(&self.path(text, &member));
+                i += 3;
+                if self.linked.contains(&format!("{}.{}", text, member)) {
+                    i = self.pass_signer(&mut code, tokens, i);
+                }
+            } else if is_identifier(text) {
+                code.push_str(&self.identifier(text));
+                i += 1;
+            } else {
+                code.push_str(text);
+                i += 1;
+            }
+        }
+        code
+    }
+
+    // Passes `account` on to a call of a function that takes the signer
+    fn pass_signer(&self, code: &mut String, tokens: &[Token], i: usize) -> usize {
+        if tokens.get(i).map(|t| t.text.as_str()) != Some("(") {
+            return i;
+        }
+        code.push_str("(account");
+        if tokens.get(i + 1).map(|t| t.text.as_str()) != Some(")") {
+            code.push_str(", ");
+        }
+        i + 1
+    }
+
+    fn is_module(&self, name: &str) -> bool {
+        name == "LibraCoin"
+            || name == "LibraAccount"
+            || name == "Vector"
+            || self.modules.iter().any(|m| m == name)
+    }
+
+    fn path(&self, module: &str, member: &str) -> String {
+        match module {
+            "Vector" => format!("vector::{}", member),
+            "LibraCoin" if member == "T" => {
+                format!("{}::{}", self.coin.module, self.coin.resource)
+            }
+            "LibraCoin" => format!("{}::{}", self.coin.module, member),
+            "LibraAccount" => format!("{}::{}", self.coin.account_module, member),
+            _ => format!("{}::{}", module, member),
+        }
+    }
+
+    // `$` is valid in Move IR identifiers but not in Move source
+    fn identifier(&self, name: &str) -> String {
+        name.replace("$", "_")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{translate, CoinModule};
+    use std::collections::HashSet;
+
+    const COUNTER: &str = "module Counter {
+  import 0x0.LibraCoin;
+  import 0x0.LibraAccount;
+  import 0x0.Vector;
+
+  resource T {
+    value: u64,
+    owner: address
+  }
+
+  public get(this: &mut Self.T): u64 {
+    return *&mut copy(this).value;
+  }
+
+  public publish(value: u64) {
+    let owner: address;
+    owner = get_txn_sender();
+    move_to_sender<T>(T { value: move(value), owner: move(owner) });
+    return;
+  }
+
+  pay(money: LibraCoin.T, to: address) {
+    assert(copy(to) != 0x0, 1);
+    LibraAccount.deposit(move(to), move(money));
+    return;
+  }
+}";
+
+    #[test]
+    fn test_translate() {
+        let mut linked = HashSet::new();
+        let code = translate(COUNTER, &CoinModule::default(), &mut linked);
+        assert_eq!(
+            code,
+            "module quartz::Counter {
+  use std::signer;
+  use std::vector;
+  use 0x1::LibraCoin;
+  use 0x1::LibraAccount;
+
+  struct T has key {
+    value: u64,
+    owner: address
+  }
+
+
+  public fun get(this: &mut T): u64 {
+    return *&mut (copy this).value;
+  }
+
+  public fun publish(account: &signer, value: u64) {
+    let owner: address;
+    owner = signer::address_of(account);
+    move_to<T>(account, T { value: move value, owner: move owner });
+    return;
+  }
+
+  fun pay(money: LibraCoin::T, to: address) {
+    assert!(copy to != @0x0, 1);
+    LibraAccount::deposit(move to, move money);
+    return;
+  }
+}
+"
+        );
+        assert!(linked.contains("Counter.publish"));
+    }
+
+    #[test]
+    fn test_signer_forwarding() {
+        let store = "module Store {
+  resource T {
+    value: u64
+  }
+
+  public set(value: u64) {
+    move_to_sender<T>(T { value: move(value) });
+    return;
+  }
+}";
+        let shop = "module Shop {
+  import 0x0.LibraCoin;
+  import {{default}}.Store;
+  import 0x0.Vector;
+
+  public stock(value: u64) {
+    Self.restock(move(value));
+    return;
+  }
+
+  restock(value: u64) {
+    Store.set(move(value));
+    return;
+  }
+}";
+        let mut linked = HashSet::new();
+        translate(store, &CoinModule::default(), &mut linked);
+        assert!(linked.contains("Store.set"));
+        let code = translate(shop, &CoinModule::default(), &mut linked);
+        assert!(code.starts_with(
+            "module quartz::Shop {
+  use std::signer;
+  use std::vector;
+  use 0x1::LibraCoin;
+  use 0x1::LibraAccount;
+  use quartz::Store;
+"
+        ));
+        assert!(code.contains("public fun stock(account: &signer, value: u64) {"));
+        assert!(code.contains("restock(account, move value);"));
+        assert!(code.contains("fun restock(account: &signer, value: u64) {"));
+        assert!(code.contains("Store::set(account, move value);"));
+        assert!(linked.contains("Shop.stock"));
+        assert!(!linked.contains("Shop.restock"));
+    }
+
+    #[test]
+    fn test_coin_module() {
+        let coin = CoinModule::from("0x2::Coin::Coin", "0x3::Bank").unwrap();
+        let code = translate(COUNTER, &coin, &mut HashSet::new());
+        assert!(code.contains("  use 0x2::Coin;\n  use 0x3::Bank;\n"));
+        assert!(code.contains("fun pay(money: Coin::Coin, to: address)"));
+        assert!(code.contains("Bank::deposit(move to, move money);"));
+    }
+
+    #[test]
+    fn test_malformed_coin_module() {
+        let error = CoinModule::from("LibraCoin", "").unwrap_err();
+        assert!(error.contains("must have the form <address>::<module>::<type>"));
+        let error = CoinModule::from("", "0x1::").unwrap_err();
+        assert!(error.contains("must have the form <address>::<module>"));
+    }
+}
@@ -2,12 +2,14 @@ use super::context::*;
 use super::environment::*;
 use super::AST::*;
 use crate::MoveCodeGen::MoveIRTransfer::Move;
+use std::collections::HashSet;
 use std::fmt;
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
 pub mod MovePreProcessor;
+pub mod MoveSource;
 
 #[derive(Debug, Clone)]
 pub enum MovePosition {
@@ -23,14 +25,98 @@ impl Default for MovePosition {
     }
 }
 
-pub fn generate(module: Module, context: &mut Context) {
+pub fn generate(module: Module, context: &mut Context) -> VResult {
+    let contracts = contracts(&module, context)?;
 
+    for contract in contracts {
+        let c = contract.generate();
+
+        let mut code = CodeGen {
+            code: "".to_string(),
+            indent_level: 0,
+            indent_size: 2,
+        };
+
+        code.add(c);
+        print!("{}", code.code);
+
+        let name = contract.contract_declaration.identifier.token.clone();
+        let path = &format!("output/{name}.mvir", name = name);
+        let path = Path::new(path);
+        let display = path.display();
+
+        let mut file = match File::create(&path) {
+            Err(why) => panic!("couldn't create {}: {}", display, why),
+            Ok(file) => file,
+        };
+
+        match file.write_all(code.code.as_bytes()) {
+            Err(why) => panic!("couldn't write to {}: {}", display, why),
+            Ok(_) => println!("successfully wrote to {}", display),
+        }
+
+        for (function, script) in contract.scripts() {
+            let path = &format!("output/{name}.{function}.mvir", name = name, function = function);
+            let path = Path::new(path);
+            let display = path.display();
+
+            let mut file = match File::create(&path) {
+                Err(why) => panic!("couldn't create {}: {}", display, why),
+                Ok(file) => file,
+            };
+
+            match file.write_all(script.as_bytes()) {
+                Err(why) => panic!("couldn't write to {}: {}", display, why),
+                Ok(_) => println!("successfully wrote to {}", display),
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn generate_source(module: Module, context: &mut Context) -> VResult {
+    let contracts = contracts(&module, context)?;
+    let coin = context.environment.coin_module.clone();
+    let mut linked = HashSet::new();
+
+    for contract in contracts {
+        let mut code = CodeGen {
+            code: "".to_string(),
+            indent_level: 0,
+            indent_size: 2,
+        };
+        code.add(contract.generate());
+
+        let source = MoveSource::translate(&code.code, &coin, &mut linked);
+
+        let name = contract.contract_declaration.identifier.token.clone();
+        let path = &format!("output/{name}.move", name = name);
+        let path = Path::new(path);
+        let display = path.display();
+
+        let mut file = match File::create(&path) {
+            Err(why) => panic!("couldn't create {}: {}", display, why),
+            Ok(file) => file,
+        };
+
+        match file.write_all(source.as_bytes()) {
+            Err(why) => panic!("couldn't write to {}: {}", display, why),
+            Ok(_) => println!("successfully wrote to {}", display),
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn contracts(
+    module: &Module,
+    context: &Context,
+) -> Result<Vec<MoveContract>, Box<dyn std::error::Error>> {
     let trait_declarations: Vec<TraitDeclaration> = module
         .declarations
         .clone()
         .into_iter()
         .filter_map(|d| match d {
-            TopLevelDeclaration::TraitDeclaration(t) => Some(t),
+            TopLevelDeclaration::TraitDeclaration(t) if t.is_external() => Some(t),
             _ => None,
         })
         .collect();
@@ -81,32 +167,61 @@ pub fn generate(module: Module, context: &mut Context) {
         }
     }
 
-    for contract in contracts {
-        let c = contract.generate();
-
-        let mut code = CodeGen {
-            code: "".to_string(),
-            indent_level: 0,
-            indent_size: 2,
-        };
-
-        code.add(c);
-        print!("{}", code.code);
-
-        let name = contract.contract_declaration.identifier.token.clone();
-        let path = &format!("output/{name}.mvir", name = name);
-        let path = Path::new(path);
-        let display = path.display();
-
-        let mut file = match File::create(&path) {
-            Err(why) => panic!("couldn't create {}: {}", display, why),
-            Ok(file) => file,
-        };
+    // Move modules cannot be published before the modules they import, so
+    // contracts are generated after the contracts they call
+    let mut ordered: Vec<MoveContract> = Vec::new();
+    while !contracts.is_empty() {
+        let published: Vec<String> = ordered
+            .iter()
+            .map(|c| c.contract_declaration.identifier.token.clone())
+            .collect();
+        let next = contracts
+            .iter()
+            .position(|c| c.dependencies().iter().all(|d| published.contains(d)));
+        match next {
+            Some(index) => ordered.push(contracts.remove(index)),
+            None => {
+                let message =
+                    "Dependency error: Move modules cannot depend on each other cyclically";
+                println!("{}", message);
+                for contract in &contracts {
+                    let dependencies: Vec<String> = contract
+                        .dependencies()
+                        .into_iter()
+                        .filter(|d| !published.contains(d))
+                        .collect();
+                    println!(
+                        "    {} on line {} depends on {}",
+                        contract.contract_declaration.identifier.token,
+                        contract.contract_declaration.identifier.line_info.line,
+                        dependencies.join(", ")
+                    );
+                }
+                return Err(Box::from(message));
+            }
+        }
+    }
+    Ok(ordered)
+}
 
-        match file.write_all(code.code.as_bytes()) {
-            Err(why) => panic!("couldn't write to {}: {}", display, why),
-            Ok(_) => println!("successfully wrote to {}", display),
+fn referenced_contract(t: &Type, environment: &Environment) -> Vec<String> {
+    match t {
+        Type::InoutType(i) => referenced_contract(&i.key_type, environment),
+        Type::ArrayType(a) => referenced_contract(&a.key_type, environment),
+        Type::FixedSizedArrayType(a) => referenced_contract(&a.key_type, environment),
+        Type::DictionaryType(d) => {
+            let mut contracts = referenced_contract(&d.key_type, environment);
+            contracts.append(&mut referenced_contract(&d.value_type, environment));
+            contracts
+        }
+        Type::UserDefinedType(i) => {
+            if environment.is_contract_declared(&i.token) {
+                vec![i.token.clone()]
+            } else {
+                vec![]
+            }
         }
+        _ => vec![],
     }
 }
 
@@ -120,7 +235,69 @@ pub struct MoveContract {
 }
 
 impl MoveContract {
-    fn generate(&self) -> String {
+    // Other contracts of the same build whose addresses this contract holds
+    // or is passed, and whose modules it therefore imports
+    fn dependencies(&self) -> Vec<String> {
+        let name = self.contract_declaration.identifier.token.clone();
+        let mut types: Vec<Type> = vec![];
+        if let Some(type_info) = self.environment.types.get(&name) {
+            for property in type_info.properties.values() {
+                types.push(property.get_type().clone());
+            }
+            for functions in type_info.functions.values() {
+                for function in functions {
+                    types.append(&mut function.get_parameter_types());
+                    if let Some(result) = function.get_result_type() {
+                        types.push(result);
+                    }
+                }
+            }
+            for initialiser in &type_info.initialisers {
+                types.append(&mut initialiser.parameter_types());
+            }
+        }
+        types.append(&mut self.local_types());
+
+        let mut dependencies: Vec<String> = vec![];
+        for t in types {
+            for contract in referenced_contract(&t, &self.environment) {
+                if contract != name && !dependencies.contains(&contract) {
+                    dependencies.push(contract);
+                }
+            }
+        }
+        dependencies.sort();
+        dependencies
+    }
+
+    // Types of the locals of the contract's functions and initialisers
+    fn local_types(&self) -> Vec<Type> {
+        self.contract_behaviour_declarations
+            .iter()
+            .flat_map(|c| c.members.iter())
+            .flat_map(|m| match m {
+                ContractBehaviourMember::FunctionDeclaration(f) => f.body.clone(),
+                ContractBehaviourMember::SpecialDeclaration(s) => s.body.clone(),
+                _ => vec![],
+            })
+            .filter_map(|s| match s {
+                Statement::Expression(Expression::VariableDeclaration(v)) => Some(v.variable_type),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // The resource of a contract with @nonReentrant functions holds their lock
+    fn has_non_reentrant_functions(&self) -> bool {
+        self.contract_behaviour_declarations.iter().any(|c| {
+            c.members.iter().any(|m| match m {
+                ContractBehaviourMember::FunctionDeclaration(f) => f.is_non_reentrant(),
+                _ => false,
+            })
+        })
+    }
+
+    pub(crate) fn generate(&self) -> String {
         let imports = self.external_traits.clone();
         let imports: Vec<TraitDeclaration> = imports
             .into_iter()
@@ -143,6 +320,17 @@ impl MoveContract {
                 })
             })
             .collect();
+        let mut contract_imports: Vec<MoveIRStatement> = self
+            .dependencies()
+            .into_iter()
+            .map(|name| {
+                MoveIRStatement::Import(MoveIRModuleImport {
+                    name,
+                    address: "{{default}}".to_string(),
+                })
+            })
+            .collect();
+        imports.append(&mut contract_imports);
         let mut runtime_imports = MoveRuntimeTypes::get_all_imports();
         imports.append(&mut runtime_imports);
         let imports = imports.clone();
@@ -151,7 +339,10 @@ impl MoveContract {
         let import_code = import_code.join("\n");
 
 
-        let runtime_funcions = MoveRuntimeFunction::get_all_functions();
+        let mut runtime_funcions = MoveRuntimeFunction::get_all_functions();
+        if self.has_non_reentrant_functions() {
+            runtime_funcions.push(MoveRuntimeFunction::get_reentrancy_lock());
+        }
         let runtime_functions = runtime_funcions.join("\n\n");
 
         let functions: Vec<FunctionDeclaration> = self
@@ -201,11 +392,7 @@ impl MoveContract {
             })
             .collect();
 
-        let members: Vec<VariableDeclaration> = members
-            .into_iter()
-            .filter(|m| !m.variable_type.is_dictionary_type())
-            .collect();
-        let members: Vec<String> = members
+        let mut members: Vec<String> = members
             .into_iter()
             .map(|v| {
                 let declaration =
@@ -213,90 +400,148 @@ impl MoveContract {
                 return format!("{declaration}", declaration = declaration);
             })
             .collect();
+        if self.has_non_reentrant_functions() {
+            members.push(format!("{}: bool", MoveRuntimeFunction::reentrancy_lock_field()));
+        }
         let members = members.join(",\n");
 
-        let dict_resources: Vec<VariableDeclaration> = self
-            .contract_declaration
-            .contract_members
+        // Locals are hoisted to the top of each body by the preprocessor
+        let scopes: Vec<(Option<ScopeContext>, Vec<Statement>)> = self
+            .contract_behaviour_declarations
             .clone()
             .into_iter()
-            .filter_map(|m| {
-                if let ContractMember::VariableDeclaration(v) = m {
-                    Some(v)
-                } else {
-                    None
+            .flat_map(|c| c.members)
+            .filter_map(|m| match m {
+                ContractBehaviourMember::FunctionDeclaration(f) => Some((f.ScopeContext, f.body)),
+                ContractBehaviourMember::SpecialDeclaration(s) => {
+                    Some((Some(s.ScopeContext), s.body))
                 }
+                _ => None,
             })
+            .chain(
+                self.struct_declarations
+                    .clone()
+                    .into_iter()
+                    .flat_map(|s| s.members)
+                    .filter_map(|m| match m {
+                        StructMember::FunctionDeclaration(f) => Some((f.ScopeContext, f.body)),
+                        StructMember::SpecialDeclaration(s) => Some((Some(s.ScopeContext), s.body)),
+                        _ => None,
+                    }),
+            )
             .collect();
-
-        let dict_resources: Vec<VariableDeclaration> = dict_resources
+        let struct_member_types: Vec<Type> = self
+            .struct_declarations
+            .clone()
             .into_iter()
-            .filter(|m| m.variable_type.is_dictionary_type())
+            .flat_map(|s| {
+                s.members.into_iter().filter_map(|m| match m {
+                    StructMember::VariableDeclaration(v) => Some(v.variable_type),
+                    _ => None,
+                })
+            })
             .collect();
-        let dict_runtime = dict_resources.clone();
-        let dict_resources: Vec<String> = dict_resources
+        let scope_types: Vec<Type> = scopes
             .into_iter()
-            .map(|d| {
-                let result_type = MoveType::move_type(
-                    d.variable_type.clone(),
-                    Option::from(self.environment.clone()),
-                );
-                let result_type = result_type.generate(&function_context);
-                format!(
-                    "resource {name} {{ \n value: {dic_type} \n }}",
-                    name = mangle_dictionary(d.identifier.token.clone()),
-                    dic_type = result_type
-                )
+            .flat_map(|(scope, body)| {
+                let scope = scope.unwrap_or_default();
+                let parameters: Vec<Type> = scope
+                    .parameters
+                    .into_iter()
+                    .map(|p| p.type_assignment)
+                    .collect();
+                let locals: Vec<Type> = scope
+                    .local_variables
+                    .into_iter()
+                    .map(|v| v.variable_type)
+                    .collect();
+                let declarations: Vec<Type> = body
+                    .into_iter()
+                    .filter_map(|s| match s {
+                        Statement::Expression(Expression::VariableDeclaration(v)) => {
+                            Some(v.variable_type)
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                parameters.into_iter().chain(locals).chain(declarations)
             })
             .collect();
 
-        let dict_resources = dict_resources.join("\n\n");
-
-
-        let dict_runtime: Vec<String> = dict_runtime
+        // Properties, struct members, parameters and locals all need their
+        // dictionary and array helpers generated
+        let declared_types: Vec<Type> = self
+            .contract_declaration
+            .get_variable_declarations()
             .into_iter()
-            .map(|d| {
-                let r_name = mangle_dictionary(d.identifier.token.clone());
-                let result_type = MoveType::move_type(
-                    d.variable_type.clone(),
-                    Option::from(self.environment.clone()),
-                );
-                let result_type = result_type.generate(&function_context);
-                format!(
-                    "_get_{r_name}(__address_this: address): {r_type} acquires {r_name} {{
-    let this: &mut Self.{r_name};
-    let temp: &{r_type};
-    let result: {r_type};
-    this = borrow_global_mut<{r_name}>(move(__address_this));
-    temp = &copy(this).value;
-    result = *copy(temp);
-    return move(result);
-  }}
-
-        _insert_{r_name}(__address_this: address, v: {r_type}) acquires {r_name} {{
-    let new_value: Self.{r_name};
-    let cur: &mut Self.{r_name};
-    let b: bool;
-    b = exists<{r_name}>(copy(__address_this));
-    if (move(b)) {{
-      cur = borrow_global_mut<{r_name}>(move(__address_this));
-      *(&mut move(cur).value) = move(v);
-    }} else {{
-       new_value = {r_name} {{
-      value: move(v)
-    }};
-    move_to_sender<{r_name}>(move(new_value));
-    }}
-    return;
-  }}",
-                    r_name = r_name,
-                    r_type = result_type
-                )
+            .map(|v| v.variable_type)
+            .chain(struct_member_types)
+            .chain(scope_types)
+            .map(|t| match t {
+                Type::InoutType(i) => *i.key_type,
+                t => t,
             })
             .collect();
 
+        let mut dictionary_types: Vec<DictionaryType> = vec![];
+        for declared_type in declared_types.clone() {
+            if let Type::DictionaryType(d) = declared_type {
+                let name = MoveRuntimeTypes::dictionary_name(&d);
+                if !dictionary_types
+                    .iter()
+                    .any(|t| MoveRuntimeTypes::dictionary_name(t) == name)
+                {
+                    dictionary_types.push(d);
+                }
+            }
+        }
+
+        let dict_resources: Vec<String> = dictionary_types
+            .iter()
+            .map(|d| MoveRuntimeTypes::dictionary_declaration(d))
+            .collect();
+        let dict_resources = dict_resources.join("\n\n");
+
+        let dict_runtime: Vec<String> = dictionary_types
+            .iter()
+            .map(|d| MoveRuntimeFunction::dictionary_functions(d))
+            .collect();
         let dict_runtime = dict_runtime.join("\n\n");
 
+        // Fixed-size arrays also need a helper that fills them with defaults
+        let mut array_types: Vec<(String, MoveIRType, bool, Option<String>)> = vec![];
+        for array_type in declared_types {
+            let (element, fixed) = match array_type {
+                Type::ArrayType(a) => (*a.key_type, false),
+                Type::FixedSizedArrayType(a) => (*a.key_type, true),
+                _ => continue,
+            };
+            let default = match MoveRuntimeTypes::default_element(&element) {
+                Some((_, default)) if fixed => Some(default),
+                _ => None,
+            };
+            let element = MoveType::move_type(element, Option::from(self.environment.clone()));
+            let is_resource = element.is_resource();
+            let element = element.generate(&function_context);
+            let name = MoveRuntimeTypes::array_name(&element);
+            match array_types.iter_mut().find(|(n, _, _, _)| *n == name) {
+                Some((_, _, _, fill)) => {
+                    if fill.is_none() {
+                        *fill = default;
+                    }
+                }
+                None => array_types.push((name, element, is_resource, default)),
+            }
+        }
+
+        let array_runtime: Vec<String> = array_types
+            .iter()
+            .map(|(_, element, is_resource, default)| {
+                MoveRuntimeFunction::array_functions(element, *is_resource, default)
+            })
+            .collect();
+        let array_runtime = array_runtime.join("\n\n");
+
         let structs: Vec<StructDeclaration> = self
             .struct_declarations
             .clone()
@@ -448,7 +693,7 @@ impl MoveContract {
         let mut statements = initialiser_declaration.body.clone();
         let properties = self
             .contract_declaration
-            .get_variable_declarations_without_dict();
+            .get_variable_declarations();
 
         let mut function_context = FunctionContext {
             environment: self.environment.clone(),
@@ -477,7 +722,7 @@ impl MoveContract {
 
         let unassigned = self
             .contract_declaration
-            .get_variable_declarations_without_dict();
+            .get_variable_declarations();
         let mut unassigned: Vec<Identifier> =
             unassigned.into_iter().map(|v| v.identifier).collect();
 
@@ -526,8 +771,8 @@ impl MoveContract {
 
         let fields = self
             .contract_declaration
-            .get_variable_declarations_without_dict();
-        let fields: Vec<(String, MoveIRExpression)> = fields
+            .get_variable_declarations();
+        let mut fields: Vec<(String, MoveIRExpression)> = fields
             .into_iter()
             .map(|p| {
                 (
@@ -538,6 +783,12 @@ impl MoveContract {
                 )
             })
             .collect();
+        if self.has_non_reentrant_functions() {
+            fields.push((
+                MoveRuntimeFunction::reentrancy_lock_field(),
+                MoveIRExpression::Inline("false".to_string()),
+            ));
+        }
         let constructor = MoveIRExpression::StructConstructor(MoveIRStructConstructor {
             identifier: Identifier {
                 token: format!("T"),
@@ -590,6 +841,7 @@ impl MoveContract {
 
             let mut scope = function_context.ScopeContext.clone();
             scope.local_variables.push(VariableDeclaration {
+                attributes: vec![],
                 declaration_token: None,
                 identifier: self_identifier,
                 variable_type: Type::InoutType(InoutType {
@@ -625,12 +877,177 @@ impl MoveContract {
         );
 
 
-        return format!("module {name} {{ \n  {imports} \n resource T {{ \n {members} \n }} {dict_resources} \n {assets}  \n {structs} \n {init} \n \n {asset_functions} \n \n {struct_functions} \n {functions} \n {runtime} \n {dict_runtime} }}"
+        return format!("module {name} {{ \n  {imports} \n resource T {{ \n {members} \n }} {dict_resources} \n {assets}  \n {structs} \n {init} \n \n {asset_functions} \n \n {struct_functions} \n {functions} \n {runtime} \n {dict_runtime} \n {array_runtime} }}"
                  , name = self.contract_declaration.identifier.token, functions = functions, members = members,
                              assets = assets, asset_functions = asset_functions, structs = structs, dict_resources = dict_resources,
                              init = initialiser, struct_functions = struct_functions, imports = import_code,
-                            runtime = runtime_functions, dict_runtime = dict_runtime
+                            runtime = runtime_functions, dict_runtime = dict_runtime, array_runtime = array_runtime
+        );
+    }
+
+    // One transaction script for publishing the contract and one for each
+    // public function wrapper
+    pub(crate) fn scripts(&self) -> Vec<(String, String)> {
+        let name = self.contract_declaration.identifier.token.clone();
+        let mut scripts = vec![];
+
+        for declaration in self.contract_behaviour_declarations.clone() {
+            for member in declaration.members {
+                match member {
+                    ContractBehaviourMember::SpecialDeclaration(s) => {
+                        if s.is_init() && s.is_public() {
+                            let script = MoveTransactionScript {
+                                module_name: name.clone(),
+                                function_name: "publish".to_string(),
+                                parameters: s.head.parameters.clone(),
+                                has_result: false,
+                                takes_contract: false,
+                            };
+                            scripts.push(("publish".to_string(), script.generate()));
+                        }
+                    }
+                    ContractBehaviourMember::FunctionDeclaration(f) => {
+                        if !f.is_public() {
+                            continue;
+                        }
+                        let function_name = f
+                            .mangledIdentifier
+                            .clone()
+                            .unwrap_or(f.head.identifier.token.clone());
+                        let mut parameters = f.head.parameters.clone();
+                        parameters.remove(0);
+
+                        let unsupported = parameters.iter().find(|p| {
+                            !MoveTransactionScript::is_script_argument(
+                                &p.type_assignment,
+                                &self.environment,
+                            )
+                        });
+                        if unsupported.is_some() {
+                            println!(
+                                "No transaction script generated for {}: parameter {} cannot be passed to a script",
+                                function_name,
+                                unsupported.unwrap().identifier.token
+                            );
+                            continue;
+                        }
+
+                        let script = MoveTransactionScript {
+                            module_name: name.clone(),
+                            function_name: function_name.clone(),
+                            parameters,
+                            has_result: f.get_result_type().is_some(),
+                            takes_contract: true,
+                        };
+                        scripts.push((function_name, script.generate()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        scripts
+    }
+}
+
+struct MoveTransactionScript {
+    pub module_name: String,
+    pub function_name: String,
+    pub parameters: Vec<Parameter>,
+    pub has_result: bool,
+    pub takes_contract: bool,
+}
+
+impl MoveTransactionScript {
+    pub fn is_script_argument(t: &Type, environment: &Environment) -> bool {
+        match t {
+            Type::Int | Type::Bool | Type::Address => true,
+            Type::UserDefinedType(i) => {
+                i.token == "LibraCoin.T"
+                    || i.token == "Libra"
+                    || environment.is_enum_declared(&i.token)
+            }
+            _ => false,
+        }
+    }
+
+    // Scripts cannot receive resources, so currency arguments are taken as
+    // amounts and withdrawn from the sender's account
+    pub fn generate(&self) -> String {
+        let mut parameters = vec![];
+        let mut declarations = vec![];
+        let mut statements = vec![];
+        let mut arguments = vec![];
+
+        if self.takes_contract {
+            parameters.push("contract: address".to_string());
+            arguments.push("move(contract)".to_string());
+        }
+
+        for parameter in &self.parameters {
+            let name = mangle(parameter.identifier.token.clone());
+            match parameter.type_assignment.clone() {
+                Type::Bool => parameters.push(format!("{}: bool", name)),
+                Type::Address => parameters.push(format!("{}: address", name)),
+                Type::UserDefinedType(i) if i.token == "LibraCoin.T" || i.token == "Libra" => {
+                    parameters.push(format!("{}: u64", name));
+                    let coin = format!(
+                        "LibraAccount.withdraw_from_sender(move({name}))",
+                        name = name
+                    );
+                    let (value_type, value) = if i.token == "Libra" {
+                        (
+                            format!("{}.Libra", self.module_name),
+                            format!(
+                                "{module}.Quartz_Self_Create_Libra({coin})",
+                                module = self.module_name,
+                                coin = coin
+                            ),
+                        )
+                    } else {
+                        ("LibraCoin.T".to_string(), coin)
+                    };
+                    declarations.push(format!("let {}_value: {};", name, value_type));
+                    statements.push(format!("{}_value = {};", name, value));
+                    arguments.push(format!("move({}_value)", name));
+                    continue;
+                }
+                _ => parameters.push(format!("{}: u64", name)),
+            }
+            arguments.push(format!("move({})", name));
+        }
+
+        let call = format!(
+            "{module}.{function}({arguments})",
+            module = self.module_name,
+            function = self.function_name,
+            arguments = arguments.join(", ")
         );
+        if self.has_result {
+            statements.push(format!("_ = {};", call));
+        } else {
+            statements.push(format!("{};", call));
+        }
+
+        let body: Vec<String> = declarations
+            .into_iter()
+            .chain(statements)
+            .map(|s| format!("  {}", s))
+            .collect();
+
+        format!(
+            "import 0x0.LibraAccount;
+import 0x0.LibraCoin;
+import {{{{default}}}}.{module};
+
+main({parameters}) {{
+{body}
+  return;
+}}
+",
+            module = self.module_name,
+            parameters = parameters.join(", "),
+            body = body.join("\n")
+        )
     }
 }
 
@@ -868,13 +1285,36 @@ impl MoveStatement {
             Statement::ReturnStatement(r) => {
                 MoveReturnStatement { statement: r }.generate(function_context)
             }
-            Statement::Expression(e) => MoveIRStatement::Expression(
-                MoveExpression {
-                    expression: e,
-                    position: Default::default(),
+            Statement::Expression(e) => {
+                if let Expression::BinaryExpression(b) = e.clone() {
+                    if let BinOp::Equal = b.op {
+                        match *b.rhs_expression {
+                            Expression::ArrayLiteral(a) if !a.elements.is_empty() => {
+                                return MoveArrayLiteralAssignment {
+                                    lhs: *b.lhs_expression,
+                                    literal: a,
+                                }
+                                .generate(function_context);
+                            }
+                            Expression::DictionaryLiteral(d) => {
+                                return MoveDictionaryLiteralAssignment {
+                                    lhs: *b.lhs_expression,
+                                    literal: d,
+                                }
+                                .generate(function_context);
+                            }
+                            _ => {}
+                        }
+                    }
                 }
-                .generate(function_context),
-            ),
+                MoveIRStatement::Expression(
+                    MoveExpression {
+                        expression: e,
+                        position: Default::default(),
+                    }
+                    .generate(function_context),
+                )
+            }
             Statement::BecomeStatement(b) => {
                 MoveBecomeStatement { statement: b }.generate(function_context)
             }
@@ -888,6 +1328,7 @@ impl MoveStatement {
                 MoveIfStatement { statement: i }.generate(function_context)
             }
             Statement::DoCatchStatement(_) => panic!("Do Catch not currently supported"),
+            Statement::TargetStatement(_) => panic!("Target statement not stripped before codegen"),
         }
     }
 }
@@ -1526,6 +1967,18 @@ impl MoveExternalCall {
                         .generate(function_context);
                     }
                 }
+
+                if function_context
+                    .environment
+                    .is_contract_declared(&external_trait_name)
+                    && external_trait_name != function_context.enclosing_type
+                {
+                    return MoveFunctionCall {
+                        function_call: f.clone(),
+                        module_name: external_trait_name,
+                    }
+                    .generate(function_context);
+                }
             }
 
             let mut function_call = f.clone();
@@ -1688,6 +2141,9 @@ impl MoveExpression {
                 MoveIRExpression::Literal(MoveLiteralToken { token: l }.generate())
             }
             Expression::ArrayLiteral(a) => {
+                if !a.elements.is_empty() {
+                    panic!("Non-empty array literals can only be assigned")
+                }
                 let elements = a.elements.clone();
                 let elements = elements
                     .into_iter()
@@ -1704,7 +2160,9 @@ impl MoveExpression {
                     vec_type: None,
                 })
             }
-            Expression::DictionaryLiteral(_) => unimplemented!(),
+            Expression::DictionaryLiteral(_) => {
+                panic!("Dictionary literals can only be assigned to variables")
+            }
             Expression::SelfExpression => MoveSelf {
                 token: "self".to_string(),
                 position: self.position.clone(),
@@ -1726,17 +2184,45 @@ impl MoveExpression {
     }
 }
 
-struct MoveCastExpression {
-    pub expression: CastExpression,
-}
-
-impl MoveCastExpression {
-    pub fn generate(&self, function_context: &FunctionContext) -> MoveIRExpression {
-        let enclosing = self.expression.expression.enclosing_type().clone();
-        let enclosing = enclosing.unwrap_or(function_context.enclosing_type.clone());
-        let scope = function_context.ScopeContext.clone();
-        let original_type = function_context.environment.get_expression_type(
-            *self.expression.expression.clone(),
+impl MoveBinaryExpression {
+    fn fixed_point_scale(&self, function_context: &FunctionContext) -> Option<u64> {
+        let op = self.expression.op.clone();
+        if !op.is_arithmetic() && !op.is_boolean() {
+            return None;
+        }
+        let operands = vec![
+            *self.expression.lhs_expression.clone(),
+            *self.expression.rhs_expression.clone(),
+        ];
+        let mut types = vec![];
+        for operand in operands {
+            if let Expression::RawAssembly(_, _) = operand {
+                return None;
+            }
+            types.push(function_context.environment.get_expression_type(
+                operand,
+                &function_context.enclosing_type,
+                vec![],
+                vec![],
+                function_context.ScopeContext.clone(),
+            ));
+        }
+        // Operands that cannot be combined are rejected by the type checker
+        FixedPointType::operation_scale(&op, &types[0], &types[1]).unwrap_or(None)
+    }
+}
+
+struct MoveCastExpression {
+    pub expression: CastExpression,
+}
+
+impl MoveCastExpression {
+    pub fn generate(&self, function_context: &FunctionContext) -> MoveIRExpression {
+        let enclosing = self.expression.expression.enclosing_type().clone();
+        let enclosing = enclosing.unwrap_or(function_context.enclosing_type.clone());
+        let scope = function_context.ScopeContext.clone();
+        let original_type = function_context.environment.get_expression_type(
+            *self.expression.expression.clone(),
             &enclosing,
             vec![],
             vec![],
@@ -1744,6 +2230,15 @@ impl MoveCastExpression {
         );
         let target_type = self.expression.cast_type.clone();
 
+        if original_type.is_fixed_point_type() || target_type.is_fixed_point_type() {
+            let expression_code = MoveExpression {
+                expression: *self.expression.expression.clone(),
+                position: Default::default(),
+            }
+            .generate(function_context);
+            return MoveCastExpression::rescale(expression_code, &original_type, &target_type);
+        }
+
         let original_type_information = MoveCastExpression::get_type_info(original_type);
         let target_type_information = MoveCastExpression::get_type_info(target_type);
 
@@ -1775,6 +2270,31 @@ impl MoveCastExpression {
         }
     }
 
+    // Casts between Fixed precisions and Int move the value to the new scale,
+    // dropping any digits the target cannot hold
+    fn rescale(expression: MoveIRExpression, original: &Type, target: &Type) -> MoveIRExpression {
+        let decimals = |t: &Type| match t {
+            Type::FixedPointType(f) => f.decimals,
+            _ => 0,
+        };
+        let (from, to) = (decimals(original), decimals(target));
+        if to > from {
+            let factor = FixedPointType { decimals: to - from }.scale();
+            MoveIRExpression::Operation(MoveIROperation::Times(
+                Box::from(expression),
+                Box::from(MoveIRExpression::Literal(MoveIRLiteral::Num(factor))),
+            ))
+        } else if to < from {
+            let factor = FixedPointType { decimals: from - to }.scale();
+            MoveIRExpression::Operation(MoveIROperation::Divide(
+                Box::from(expression),
+                Box::from(MoveIRExpression::Literal(MoveIRLiteral::Num(factor))),
+            ))
+        } else {
+            expression
+        }
+    }
+
     pub fn maximum_value(input: u64) -> String {
         match input {
             8 => format!("255"),
@@ -1811,13 +2331,6 @@ impl MoveSubscriptExpression {
         }
         .generate(function_context);
 
-        let identifier = self.expression.base_expression.clone();
-
-        let identifier_code = MoveIdentifier {
-            identifier,
-            position: self.position.clone(),
-        }
-        .generate(function_context, false, false);
         let base_type = function_context.environment.get_expression_type(
             Expression::Identifier(self.expression.base_expression.clone()),
             &function_context.enclosing_type.clone(),
@@ -1826,64 +2339,67 @@ impl MoveSubscriptExpression {
             function_context.ScopeContext.clone(),
         );
 
+        let base = MoveExpression {
+            expression: Expression::Identifier(self.expression.base_expression.clone()),
+            position: MovePosition::Left,
+        }
+        .generate(function_context);
 
-        let inner_type = match base_type.clone() {
-            Type::ArrayType(a) => *a.key_type,
-            Type::DictionaryType(d) => *d.key_type,
-            _ => unimplemented!(),
-        };
-
-        let move_type = MoveType::move_type(
-            inner_type,
-            Option::from(function_context.environment.clone()),
-        );
-        let move_type = move_type.generate(function_context);
-
-        if let MovePosition::Left = self.position.clone() {
-            match base_type {
-                Type::ArrayType(a) => {
-                    return MoveRuntimeFunction::append_to_array_int(identifier_code, rhs);
-                }
-                Type::FixedSizedArrayType(a) => panic!("Fixed Size Arrays not currently supported"),
-                Type::DictionaryType(p) => {
-                    let f_name = format!(
-                        "Self._insert_{}",
-                        mangle_dictionary(self.expression.base_expression.token.clone())
-                    );
+        let (element_type, index) = match base_type.clone() {
+            Type::ArrayType(a) => (*a.key_type, index),
+            Type::FixedSizedArrayType(a) => (
+                *a.key_type,
+                MoveRuntimeFunction::revert_if_greater(
+                    index,
+                    MoveIRExpression::Literal(MoveIRLiteral::Num(a.size.saturating_sub(1))),
+                ),
+            ),
+            Type::DictionaryType(d) => {
+                let name = MoveRuntimeTypes::dictionary_name(&d);
+                if let MovePosition::Left = self.position.clone() {
                     return MoveIRExpression::FunctionCall(MoveIRFunctionCall {
-                        identifier: f_name,
-                        arguments: vec![index.clone(), rhs],
+                        identifier: format!("Self.{}_insert", name),
+                        arguments: vec![index, rhs, mutable_reference(base)],
                     });
                 }
-                _ => panic!("Invalid Type for Subscript Expression"),
-            }
-        }
-
-        match base_type.clone() {
-            Type::ArrayType(a) => {
-                let identifier = self.expression.base_expression.clone();
-
-                let identifier_code = MoveIdentifier {
-                    identifier,
-                    position: self.position.clone(),
+                if d.value_type.is_currency_type() {
+                    return MoveIRExpression::FunctionCall(MoveIRFunctionCall {
+                        identifier: format!("Self.{}_borrow", name),
+                        arguments: vec![index, mutable_reference(base)],
+                    });
                 }
-                .generate(function_context, false, true);
-                return MoveRuntimeFunction::get_from_array_int(identifier_code, index);
-            }
-            Type::FixedSizedArrayType(a) => panic!("Fixed Size Arrays not currently supported"),
-            Type::DictionaryType(p) => {
-                let f_name = format!(
-                    "Self._get_{}",
-                    mangle_dictionary(self.expression.base_expression.token.clone())
-                );
                 return MoveIRExpression::FunctionCall(MoveIRFunctionCall {
-                    identifier: f_name,
-                    arguments: vec![rhs],
+                    identifier: format!("Self.{}_get", name),
+                    arguments: vec![index, immutable_reference(base)],
                 });
             }
             _ => panic!("Invalid Type for Subscript Expression"),
+        };
+
+        let element_type = MoveType::move_type(
+            element_type,
+            Option::from(function_context.environment.clone()),
+        );
+        let is_resource = element_type.is_resource();
+        let name = MoveRuntimeTypes::array_name(&element_type.generate(function_context));
+
+        if let MovePosition::Left = self.position.clone() {
+            return MoveIRExpression::FunctionCall(MoveIRFunctionCall {
+                identifier: format!("Self.{}_set", name),
+                arguments: vec![index, rhs, mutable_reference(base)],
+            });
         }
 
+        if is_resource {
+            return MoveIRExpression::FunctionCall(MoveIRFunctionCall {
+                identifier: format!("Self.{}_borrow", name),
+                arguments: vec![index, mutable_reference(base)],
+            });
+        }
+        MoveIRExpression::FunctionCall(MoveIRFunctionCall {
+            identifier: format!("Self.{}_get", name),
+            arguments: vec![index, immutable_reference(base)],
+        })
     }
 }
 
@@ -2005,6 +2521,17 @@ impl MoveInoutExpression {
             .generate(function_context);
         }
 
+        // Coins in collections are read through the mutable borrow of their helpers
+        if let Expression::SubscriptExpression(_) = *self.expression.expression.clone() {
+            if expression_type.is_currency_type() {
+                return MoveExpression {
+                    expression: *self.expression.expression.clone(),
+                    position: MovePosition::Inout,
+                }
+                .generate(function_context);
+            }
+        }
+
         let expression = self.expression.clone();
         return MoveIRExpression::Operation(MoveIROperation::MutableReference(Box::from(
             MoveExpression {
@@ -2027,7 +2554,7 @@ impl MoveLiteralToken {
             Literal::AddressLiteral(a) => MoveIRLiteral::Hex(a),
             Literal::StringLiteral(s) => MoveIRLiteral::String(s),
             Literal::IntLiteral(i) => MoveIRLiteral::Num(i),
-            Literal::FloatLiteral(i) => panic!("Floats not currently supported"),
+            Literal::FloatLiteral(f) => MoveIRLiteral::Num(FixedPointType::raw_value(&f)),
         };
     }
 }
@@ -2083,11 +2610,25 @@ impl MoveIdentifier {
 
                 return MoveIRExpression::Transfer(MoveIRTransfer::Move(Box::from(ir_identifier)));
             }
+            // Coins are resources, so they are borrowed to be changed and moved
+            // everywhere else
             if unwrapped_type.is_currency_type() {
-
-                return ir_identifier;
+                return match self.position {
+                    MovePosition::Left => ir_identifier,
+                    MovePosition::Inout | MovePosition::Accessed => MoveIRExpression::Operation(
+                        MoveIROperation::MutableReference(Box::from(ir_identifier)),
+                    ),
+                    MovePosition::Normal => {
+                        MoveIRExpression::Transfer(MoveIRTransfer::Move(Box::from(ir_identifier)))
+                    }
+                };
             }
-            if !unwrapped_type.is_inout_type() && unwrapped_type.is_user_defined_type() {
+            if !unwrapped_type.is_inout_type()
+                && unwrapped_type.is_user_defined_type()
+                && !function_context
+                    .environment
+                    .is_enum_declared(&unwrapped_type.name())
+            {
                 return MoveIRExpression::Operation(MoveIROperation::MutableReference(Box::from(
                     ir_identifier,
                 )));
@@ -2156,6 +2697,20 @@ impl MoveBinaryExpression {
             .generate(function_context);
         }
 
+        if self.expression.op.is_assignment_shorthand() {
+            let op = self.expression.op.get_assignment_shorthand();
+            return MoveAssignment {
+                lhs: *self.expression.lhs_expression.clone(),
+                rhs: Expression::BinaryExpression(BinaryExpression {
+                    lhs_expression: self.expression.lhs_expression.clone(),
+                    rhs_expression: self.expression.rhs_expression.clone(),
+                    op,
+                    line_info: self.expression.line_info.clone(),
+                }),
+            }
+            .generate(function_context);
+        }
+
         let lhs = MoveExpression {
             expression: *self.expression.lhs_expression.clone(),
             position: self.position.clone(),
@@ -2167,30 +2722,36 @@ impl MoveBinaryExpression {
         }
         .generate(function_context);
 
+        if let Some(scale) = self.fixed_point_scale(function_context) {
+            let scale = MoveIRExpression::Literal(MoveIRLiteral::Num(scale));
+            if let BinOp::Times = self.expression.op {
+                return MoveRuntimeFunction::fixed_mul(lhs, rhs, scale);
+            }
+            return MoveRuntimeFunction::fixed_div(lhs, rhs, scale);
+        }
+
         return match self.expression.op.clone() {
             BinOp::Plus => {
                 MoveIRExpression::Operation(MoveIROperation::Add(Box::from(lhs), Box::from(rhs)))
             }
-            BinOp::Implies => panic!("operator not supported"),
+            BinOp::Implies => MoveIRExpression::Operation(MoveIROperation::Or(
+                Box::from(MoveIRExpression::Operation(MoveIROperation::Not(Box::from(lhs)))),
+                Box::from(rhs),
+            )),
             BinOp::GreaterThan => MoveIRExpression::Operation(MoveIROperation::GreaterThan(
                 Box::from(lhs),
                 Box::from(rhs),
             )),
-
-            BinOp::OverflowingPlus => {
-                MoveIRExpression::Operation(MoveIROperation::Add(Box::from(lhs), Box::from(rhs)))
-            },
+            BinOp::OverflowingPlus => MoveRuntimeFunction::wrapping_add(lhs, rhs),
             BinOp::Minus => {
                 MoveIRExpression::Operation(MoveIROperation::Minus(Box::from(lhs), Box::from(rhs)))
-            },
-            BinOp::OverflowingMinus => MoveIRExpression::Operation(MoveIROperation::Minus(Box::from(lhs), Box::from(rhs))),
+            }
+            BinOp::OverflowingMinus => MoveRuntimeFunction::wrapping_sub(lhs, rhs),
             BinOp::Times => {
                 MoveIRExpression::Operation(MoveIROperation::Times(Box::from(lhs), Box::from(rhs)))
-            },
-            BinOp::OverflowingTimes => MoveIRExpression::Operation(MoveIROperation::Times(Box::from(lhs), Box::from(rhs))),
-            BinOp::Power => {
-                MoveIRExpression::Operation(MoveIROperation::Power(Box::from(lhs), Box::from(rhs)))
-            },
+            }
+            BinOp::OverflowingTimes => MoveRuntimeFunction::wrapping_mul(lhs, rhs),
+            BinOp::Power => MoveRuntimeFunction::power(lhs, rhs),
             BinOp::Divide => {
                 MoveIRExpression::Operation(MoveIROperation::Divide(Box::from(lhs), Box::from(rhs)))
             },
@@ -2201,10 +2762,10 @@ impl MoveBinaryExpression {
             BinOp::Equal => {
                 MoveIRExpression::Operation(MoveIROperation::Equal(Box::from(lhs), Box::from(rhs)))
             }
-            BinOp::PlusEqual => panic!("operator not supported"),
-            BinOp::MinusEqual => panic!("operator not supported"),
-            BinOp::TimesEqual => panic!("operator not supported"),
-            BinOp::DivideEqual => panic!("operator not supported"),
+            BinOp::PlusEqual => panic!("Unexpected Operator"),
+            BinOp::MinusEqual => panic!("Unexpected Operator"),
+            BinOp::TimesEqual => panic!("Unexpected Operator"),
+            BinOp::DivideEqual => panic!("Unexpected Operator"),
             BinOp::DoubleEqual => {
                 MoveIRExpression::Operation(MoveIROperation::Equal(Box::from(lhs), Box::from(rhs)))
             }
@@ -2256,6 +2817,42 @@ impl MovePropertyAccess {
                 }
             }
         }
+        if let Expression::Identifier(p) = self.right.clone() {
+            if p.token == "size".to_string() {
+                let lhs_type = function_context.environment.get_expression_type(
+                    self.left.clone(),
+                    &function_context.enclosing_type.clone(),
+                    vec![],
+                    vec![],
+                    function_context.ScopeContext.clone(),
+                );
+                match lhs_type {
+                    Type::FixedSizedArrayType(a) => {
+                        return MoveIRExpression::Literal(MoveIRLiteral::Num(a.size));
+                    }
+                    Type::ArrayType(a) => {
+                        let element = MoveType::move_type(
+                            *a.key_type,
+                            Option::from(function_context.environment.clone()),
+                        )
+                        .generate(function_context);
+                        let vector = MoveExpression {
+                            expression: self.left.clone(),
+                            position: MovePosition::Left,
+                        }
+                        .generate(function_context);
+                        return MoveIRExpression::FunctionCall(MoveIRFunctionCall {
+                            identifier: format!(
+                                "Self.{}_count",
+                                MoveRuntimeTypes::array_name(&element)
+                            ),
+                            arguments: vec![immutable_reference(vector)],
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
         let rhs_enclosing = self.right.enclosing_identifier();
         if rhs_enclosing.is_some() {
             if function_context.is_constructor {
@@ -2295,6 +2892,183 @@ impl MovePropertyAccess {
     }
 }
 
+fn mutable_reference(expression: MoveIRExpression) -> MoveIRExpression {
+    match expression {
+        MoveIRExpression::Operation(MoveIROperation::Access(base, field)) => {
+            let base = match *base {
+                MoveIRExpression::Operation(MoveIROperation::Dereference(r)) => match *r {
+                    MoveIRExpression::Operation(MoveIROperation::MutableReference(b)) => *b,
+                    r => r,
+                },
+                b => b,
+            };
+            MoveIRExpression::Operation(MoveIROperation::MutableReference(Box::from(
+                MoveIRExpression::Operation(MoveIROperation::Access(Box::from(base), field)),
+            )))
+        }
+        e => MoveIRExpression::Operation(MoveIROperation::MutableReference(Box::from(e))),
+    }
+}
+
+fn immutable_reference(expression: MoveIRExpression) -> MoveIRExpression {
+    match mutable_reference(expression) {
+        MoveIRExpression::Operation(MoveIROperation::MutableReference(e)) => {
+            MoveIRExpression::Operation(MoveIROperation::Reference(e))
+        }
+        e => e,
+    }
+}
+
+struct MoveArrayLiteralAssignment {
+    pub lhs: Expression,
+    pub literal: ArrayLiteral,
+}
+
+impl MoveArrayLiteralAssignment {
+    pub fn generate(&self, function_context: &mut FunctionContext) -> MoveIRStatement {
+        let lhs_type = function_context.environment.get_expression_type(
+            self.lhs.clone(),
+            &function_context.enclosing_type.clone(),
+            vec![],
+            vec![],
+            function_context.ScopeContext.clone(),
+        );
+        let element_type = match lhs_type {
+            Type::ArrayType(a) => *a.key_type,
+            Type::FixedSizedArrayType(a) => *a.key_type,
+            _ => panic!("Array literal assigned to non array type"),
+        };
+        let element_type =
+            MoveType::move_type(element_type, Option::from(function_context.environment.clone()))
+                .generate(function_context);
+
+        let vector = MoveExpression {
+            expression: self.lhs.clone(),
+            position: MovePosition::Left,
+        }
+        .generate(function_context);
+        let reference = mutable_reference(vector.clone());
+
+        function_context.emit(MoveIRStatement::Expression(MoveIRExpression::Assignment(
+            MoveIRAssignment {
+                identifier: format!("{}", vector),
+                expresion: Box::new(MoveIRExpression::Vector(MoveIRVector {
+                    elements: vec![],
+                    vec_type: Option::from(element_type.clone()),
+                })),
+            },
+        )));
+
+        let mut elements = self.literal.elements.clone();
+        let last = elements.pop().unwrap();
+        for element in elements {
+            let statement = MoveArrayLiteralAssignment::push_back(
+                reference.clone(),
+                element,
+                element_type.clone(),
+                function_context,
+            );
+            function_context.emit(statement);
+        }
+        MoveArrayLiteralAssignment::push_back(reference, last, element_type, function_context)
+    }
+
+    fn push_back(
+        reference: MoveIRExpression,
+        element: Expression,
+        element_type: MoveIRType,
+        function_context: &FunctionContext,
+    ) -> MoveIRStatement {
+        let element = MoveExpression {
+            expression: element,
+            position: Default::default(),
+        }
+        .generate(function_context);
+        MoveIRStatement::Expression(MoveIRExpression::FunctionCall(MoveIRFunctionCall {
+            identifier: format!("Vector.push_back<{}>", element_type),
+            arguments: vec![reference, element],
+        }))
+    }
+}
+
+struct MoveDictionaryLiteralAssignment {
+    pub lhs: Expression,
+    pub literal: DictionaryLiteral,
+}
+
+impl MoveDictionaryLiteralAssignment {
+    pub fn generate(&self, function_context: &mut FunctionContext) -> MoveIRStatement {
+        let identifier = match self.lhs.clone() {
+            Expression::Identifier(i) => i,
+            Expression::BinaryExpression(b) => match *b.rhs_expression {
+                Expression::Identifier(i) => i,
+                _ => panic!("Dictionary literals can only be assigned to variables"),
+            },
+            _ => panic!("Dictionary literals can only be assigned to variables"),
+        };
+
+        let lhs_type = function_context.environment.get_expression_type(
+            self.lhs.clone(),
+            &function_context.enclosing_type.clone(),
+            vec![],
+            vec![],
+            function_context.ScopeContext.clone(),
+        );
+        let dictionary_type = match lhs_type {
+            Type::DictionaryType(d) => d,
+            _ => panic!("Dictionary literal assigned to non dictionary type"),
+        };
+
+        let dictionary = MoveExpression {
+            expression: self.lhs.clone(),
+            position: MovePosition::Left,
+        }
+        .generate(function_context);
+        let empty = MoveIRStatement::Expression(MoveIRExpression::Assignment(MoveIRAssignment {
+            identifier: format!("{}", dictionary),
+            expresion: Box::new(MoveIRExpression::FunctionCall(MoveIRFunctionCall {
+                identifier: format!(
+                    "Self.{}_new",
+                    MoveRuntimeTypes::dictionary_name(&dictionary_type)
+                ),
+                arguments: vec![],
+            })),
+        }));
+
+        let mut elements = self.literal.elements.clone();
+        let last = elements.pop();
+        if last.is_none() {
+            return empty;
+        }
+        function_context.emit(empty);
+        for (key, value) in elements {
+            let statement =
+                MoveDictionaryLiteralAssignment::insert(&identifier, key, value, function_context);
+            function_context.emit(statement);
+        }
+        let (key, value) = last.unwrap();
+        MoveDictionaryLiteralAssignment::insert(&identifier, key, value, function_context)
+    }
+
+    fn insert(
+        identifier: &Identifier,
+        key: Expression,
+        value: Expression,
+        function_context: &FunctionContext,
+    ) -> MoveIRStatement {
+        MoveIRStatement::Expression(
+            MoveAssignment {
+                lhs: Expression::SubscriptExpression(SubscriptExpression {
+                    base_expression: identifier.clone(),
+                    index_expression: Box::new(key),
+                }),
+                rhs: value,
+            }
+            .generate(function_context),
+        )
+    }
+}
+
 #[derive(Debug)]
 struct MoveAssignment {
     pub lhs: Expression,
@@ -2302,9 +3076,60 @@ struct MoveAssignment {
 }
 
 impl MoveAssignment {
+    // Fixed-size arrays start out with their declared number of elements
+    fn fill_fixed_array(&self, function_context: &FunctionContext) -> Option<MoveIRExpression> {
+        match &self.rhs {
+            Expression::ArrayLiteral(l) if l.elements.is_empty() => {}
+            _ => return None,
+        }
+        let enclosing = match &self.lhs {
+            Expression::Identifier(i) => i
+                .enclosing_type
+                .clone()
+                .unwrap_or_else(|| function_context.enclosing_type.clone()),
+            _ => return None,
+        };
+        let lhs_type = function_context.environment.get_expression_type(
+            self.lhs.clone(),
+            &enclosing,
+            vec![],
+            vec![],
+            function_context.ScopeContext.clone(),
+        );
+        // Without a default the array grows as its elements are set in order
+        let array = match lhs_type {
+            Type::FixedSizedArrayType(a)
+                if MoveRuntimeTypes::default_element(&a.key_type).is_some() =>
+            {
+                a
+            }
+            _ => return None,
+        };
+        let element = MoveType::move_type(
+            *array.key_type,
+            Option::from(function_context.environment.clone()),
+        )
+        .generate(function_context);
+        let lhs = MoveExpression {
+            expression: self.lhs.clone(),
+            position: MovePosition::Left,
+        }
+        .generate(function_context);
+        Some(MoveIRExpression::Assignment(MoveIRAssignment {
+            identifier: format!("{lhs}", lhs = lhs),
+            expresion: Box::new(MoveIRExpression::FunctionCall(MoveIRFunctionCall {
+                identifier: format!("Self.{}_fill", MoveRuntimeTypes::array_name(&element)),
+                arguments: vec![MoveIRExpression::Literal(MoveIRLiteral::Num(array.size))],
+            })),
+        }))
+    }
+
     pub fn generate(&self, function_context: &FunctionContext) -> MoveIRExpression {
 
         let lhs = self.lhs.clone();
+        if let Some(fill) = self.fill_fixed_array(function_context) {
+            return fill;
+        }
         if let Expression::Identifier(i) = &lhs {
             if i.enclosing_type.is_some() {
                 let enclosing = i.enclosing_type.clone();
@@ -2316,7 +3141,15 @@ impl MoveAssignment {
                     vec![],
                     function_context.ScopeContext.clone(),
                 );
-                if let Type::ArrayType(a) = var_type {
+                // Fixed-size arrays without a default start out empty
+                let element = match (var_type, &self.rhs) {
+                    (Type::ArrayType(a), _) => Some(*a.key_type),
+                    (Type::FixedSizedArrayType(a), Expression::ArrayLiteral(_)) => {
+                        Some(*a.key_type)
+                    }
+                    _ => None,
+                };
+                if let Some(element) = element {
                     let lhs_ir = MoveExpression {
                         expression: self.lhs.clone(),
                         position: MovePosition::Left,
@@ -2333,7 +3166,7 @@ impl MoveAssignment {
                         if let MoveIRExpression::Vector(v) = rhs_ir {
                             let mut vector = v.clone();
                             let vec_type = MoveType::move_type(
-                                *a.key_type,
+                                element,
                                 Option::from(function_context.environment.clone()),
                             )
                             .generate(function_context);
@@ -2470,7 +3303,7 @@ impl MoveVariableDeclaration {
     }
 }
 
-struct MoveRuntimeTypes {}
+pub struct MoveRuntimeTypes {}
 
 impl MoveRuntimeTypes {
     pub fn get_all_declarations() -> Vec<String> {
@@ -2478,6 +3311,67 @@ impl MoveRuntimeTypes {
         vec![libra]
     }
 
+    // Dictionaries are stored as parallel key and value vectors, one struct
+    // per key and value type pair used in the module
+    pub fn dictionary_name(dictionary: &DictionaryType) -> String {
+        let (key, _) = MoveRuntimeTypes::dictionary_element(*dictionary.key_type.clone());
+        let (value, _) = MoveRuntimeTypes::dictionary_element(*dictionary.value_type.clone());
+        format!(
+            "_Dictionary_{key}_{value}",
+            key = key,
+            value = value.trim_start_matches("Self.")
+        )
+    }
+
+    // Dictionary keys and elements that fixed-size arrays are filled with must
+    // be copyable and have a default value
+    pub fn default_element(element: &Type) -> Option<(String, String)> {
+        match element {
+            Type::Int => Some(("u64".to_string(), "0".to_string())),
+            Type::Bool => Some(("bool".to_string(), "false".to_string())),
+            Type::Address => Some(("address".to_string(), "0x0".to_string())),
+            Type::String => Some(("bytearray".to_string(), "h\"\"".to_string())),
+            _ => None,
+        }
+    }
+
+    // Dictionary values without a default are structs, or coins which are
+    // resources
+    pub fn dictionary_element(element: Type) -> (String, Option<String>) {
+        if let Some((element, default)) = MoveRuntimeTypes::default_element(&element) {
+            return (element, Some(default));
+        }
+        match element {
+            Type::UserDefinedType(i) => (format!("Self.{}", i.token), None),
+            t => panic!(
+                "Dictionaries of {} are not supported by the Move backend",
+                t.name()
+            ),
+        }
+    }
+
+    // Arrays are plain vectors, with one set of helpers per element type
+    pub fn array_name(element: &MoveIRType) -> String {
+        let element = format!("{}", element);
+        let element = element.trim_start_matches("Self.").replace(".", "_");
+        format!("_Array_{}", element)
+    }
+
+    pub fn dictionary_declaration(dictionary: &DictionaryType) -> String {
+        let kind = if dictionary.value_type.is_currency_type() {
+            "resource"
+        } else {
+            "struct"
+        };
+        format!(
+            "{kind} {name} {{ \n keys: vector<{key}>, \n values: vector<{value}> \n }}",
+            kind = kind,
+            name = MoveRuntimeTypes::dictionary_name(dictionary),
+            key = MoveRuntimeTypes::dictionary_element(*dictionary.key_type.clone()).0,
+            value = MoveRuntimeTypes::dictionary_element(*dictionary.value_type.clone()).0
+        )
+    }
+
     pub fn get_all_imports() -> Vec<MoveIRStatement> {
         let libra = MoveIRStatement::Import(MoveIRModuleImport {
             name: "LibraCoin".to_string(),
@@ -2497,12 +3391,17 @@ impl MoveRuntimeTypes {
 
 #[derive(Debug)]
 enum MoveRuntimeFunction {
-    AppendToArrayInt,
-    GetFromArrayInt,
-    AssignToFixedArray,
     RevertIfGreater,
+    Power,
+    WrappingAdd,
+    WrappingSub,
+    WrappingMul,
+    FixedMul,
+    FixedDiv,
     Transfer,
     WithdrawAll,
+    Lock,
+    Unlock,
 }
 
 impl MoveRuntimeFunction {
@@ -2513,17 +3412,53 @@ impl MoveRuntimeFunction {
         })
     }
 
-    pub fn append_to_array_int(vec: MoveIRExpression, value: MoveIRExpression) -> MoveIRExpression {
+    pub fn power(base: MoveIRExpression, exponent: MoveIRExpression) -> MoveIRExpression {
         MoveIRExpression::FunctionCall(MoveIRFunctionCall {
-            identifier: MoveRuntimeFunction::AppendToArrayInt.mangle_runtime(),
-            arguments: vec![vec, value],
+            identifier: MoveRuntimeFunction::Power.mangle_runtime(),
+            arguments: vec![base, exponent],
         })
     }
 
-    pub fn get_from_array_int(vec: MoveIRExpression, value: MoveIRExpression) -> MoveIRExpression {
+    pub fn wrapping_add(lhs: MoveIRExpression, rhs: MoveIRExpression) -> MoveIRExpression {
         MoveIRExpression::FunctionCall(MoveIRFunctionCall {
-            identifier: MoveRuntimeFunction::GetFromArrayInt.mangle_runtime(),
-            arguments: vec![vec, value],
+            identifier: MoveRuntimeFunction::WrappingAdd.mangle_runtime(),
+            arguments: vec![lhs, rhs],
+        })
+    }
+
+    pub fn wrapping_sub(lhs: MoveIRExpression, rhs: MoveIRExpression) -> MoveIRExpression {
+        MoveIRExpression::FunctionCall(MoveIRFunctionCall {
+            identifier: MoveRuntimeFunction::WrappingSub.mangle_runtime(),
+            arguments: vec![lhs, rhs],
+        })
+    }
+
+    pub fn wrapping_mul(lhs: MoveIRExpression, rhs: MoveIRExpression) -> MoveIRExpression {
+        MoveIRExpression::FunctionCall(MoveIRFunctionCall {
+            identifier: MoveRuntimeFunction::WrappingMul.mangle_runtime(),
+            arguments: vec![lhs, rhs],
+        })
+    }
+
+    pub fn fixed_mul(
+        lhs: MoveIRExpression,
+        rhs: MoveIRExpression,
+        scale: MoveIRExpression,
+    ) -> MoveIRExpression {
+        MoveIRExpression::FunctionCall(MoveIRFunctionCall {
+            identifier: MoveRuntimeFunction::FixedMul.mangle_runtime(),
+            arguments: vec![lhs, rhs, scale],
+        })
+    }
+
+    pub fn fixed_div(
+        lhs: MoveIRExpression,
+        rhs: MoveIRExpression,
+        scale: MoveIRExpression,
+    ) -> MoveIRExpression {
+        MoveIRExpression::FunctionCall(MoveIRFunctionCall {
+            identifier: MoveRuntimeFunction::FixedDiv.mangle_runtime(),
+            arguments: vec![lhs, rhs, scale],
         })
     }
 
@@ -2532,17 +3467,327 @@ impl MoveRuntimeFunction {
         format!("Self.{}", string)
     }
 
+    pub fn reentrancy_lock_field() -> String {
+        "Quartz_locked".to_string()
+    }
+
+    // Aborts when a @nonReentrant function is entered again before it returns
+    pub fn get_reentrancy_lock() -> String {
+        "_Lock(this: &mut Self.T) {
+        assert(*&mut copy(this).Quartz_locked == false, 1);
+        *&mut move(this).Quartz_locked = true;
+        return;
+    }
+
+    _Unlock(this: &mut Self.T) {
+        *&mut move(this).Quartz_locked = false;
+        return;
+    }"
+        .to_string()
+    }
+
     pub fn get_all_functions() -> Vec<String> {
         vec![
             MoveRuntimeFunction::get_revert_if_greater(),
-            MoveRuntimeFunction::get_array_funcs(),
+            MoveRuntimeFunction::get_arithmetic_funcs(),
             MoveRuntimeFunction::get_libra_internal(),
         ]
     }
 
+    // Move aborts on u64 overflow, so the plain operators are already checked.
+    // The wrapping forms reduce modulo 2^64 without ever overflowing. Fixed
+    // point products and quotients are rounded to the nearest value, halves
+    // rounding up. Products split both operands into whole and fractional
+    // parts, and quotients divide one decimal digit at a time, so that only the
+    // true result can overflow while the square of the scale and ten times the
+    // divisor fit in a u64.
+    pub fn get_arithmetic_funcs() -> String {
+        "_Power(base: u64, exponent: u64): u64 {
+        let result: u64;
+        result = 1;
+        while (copy(exponent) > 0) {
+            result = move(result) * copy(base);
+            exponent = move(exponent) - 1;
+        }
+        return move(result);
+    }
+
+    _WrappingAdd(a: u64, b: u64): u64 {
+        if (copy(b) > (18446744073709551615 - copy(a))) {
+            return move(b) - (18446744073709551615 - move(a)) - 1;
+        }
+        return move(a) + move(b);
+    }
+
+    _WrappingSub(a: u64, b: u64): u64 {
+        if (copy(b) > copy(a)) {
+            return (18446744073709551615 - (move(b) - move(a))) + 1;
+        }
+        return move(a) - move(b);
+    }
+
+    _WrappingMul(a: u64, b: u64): u64 {
+        let a_low: u64;
+        let a_high: u64;
+        let b_low: u64;
+        let b_high: u64;
+        let cross: u64;
+        a_low = copy(a) % 4294967296;
+        a_high = move(a) / 4294967296;
+        b_low = copy(b) % 4294967296;
+        b_high = move(b) / 4294967296;
+        cross = ((move(a_high) * copy(b_low)) % 4294967296
+            + (copy(a_low) * move(b_high)) % 4294967296)
+            % 4294967296;
+        return Self._WrappingAdd(move(a_low) * move(b_low), move(cross) * 4294967296);
+    }
+
+    _FixedMul(a: u64, b: u64, scale: u64): u64 {
+        let a_whole: u64;
+        let a_fraction: u64;
+        a_whole = copy(a) / copy(scale);
+        a_fraction = move(a) % copy(scale);
+        return move(a_whole) * copy(b)
+            + copy(a_fraction) * (copy(b) / copy(scale))
+            + (move(a_fraction) * (move(b) % copy(scale)) + copy(scale) / 2) / move(scale);
+    }
+
+    _FixedDiv(a: u64, b: u64, scale: u64): u64 {
+        let result: u64;
+        let remainder: u64;
+        result = copy(a) / copy(b);
+        remainder = move(a) % copy(b);
+        while (copy(scale) > 1) {
+            remainder = move(remainder) * 10;
+            result = move(result) * 10 + copy(remainder) / copy(b);
+            remainder = move(remainder) % copy(b);
+            scale = move(scale) / 10;
+        }
+        if (copy(remainder) >= copy(b) - copy(remainder)) {
+            result = move(result) + 1;
+        }
+        return move(result);
+    }"
+        .to_string()
+    }
+
+    // The dictionary is taken last so that the key and value, which may read
+    // the same dictionary, are evaluated before it is borrowed. Values without
+    // a default abort when their key is missing, and coins, which cannot be
+    // copied or dropped, are read through a mutable borrow and never
+    // overwritten.
+    pub fn dictionary_functions(dictionary: &DictionaryType) -> String {
+        let (key, _) = MoveRuntimeTypes::dictionary_element(*dictionary.key_type.clone());
+        let (value, default) =
+            MoveRuntimeTypes::dictionary_element(*dictionary.value_type.clone());
+        let name = MoveRuntimeTypes::dictionary_name(dictionary);
+
+        let common = format!(
+            "{name}_new(): Self.{name} {{
+        return {name} {{
+            keys: Vector.empty<{key}>(),
+            values: Vector.empty<{value}>()
+        }};
+    }}
+
+    {name}_find(key: {key}, this: &Self.{name}): u64 {{
+        let index: u64;
+        let length: u64;
+        index = 0;
+        length = Vector.length<{key}>(&copy(this).keys);
+        while (copy(index) < copy(length)) {{
+            if (*Vector.borrow<{key}>(&copy(this).keys, copy(index)) == copy(key)) {{
+                _ = move(this);
+                return move(index);
+            }}
+            index = move(index) + 1;
+        }}
+        _ = move(this);
+        return move(length);
+    }}
+
+    {name}_remove(key: {key}, this: &mut Self.{name}): {value} {{
+        let index: u64;
+        let last: u64;
+        let removed_key: {key};
+        index = Self.{name}_find(move(key), freeze(copy(this)));
+        last = Vector.length<{key}>(&copy(this).keys);
+        assert(copy(index) < copy(last), 1);
+        last = move(last) - 1;
+        Vector.swap<{key}>(&mut copy(this).keys, copy(index), copy(last));
+        Vector.swap<{value}>(&mut copy(this).values, move(index), move(last));
+        removed_key = Vector.pop_back<{key}>(&mut copy(this).keys);
+        _ = move(removed_key);
+        return Vector.pop_back<{value}>(&mut move(this).values);
+    }}",
+            name = name,
+            key = key,
+            value = value
+        );
+
+        let access = if dictionary.value_type.is_currency_type() {
+            format!(
+                "{name}_borrow(key: {key}, this: &mut Self.{name}): &mut {value} {{
+        let index: u64;
+        index = Self.{name}_find(move(key), freeze(copy(this)));
+        assert(copy(index) < Vector.length<{key}>(&copy(this).keys), 1);
+        return Vector.borrow_mut<{value}>(&mut move(this).values, move(index));
+    }}
+
+    {name}_insert(key: {key}, value: {value}, this: &mut Self.{name}) {{
+        let index: u64;
+        index = Self.{name}_find(copy(key), freeze(copy(this)));
+        assert(move(index) == Vector.length<{key}>(&copy(this).keys), 1);
+        Vector.push_back<{key}>(&mut copy(this).keys, move(key));
+        Vector.push_back<{value}>(&mut move(this).values, move(value));
+        return;
+    }}",
+                name = name,
+                key = key,
+                value = value
+            )
+        } else {
+            let missing = match default {
+                Some(default) => format!(
+                    "if (copy(index) >= Vector.length<{key}>(&copy(this).keys)) {{
+            _ = move(this);
+            return {default};
+        }}",
+                    key = key,
+                    default = default
+                ),
+                None => format!(
+                    "assert(copy(index) < Vector.length<{key}>(&copy(this).keys), 1);",
+                    key = key
+                ),
+            };
+            format!(
+                "{name}_get(key: {key}, this: &Self.{name}): {value} {{
+        let index: u64;
+        index = Self.{name}_find(move(key), copy(this));
+        {missing}
+        return *Vector.borrow<{value}>(&move(this).values, move(index));
+    }}
+
+    {name}_insert(key: {key}, value: {value}, this: &mut Self.{name}) {{
+        let index: u64;
+        let slot: &mut {value};
+        index = Self.{name}_find(copy(key), freeze(copy(this)));
+        if (copy(index) < Vector.length<{key}>(&copy(this).keys)) {{
+            slot = Vector.borrow_mut<{value}>(&mut move(this).values, move(index));
+            *move(slot) = move(value);
+            return;
+        }}
+        Vector.push_back<{key}>(&mut copy(this).keys, move(key));
+        Vector.push_back<{value}>(&mut move(this).values, move(value));
+        return;
+    }}",
+                name = name,
+                key = key,
+                value = value,
+                missing = missing
+            )
+        };
+
+        format!("{common}\n\n    {access}", common = common, access = access)
+    }
+
+    // Resources cannot be copied or dropped, so arrays of resources are read
+    // through a mutable borrow and can only be grown, never overwritten
+    pub fn array_functions(
+        element: &MoveIRType,
+        is_resource: bool,
+        default: &Option<String>,
+    ) -> String {
+        let mut common = format!(
+            "{name}_append(value: {element}, vec: &mut vector<{element}>) {{
+        Vector.push_back<{element}>(move(vec), move(value));
+        return;
+    }}
+
+    {name}_count(vec: &vector<{element}>): u64 {{
+        return Vector.length<{element}>(move(vec));
+    }}
+
+    {name}_remove(index: u64, vec: &mut vector<{element}>): {element} {{
+        let length: u64;
+        length = Vector.length<{element}>(freeze(copy(vec)));
+        assert(copy(index) < copy(length), 1);
+        while (copy(index) + 1 < copy(length)) {{
+            Vector.swap<{element}>(copy(vec), copy(index), copy(index) + 1);
+            index = move(index) + 1;
+        }}
+        return Vector.pop_back<{element}>(move(vec));
+    }}",
+            name = MoveRuntimeTypes::array_name(element),
+            element = element
+        );
+
+        if let Some(default) = default {
+            common = format!(
+                "{common}
+
+    {name}_fill(size: u64): vector<{element}> {{
+        let vec: vector<{element}>;
+        vec = Vector.empty<{element}>();
+        while (Vector.length<{element}>(&vec) < copy(size)) {{
+            Vector.push_back<{element}>(&mut vec, {default});
+        }}
+        return move(vec);
+    }}",
+                common = common,
+                name = MoveRuntimeTypes::array_name(element),
+                element = element,
+                default = default
+            );
+        }
+
+        let access = if is_resource {
+            format!(
+                "{name}_borrow(index: u64, vec: &mut vector<{element}>): &mut {element} {{
+        assert(copy(index) < Vector.length<{element}>(freeze(copy(vec))), 1);
+        return Vector.borrow_mut<{element}>(move(vec), move(index));
+    }}
+
+    {name}_set(index: u64, value: {element}, vec: &mut vector<{element}>) {{
+        assert(copy(index) == Vector.length<{element}>(freeze(copy(vec))), 1);
+        Vector.push_back<{element}>(move(vec), move(value));
+        return;
+    }}",
+                name = MoveRuntimeTypes::array_name(element),
+                element = element
+            )
+        } else {
+            format!(
+                "{name}_get(index: u64, vec: &vector<{element}>): {element} {{
+        assert(copy(index) < Vector.length<{element}>(copy(vec)), 1);
+        return *Vector.borrow<{element}>(move(vec), move(index));
+    }}
+
+    {name}_set(index: u64, value: {element}, vec: &mut vector<{element}>) {{
+        let length: u64;
+        let slot: &mut {element};
+        length = Vector.length<{element}>(freeze(copy(vec)));
+        assert(copy(index) <= copy(length), 1);
+        if (copy(index) == move(length)) {{
+            Vector.push_back<{element}>(move(vec), move(value));
+            return;
+        }}
+        slot = Vector.borrow_mut<{element}>(move(vec), move(index));
+        *move(slot) = move(value);
+        return;
+    }}",
+                name = MoveRuntimeTypes::array_name(element),
+                element = element
+            )
+        };
+
+        format!("{common}\n\n    {access}", common = common, access = access)
+    }
+
     pub fn get_revert_if_greater() -> String {
         format!(
-            "Quartz_RevertIfGreater(a: u64, b: u64): u64 {{  \n \
+            "_RevertIfGreater(a: u64, b: u64): u64 {{  \n \
              assert(copy(a) <= move(b), 1); \n \
              return move(a); \n }}"
         )
@@ -2556,47 +3801,8 @@ impl MoveRuntimeFunction {
         )
     }
 
-    pub fn get_array_funcs() -> String {
-        "
-
-        _GetFromArrayInt(vec: &mut vector<u64>, index: u64):u64 {
-            return  *Vector.borrow<u64>(freeze(move(vec)), move(index));
-        }
-
-
-        _insert_array_index_u64(vec: &mut vector<u64>, index: u64, value: u64) {
-    let length: u64;
-    let temp: u64;
-    length = Vector.length<u64>(freeze(copy(vec)));
-    Vector.push_back<u64>(copy(vec), move(value));
-    if (copy(length) == copy(index)) {
-      Vector.swap<u64>(copy(vec), copy(index), copy(length));
-      temp = Vector.pop_back<u64>(copy(vec));
-      _ = move(temp);
-    };
-    _ = move(vec);
-    return;
-  }
-
-
-  _insert_array_index_bool(vec: &mut vector<bool>, index: u64, value: bool) {
-    let length: u64;
-    let temp: bool;
-    length = Vector.length<bool>(freeze(copy(vec)));
-    Vector.push_back<bool>(copy(vec), move(value));
-    if (copy(length) == copy(index)) {
-      Vector.swap<bool>(copy(vec), copy(index), copy(length));
-      temp = Vector.pop_back<bool>(copy(vec));
-      _ = move(temp);
-    };
-    _ = move(vec);
-    return;
-  }"
-        .to_string()
-    }
-
     pub fn get_libra_internal() -> String {
-        "Quartz_Self_Create_Libra(input: LibraCoin.T) : Self.Libra {
+        "public Quartz_Self_Create_Libra(input: LibraCoin.T) : Self.Libra {
             return Self.Libra_produce(move(input));
         }
 
@@ -2678,6 +3884,15 @@ impl MoveRuntimeFunction {
     _ = move(_temp__5);
     _ = move(this);
     return;
+  }
+
+  Quartz_Libra_send_all (this: &mut Self.Libra, _payee: address)  {
+    let _temp__5: &mut Self.Libra_Coin;
+    let _amount: u64;
+    _temp__5 = &mut move(this).libra;
+    _amount = Self.Libra_Coin_getValue(copy(_temp__5));
+    Self.Libra_Coin_send(move(_temp__5), copy(_payee), move(_amount));
+    return;
   }"
         .to_string()
     }
@@ -2764,12 +3979,13 @@ impl MoveType {
                 MoveType::MutableReference(Box::from(base_type))
             }
             Type::ArrayType(a) => {
-                MoveType::Vector(Box::from(MoveType::move_type(*a.key_type, None)))
+                MoveType::Vector(Box::from(MoveType::move_type(*a.key_type, environment)))
             }
             Type::FixedSizedArrayType(a) => {
-                MoveType::Vector(Box::from(MoveType::move_type(*a.key_type, None)))
+                MoveType::Vector(Box::from(MoveType::move_type(*a.key_type, environment)))
             }
-            Type::DictionaryType(d) => MoveType::move_type(*d.value_type, None),
+            Type::DictionaryType(d) => MoveType::StructType(MoveRuntimeTypes::dictionary_name(&d)),
+            Type::FixedPointType(_) => MoveType::U64,
             Type::UserDefinedType(i) => {
                 if environment.is_some() {
                     let environment_value = environment.unwrap();
@@ -2801,7 +4017,7 @@ impl MoveType {
                         }
                     }
                     if environment_value.is_enum_declared(&i.token) {
-                        unimplemented!()
+                        return MoveType::U64;
                     } else {
                         return MoveType::StructType(i.token.clone());
                     }
@@ -3250,3 +4466,408 @@ impl fmt::Display for MoveIROperation {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use crate::AstProcessor::tests::{compile, preprocessed};
+    use crate::AstProcessor::Target;
+
+    const CURRENCY_PROPERTY: &str = "
+        contract C {
+          var funds: Currency = Currency()
+        }
+        C :: caller <- (any) {
+          public init() {}
+          public func pay(to: Address, amount: Int) mutates (funds) {
+            funds.send(to: to, amount: amount)
+          }
+        }";
+
+    #[test]
+    fn test_currency_property_receiver() {
+        let code = compile(CURRENCY_PROPERTY, Target::Move).unwrap();
+        assert!(code.contains("_temp__6 = &mut copy(this).funds;"));
+        assert!(code.contains("Self.Libra_send(copy(_temp__6), copy(_to), copy(_amount));"));
+    }
+
+    #[test]
+    fn test_currency_property_receiver_source() {
+        let code = compile(CURRENCY_PROPERTY, Target::MoveSource).unwrap();
+        assert!(code.contains("_temp__6 = &mut (copy this).funds;"));
+        assert!(code.contains("Libra_send(copy _temp__6, copy _to, copy _amount);"));
+    }
+
+    #[test]
+    fn test_libra_split() {
+        let code = compile(CURRENCY_PROPERTY, Target::Move).unwrap();
+        assert!(code.contains(
+            "Self.Libra_transferTo(copy(this), &mut _result, copy(_amount));"
+        ));
+        assert!(code.contains("ret = move(_result);"));
+        assert!(code.contains(
+            "__this_libra = Self.Libra_Coin_init(0x0000000000000000000000000000000000000000);"
+        ));
+    }
+
+    #[test]
+    fn test_dictionary_values() {
+        let program = "
+            struct Point {
+              var x: Int = 0
+              init() {}
+            }
+            contract Bank {
+              var points: [Address: Point] = [:]
+              var wallets: [Address: Libra] = [:]
+            }
+            Bank :: caller <- (any) {
+              public init() {}
+              public func setPoint(owner: Address) mutates (points) {
+                points[owner] = Point()
+              }
+              public func x(owner: Address) -> Int {
+                let point: Point = points[owner]
+                return point.x
+              }
+              public func balance(owner: Address) -> Int {
+                return wallets[owner].balance()
+              }
+            }";
+        let code = compile(program, Target::Move).unwrap();
+        assert!(code.contains("resource _Dictionary_address_Libra {"));
+        assert!(code.contains(
+            "Self._Dictionary_address_Point_insert(copy(_owner), Self.Point_init(), &mut copy(this).points);"
+        ));
+        assert!(code.contains(
+            "_point = Self._Dictionary_address_Point_get(copy(_owner), &copy(this).points);"
+        ));
+        assert!(code.contains(
+            "ret = Self.Libra_balance(Self._Dictionary_address_Libra_borrow(copy(_owner), &mut copy(this).wallets));"
+        ));
+        assert!(code.contains(
+            "_Dictionary_address_Point_remove(key: address, this: &mut Self._Dictionary_address_Point): Self.Point {"
+        ));
+        assert!(code.contains(
+            "_Dictionary_address_Libra_remove(key: address, this: &mut Self._Dictionary_address_Libra): Self.Libra {"
+        ));
+    }
+
+    #[test]
+    fn test_dictionary_keys() {
+        let program = "
+            struct Point {
+              var x: Int = 0
+              init() {}
+            }
+            contract Bank {
+              var owners: [Point: Address] = [:]
+            }
+            Bank :: caller <- (any) {
+              public init() {}
+            }";
+        let error = compile(program, Target::Move).unwrap_err();
+        assert!(error.contains("line 7: dictionaries keyed by Point are not supported"));
+    }
+
+    #[test]
+    fn test_array_elements() {
+        let program = "
+            struct Point {
+              var x: Int = 0
+              init() {}
+            }
+            contract Board {
+              var points: Point[2] = []
+              var flags: Bool[3] = []
+              var wallets: [Libra] = []
+            }
+            Board :: caller <- (any) {
+              public init() {}
+              public func place(index: Int) mutates (points, flags) {
+                points[index] = Point()
+                flags[index] = true
+              }
+              public func x(index: Int) -> Int {
+                let point: Point = points[index]
+                return point.x
+              }
+              public func balance(index: Int) -> Int {
+                return wallets[index].balance()
+              }
+            }";
+        let code = compile(program, Target::Move).unwrap();
+        assert!(code.contains("__this_points = Vector.empty<Self.Point>();"));
+        assert!(code.contains("__this_flags = Self._Array_bool_fill(3);"));
+        assert!(code.contains(
+            "Self._Array_Point_set(Self._RevertIfGreater(copy(_index), 1), Self.Point_init(), &mut copy(this).points);"
+        ));
+        assert!(code.contains(
+            "_point = Self._Array_Point_get(Self._RevertIfGreater(copy(_index), 1), &copy(this).points);"
+        ));
+        assert!(code.contains(
+            "ret = Self.Libra_balance(Self._Array_Libra_borrow(copy(_index), &mut copy(this).wallets));"
+        ));
+        for element in &["Point", "bool", "Libra"] {
+            assert!(code.contains(&format!("_Array_{}_append(", element)));
+            assert!(code.contains(&format!("_Array_{}_remove(", element)));
+        }
+    }
+
+    #[test]
+    fn test_enums() {
+        let program = "
+            enum Status: Int {
+              case pending = 1
+              case active = 2
+              case done
+            }
+            contract Job {
+              var status: Status = Status.pending
+            }
+            Job :: caller <- (any) {
+              public init() {}
+              public func start() mutates (status) {
+                status = Status.active
+              }
+              public func finish() mutates (status) {
+                status = Status.done
+              }
+              public func isActive() -> Bool {
+                return status == Status.active
+              }
+            }";
+        let code = compile(program, Target::Move).unwrap();
+        assert!(code.contains("status: u64"));
+        assert!(code.contains("__this_status = 1;"));
+        assert!(code.contains("*&mut copy(this).status = 2;"));
+        assert!(code.contains("*&mut copy(this).status = 3;"));
+        assert!(code.contains("ret = (*&mut copy(this).status == 2);"));
+    }
+
+    #[test]
+    fn test_contract_order() {
+        let program = "
+            contract Shop {
+              var counter: Counter
+            }
+            Shop :: caller <- (any) {
+              public init(counter: Counter) {
+                self.counter = counter
+              }
+            }
+            contract Counter {
+              var value: Int = 0
+            }
+            Counter :: caller <- (any) {
+              public init() {}
+            }";
+        let (module, context) = preprocessed(program, &Target::Move).unwrap();
+        let contracts = super::contracts(&module, &context).unwrap();
+        let names: Vec<String> = contracts
+            .iter()
+            .map(|c| c.contract_declaration.identifier.token.clone())
+            .collect();
+        assert_eq!(names, vec!["Counter", "Shop"]);
+    }
+
+    #[test]
+    fn test_cyclic_contracts() {
+        let program = "
+            contract Shop {
+              var counter: Counter
+            }
+            Shop :: caller <- (any) {
+              public init(counter: Counter) {
+                self.counter = counter
+              }
+            }
+            contract Counter {
+              var shop: Shop
+            }
+            Counter :: caller <- (any) {
+              public init(shop: Shop) {
+                self.shop = shop
+              }
+            }";
+        let (module, context) = preprocessed(program, &Target::Move).unwrap();
+        let error = super::contracts(&module, &context).err().unwrap();
+        assert!(error
+            .to_string()
+            .contains("Move modules cannot depend on each other cyclically"));
+    }
+
+    #[test]
+    fn test_scripts() {
+        let program = "
+            contract Shop {
+              var price: Int = 0
+              var till: Libra = Libra()
+            }
+            Shop :: caller <- (any) {
+              public init(price: Int) {
+                self.price = price
+              }
+              @payable
+              public func buy(payment: Libra) mutates (till) {
+                till.merge(source: payment)
+              }
+              public func getPrice() -> Int {
+                return price
+              }
+              public func tag(label: String) {}
+            }";
+        let (module, context) = preprocessed(program, &Target::Move).unwrap();
+        let contracts = super::contracts(&module, &context).unwrap();
+        let scripts = contracts[0].scripts();
+        let names: Vec<&str> = scripts.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["publish", "buy", "getPrice"]);
+        let script = |name: &str| {
+            scripts
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, s)| s.to_string())
+                .unwrap()
+        };
+        let publish = script("publish");
+        assert!(publish.contains("main(_price: u64) {"));
+        assert!(publish.contains("Shop.publish(move(_price));"));
+        let buy = script("buy");
+        assert!(buy.contains("main(contract: address, __payment: u64) {"));
+        assert!(buy.contains(
+            "__payment_value = LibraAccount.withdraw_from_sender(move(__payment));"
+        ));
+        assert!(buy.contains("Shop.buy(move(contract), move(__payment_value));"));
+        assert!(script("getPrice").contains("_ = Shop.getPrice(move(contract));"));
+    }
+
+    #[test]
+    fn test_binary_operators() {
+        let program = "
+            contract C {
+              var total: Int = 0
+            }
+            C :: caller <- (any) {
+              public init() {}
+              public func ops(a: Int, b: Int) -> Bool mutates (total) {
+                total += a
+                total = a + b
+                total = a &+ b
+                total = a &- b
+                total = a &* b
+                total = a ** b
+                let p: Bool = (a <= b) ==> (a >= b)
+                return p
+              }
+            }";
+        let code = compile(program, Target::Move).unwrap();
+        assert!(code.contains("*&mut copy(this).total = (*&mut copy(this).total + copy(_a));"));
+        assert!(code.contains("*&mut copy(this).total = (copy(_a) + copy(_b));"));
+        assert!(code.contains("Self._WrappingAdd(copy(_a), copy(_b))"));
+        assert!(code.contains("Self._WrappingSub(copy(_a), copy(_b))"));
+        assert!(code.contains("Self._WrappingMul(copy(_a), copy(_b))"));
+        assert!(code.contains("Self._Power(copy(_a), copy(_b))"));
+        assert!(code.contains("_p = (!(copy(_a) <= copy(_b)) || (copy(_a) >= copy(_b)));"));
+    }
+
+    const NON_REENTRANT: &str = "
+        contract Counter {
+          var total: Int = 0
+        }
+        Counter :: caller <- (any) {
+          public init() {}
+
+          @nonReentrant
+          public func increment() mutates (total) {
+            total += 1
+          }
+
+          public func incrementTwice() mutates (total) {
+            increment()
+            increment()
+          }
+        }";
+
+    #[test]
+    fn test_non_reentrant() {
+        let code = compile(NON_REENTRANT, Target::Move).unwrap();
+        assert!(code.contains("Quartz_locked: bool"));
+        assert!(code.contains("Quartz_locked: false"));
+        assert!(code.contains(
+            "Self._Lock(copy(this));\n    Self.Counter_increment(copy(this));\n    Self._Unlock(copy(this));"
+        ));
+        // The internal calls of incrementTwice do not take the lock
+        assert_eq!(code.matches("Self._Lock(").count(), 1);
+    }
+
+    // The fixed point runtime functions with the same u64 operations, so that
+    // an overflow Move would abort on panics here
+    fn fixed_mul(a: u64, b: u64, scale: u64) -> u64 {
+        let (a_whole, a_fraction) = (a / scale, a % scale);
+        a_whole * b + a_fraction * (b / scale) + (a_fraction * (b % scale) + scale / 2) / scale
+    }
+
+    fn fixed_div(a: u64, b: u64, mut scale: u64) -> u64 {
+        let (mut result, mut remainder) = (a / b, a % b);
+        while scale > 1 {
+            remainder *= 10;
+            result = result * 10 + remainder / b;
+            remainder %= b;
+            scale /= 10;
+        }
+        if remainder >= b - remainder {
+            result += 1;
+        }
+        result
+    }
+
+    #[test]
+    fn test_fixed_point_runtime() {
+        let program = "
+            contract Vault {
+              var rate: Fixed<4> = 1.5
+            }
+            Vault :: caller <- (any) {
+              public init() {}
+
+              public func apply(factor: Fixed<4>) mutates (rate) {
+                rate = rate * factor
+                rate = rate / factor
+              }
+            }";
+        let code = compile(program, Target::Move).unwrap();
+        assert!(code.contains("Self._FixedMul("));
+        assert!(code.contains("Self._FixedDiv("));
+        assert!(code.contains("a_fraction = move(a) % copy(scale);"));
+        assert!(code.contains("remainder = move(remainder) * 10;"));
+
+        // Rounded to the nearest value, halves rounding up, even where a
+        // fractional part times the other operand does not fit in a u64
+        let scale = 10_000;
+        let expected = |n: u128, d: u128| ((n + d / 2) / d) as u64;
+        let cases = [
+            (9_999, 10_000_000_000_000_000),
+            (10_000_000_000_009_999, 15_000),
+            (5_000, 3),
+            (15_000, 15_000),
+        ];
+        for (a, b) in cases.iter().cloned() {
+            assert_eq!(
+                fixed_mul(a, b, scale),
+                expected(a as u128 * b as u128, scale as u128)
+            );
+        }
+        let cases = [
+            (10_000_000_000_000_000, 7_000_000_000_000_000),
+            (20_000, 30_000),
+            (5, 20_000),
+            (15_000, 15_000),
+        ];
+        for (a, b) in cases.iter().cloned() {
+            assert_eq!(
+                fixed_div(a, b, scale),
+                expected(a as u128 * scale as u128, b as u128)
+            );
+        }
+    }
+}
@@ -0,0 +1,594 @@
+use super::context::*;
+use super::environment::*;
+use super::visitor::*;
+use super::CallGraph::*;
+use super::AST::*;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+// Checks the mutates clause of every function against the properties it may
+// change, directly, through nested fields, through inout arguments or through
+// the functions it calls.
+pub struct MutationChecker {
+    functions: Vec<FunctionMutations>,
+}
+
+struct FunctionMutations {
+    key: FunctionKey,
+    declared: Vec<Identifier>,
+    // Whether the body shows every property the function changes, which it
+    // does not for the standard library and external functions
+    analysable: bool,
+    direct: Vec<(String, LineInfo)>,
+    calls: Vec<(Receiver, LineInfo)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Receiver {
+    Implicit,
+    Property(String),
+    Other,
+}
+
+// Why a function may change a property
+#[derive(Clone, Debug)]
+enum Mutation {
+    Direct(LineInfo),
+    Call(FunctionKey, LineInfo),
+}
+
+impl MutationChecker {
+    pub fn new() -> MutationChecker {
+        MutationChecker { functions: vec![] }
+    }
+
+    pub fn check(&self, graph: &CallGraph, environment: &Environment) -> VResult {
+        let (errors, warnings) = self.diagnostics(graph, environment);
+        for warning in &warnings {
+            println!("{}", warning);
+        }
+        if errors.is_empty() {
+            return Ok(());
+        }
+
+        for error in &errors {
+            println!("{}", error);
+        }
+        Err(Box::from(errors.join("\n")))
+    }
+
+    // Calls to a function of a trait may run the function of any type that
+    // implements it, other calls run the callee itself
+    fn implementations(&self, callee: &FunctionKey, environment: &Environment) -> Vec<FunctionKey> {
+        let implements = |key: &FunctionKey| {
+            let conformances = match environment.types.get(&key.enclosing) {
+                Some(t) => &t.conformances,
+                None => return false,
+            };
+            conformances.iter().any(|c| {
+                c.functions.get(&callee.name).is_some_and(|functions| {
+                    functions
+                        .iter()
+                        .any(|f| f.declaration.head.identifier.line_info == callee.line_info)
+                })
+            })
+        };
+        let implementations: Vec<FunctionKey> = self
+            .functions
+            .iter()
+            .map(|f| f.key.clone())
+            .filter(|key| key.name == callee.name && implements(key))
+            .collect();
+        if implementations.is_empty() {
+            return vec![callee.clone()];
+        }
+        implementations
+    }
+
+    // The errors and warnings for every function, in the order the functions
+    // are declared
+    fn diagnostics(
+        &self,
+        graph: &CallGraph,
+        environment: &Environment,
+    ) -> (Vec<String>, Vec<String>) {
+        let mut effective: HashMap<FunctionKey, HashMap<String, Mutation>> = HashMap::new();
+        for function in &self.functions {
+            let mut mutations = HashMap::new();
+            for (property, line_info) in &function.direct {
+                mutations
+                    .entry(property.clone())
+                    .or_insert_with(|| Mutation::Direct(line_info.clone()));
+            }
+            if !function.analysable {
+                for identifier in &function.declared {
+                    mutations
+                        .entry(identifier.token.clone())
+                        .or_insert_with(|| Mutation::Direct(identifier.line_info.clone()));
+                }
+            }
+            effective.insert(function.key.clone(), mutations);
+        }
+
+        // The mutations of callees are propagated until nothing changes, which
+        // also terminates for recursive calls
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for function in &self.functions {
+                for (receiver, line_info) in &function.calls {
+                    let callee = graph
+                        .calls
+                        .iter()
+                        .find(|c| c.caller == function.key && &c.line_info == line_info)
+                        .map(|c| c.callee.clone());
+                    let callee = match callee {
+                        Some(callee) => callee,
+                        None => continue,
+                    };
+                    let mut callee_mutations: Vec<String> = vec![];
+                    for implementation in self.implementations(&callee, environment) {
+                        match effective.get(&implementation) {
+                            Some(mutations) => callee_mutations.extend(mutations.keys().cloned()),
+                            None => callee_mutations
+                                .extend(declared_mutations(&implementation, environment)),
+                        }
+                    }
+
+                    let propagated = match receiver {
+                        Receiver::Implicit if callee.enclosing == function.key.enclosing => {
+                            callee_mutations
+                        }
+                        Receiver::Property(property) if !callee_mutations.is_empty() => {
+                            vec![property.clone()]
+                        }
+                        _ => vec![],
+                    };
+
+                    let mutations = effective.get_mut(&function.key).unwrap();
+                    for property in propagated {
+                        if let Entry::Vacant(entry) = mutations.entry(property) {
+                            entry.insert(Mutation::Call(callee.clone(), line_info.clone()));
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut errors = vec![];
+        let mut warnings = vec![];
+        for function in &self.functions {
+            if Environment::is_stdlib_type(&function.key.enclosing)
+                || !environment.is_type_declared(&function.key.enclosing)
+            {
+                continue;
+            }
+
+            let declared: Vec<String> = function.declared.iter().map(|i| i.token.clone()).collect();
+            let mutations = &effective[&function.key];
+            let mut properties: Vec<&String> = mutations.keys().collect();
+            properties.sort();
+            for property in properties {
+                if declared.contains(property) {
+                    continue;
+                }
+                let error = match &mutations[property] {
+                    Mutation::Direct(line_info) => format!(
+                        "Mutating {} on line {} in {} which does not declare it in mutates",
+                        property, line_info.line, function.key
+                    ),
+                    Mutation::Call(callee, line_info) => format!(
+                        "Mutating {} through the call to {} on line {} in {} which does not declare it in mutates",
+                        property, callee, line_info.line, function.key
+                    ),
+                };
+                errors.push(error);
+            }
+
+            for identifier in &function.declared {
+                if !mutations.contains_key(&identifier.token) {
+                    warnings.push(format!(
+                        "Warning on line {}: {} declares {} in mutates but never mutates it",
+                        identifier.line_info.line, function.key, identifier.token
+                    ));
+                }
+            }
+        }
+
+        (errors, warnings)
+    }
+}
+
+// The properties a function without a body of its own declares it mutates
+fn declared_mutations(key: &FunctionKey, environment: &Environment) -> Vec<String> {
+    let functions = environment
+        .types
+        .get(&key.enclosing)
+        .and_then(|t| t.functions.get(&key.name));
+    let function = functions.and_then(|functions| {
+        functions
+            .iter()
+            .find(|f| f.declaration.head.identifier.line_info == key.line_info)
+    });
+    match function {
+        Some(function) => function
+            .declaration
+            .mutates()
+            .iter()
+            .map(|m| m.token.clone())
+            .collect(),
+        None => vec![],
+    }
+}
+
+impl Visitor for MutationChecker {
+    fn start_function_declaration(
+        &mut self,
+        _t: &mut FunctionDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        let enclosing = match _ctx.enclosing_type_identifier() {
+            Some(enclosing) => enclosing.token,
+            None => return Ok(()),
+        };
+
+        let mut walker = MutationWalker {
+            environment: &_ctx.environment,
+            enclosing: enclosing.clone(),
+            locals: _t
+                .head
+                .parameters
+                .iter()
+                .map(|p| p.identifier.token.clone())
+                .collect(),
+            direct: vec![],
+            calls: vec![],
+        };
+        walker.block(&_t.body);
+
+        self.functions.push(FunctionMutations {
+            key: FunctionKey::function(&enclosing, _t),
+            declared: _t.mutates(),
+            analysable: !Environment::is_stdlib_type(&enclosing) && !_t.is_external,
+            direct: walker.direct,
+            calls: walker.calls,
+        });
+        Ok(())
+    }
+}
+
+struct MutationWalker<'a> {
+    environment: &'a Environment,
+    enclosing: TypeIdentifier,
+    locals: Vec<String>,
+    direct: Vec<(String, LineInfo)>,
+    calls: Vec<(Receiver, LineInfo)>,
+}
+
+impl<'a> MutationWalker<'a> {
+    // Locals declared in a block go out of scope at its end
+    fn block(&mut self, statements: &[Statement]) {
+        let depth = self.locals.len();
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.locals.truncate(depth);
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::ReturnStatement(r) => {
+                if let Some(expression) = &r.expression {
+                    self.expression(expression);
+                }
+            }
+            Statement::Expression(e) => self.expression(e),
+            Statement::BecomeStatement(b) => self.expression(&b.expression),
+            Statement::EmitStatement(e) => self.arguments(&e.function_call.arguments),
+            Statement::ForStatement(f) => {
+                self.expression(&f.iterable);
+                self.locals.push(f.variable.identifier.token.clone());
+                self.block(&f.body);
+                self.locals.pop();
+            }
+            Statement::IfStatement(i) => {
+                self.expression(&i.condition);
+                self.block(&i.body);
+                self.block(&i.else_body);
+            }
+            Statement::DoCatchStatement(d) => {
+                self.block(&d.do_body);
+                self.block(&d.catch_body);
+            }
+            Statement::TargetStatement(t) => self.statement(&t.statement),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::BinaryExpression(b) => {
+                if b.op.is_assignment() {
+                    if let Some(property) = self.property_root(&b.lhs_expression) {
+                        let line_info = b.lhs_expression.get_line_info();
+                        self.direct.push((property, line_info));
+                    }
+                }
+
+                if let Some((receiver, call)) = self.dot_call(b) {
+                    self.call(call, receiver);
+                    return;
+                }
+                self.expression(&b.lhs_expression);
+                self.expression(&b.rhs_expression);
+            }
+            Expression::InoutExpression(i) => {
+                // Passing a property by reference lets the callee change it
+                if let Some(property) = self.property_root(&i.expression) {
+                    self.direct.push((property, i.expression.get_line_info()));
+                }
+                self.expression(&i.expression);
+            }
+            Expression::ExternalCall(e) => {
+                self.arguments(&e.arguments);
+                self.expression(&e.function_call.lhs_expression);
+                if let Expression::FunctionCall(f) = &*e.function_call.rhs_expression {
+                    self.arguments(&f.arguments);
+                }
+            }
+            Expression::FunctionCall(f) => self.call(f, Receiver::Implicit),
+            Expression::VariableDeclaration(v) => {
+                self.locals.push(v.identifier.token.clone());
+                if let Some(expression) = &v.expression {
+                    self.expression(expression);
+                }
+            }
+            Expression::BracketedExpression(b) => self.expression(&b.expression),
+            Expression::AttemptExpression(a) => self.call(&a.function_call, Receiver::Implicit),
+            Expression::ArrayLiteral(a) => {
+                for element in &a.elements {
+                    self.expression(element);
+                }
+            }
+            Expression::SubscriptExpression(s) => self.expression(&s.index_expression),
+            Expression::RangeExpression(r) => {
+                self.expression(&r.start_expression);
+                self.expression(&r.end_expression);
+            }
+            Expression::CastExpression(c) => self.expression(&c.expression),
+            Expression::Sequence(expressions) => {
+                for expression in expressions {
+                    self.expression(expression);
+                }
+            }
+            Expression::Identifier(_)
+            | Expression::Literal(_)
+            | Expression::DictionaryLiteral(_)
+            | Expression::SelfExpression
+            | Expression::RawAssembly(_, _) => {}
+        }
+    }
+
+    fn call(&mut self, call: &FunctionCall, receiver: Receiver) {
+        self.calls
+            .push((receiver, call.identifier.line_info.clone()));
+        self.arguments(&call.arguments);
+    }
+
+    fn arguments(&mut self, arguments: &[FunctionArgument]) {
+        for argument in arguments {
+            self.expression(&argument.expression);
+        }
+    }
+
+    // A call such as self.a.b.f(), made on the property the chain starts from
+    fn dot_call<'b>(&self, b: &'b BinaryExpression) -> Option<(Receiver, &'b FunctionCall)> {
+        if let BinOp::Dot = b.op {
+        } else {
+            return None;
+        }
+
+        match &*b.rhs_expression {
+            Expression::FunctionCall(f) => Some((self.receiver(&b.lhs_expression), f)),
+            Expression::BinaryExpression(inner) => {
+                let (receiver, call) = self.dot_call(inner)?;
+                match &*b.lhs_expression {
+                    Expression::SelfExpression => Some((receiver, call)),
+                    lhs => Some((self.receiver(lhs), call)),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn receiver(&self, expression: &Expression) -> Receiver {
+        if let Expression::SelfExpression = expression {
+            return Receiver::Implicit;
+        }
+        match self.property_root(expression) {
+            Some(property) => Receiver::Property(property),
+            None => Receiver::Other,
+        }
+    }
+
+    // The property of the enclosing type that an lvalue such as self.a.b[i]
+    // belongs to
+    fn property_root(&self, expression: &Expression) -> Option<String> {
+        match expression {
+            Expression::Identifier(i) => {
+                if self.locals.contains(&i.token)
+                    || !self
+                        .environment
+                        .is_property_defined(i.token.clone(), &self.enclosing)
+                {
+                    return None;
+                }
+                Some(i.token.clone())
+            }
+            Expression::BinaryExpression(b) => match b.op {
+                BinOp::Dot => match &*b.lhs_expression {
+                    Expression::SelfExpression => self.self_property_root(&b.rhs_expression),
+                    lhs => self.property_root(lhs),
+                },
+                _ => None,
+            },
+            Expression::SubscriptExpression(s) => {
+                self.property_root(&Expression::Identifier(s.base_expression.clone()))
+            }
+            Expression::BracketedExpression(b) => self.property_root(&b.expression),
+            Expression::InoutExpression(i) => self.property_root(&i.expression),
+            _ => None,
+        }
+    }
+
+    // The right hand side of self., which names a property even when a local
+    // variable hides it
+    fn self_property_root(&self, expression: &Expression) -> Option<String> {
+        let identifier = match expression {
+            Expression::Identifier(i) => i,
+            Expression::BinaryExpression(b) if b.op == BinOp::Dot => {
+                return self.self_property_root(&b.lhs_expression);
+            }
+            Expression::SubscriptExpression(s) => &s.base_expression,
+            _ => return None,
+        };
+
+        if self
+            .environment
+            .is_property_defined(identifier.token.clone(), &self.enclosing)
+        {
+            return Some(identifier.token.clone());
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::MutationChecker;
+    use crate::AstProcessor::tests::checked;
+    use crate::AstProcessor::Target;
+    use crate::CallGraph::CallGraph;
+    use crate::AST::Visitable;
+
+    // The warnings for a program that checks
+    fn warnings(program: &str, target: &Target) -> Vec<String> {
+        let (mut module, mut context) = checked(program, target).unwrap();
+        let graph = &mut CallGraph::new();
+        module.visit(graph, &mut context).unwrap();
+        let checker = &mut MutationChecker::new();
+        module.visit(checker, &mut context).unwrap();
+        checker.diagnostics(graph, &context.environment).1
+    }
+
+    #[test]
+    fn test_transitive_mutations() {
+        let program = "
+            struct Point {
+              var x: Int = 0
+
+              func move(by: Int) mutates (x) {
+                x += by
+              }
+            }
+            contract Board {
+              var origin: Point = Point()
+              var count: Int = 0
+              var unused: Int = 0
+            }
+            Board :: caller <- (any) {
+              public init() {}
+
+              func increment() mutates (count) {
+                count += 1
+              }
+
+              func bump(value: inout Int) {
+                value += 1
+              }
+
+              public func step() {
+                increment()
+              }
+
+              public func shift() {
+                origin.move(by: 1)
+              }
+
+              public func pass() {
+                bump(value: &count)
+              }
+
+              public func later() mutates (count) {
+                step()
+              }
+            }";
+        let error = checked(program, &Target::Ether).err().unwrap();
+        let errors: Vec<&str> = error.lines().collect();
+        assert_eq!(
+            errors,
+            vec![
+                "Mutating count through the call to Board.increment on line 26 in Board.step which does not declare it in mutates",
+                "Mutating origin through the call to Point.move on line 30 in Board.shift which does not declare it in mutates",
+                "Mutating count on line 34 in Board.pass which does not declare it in mutates",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_mutates() {
+        let program = "
+            contract Counter {
+              var count: Int = 0
+              var total: Int = 0
+            }
+            Counter :: caller <- (any) {
+              public init() {}
+
+              public func increment() mutates (count, total) {
+                count += 1
+              }
+            }";
+        assert_eq!(
+            warnings(program, &Target::Ether),
+            vec!["Warning on line 9: Counter.increment declares total in mutates but never mutates it"]
+        );
+    }
+
+    #[test]
+    fn test_currency_mutations() {
+        let currency = "
+            contract C {
+              var funds: Currency = Currency()
+            }
+            C :: caller <- (any) {
+              public init() {}
+              public func pay(to: Address, amount: Int) mutates (funds) {
+                funds.send(to: to, amount: amount)
+              }
+            }";
+        assert!(warnings(currency, &Target::Ether).is_empty());
+        assert!(warnings(currency, &Target::Move).is_empty());
+
+        let libra = "
+            contract Shop {
+              var till: Libra = Libra()
+            }
+            Shop :: caller <- (any) {
+              public init() {}
+              @payable
+              public func buy(payment: Libra) mutates (till) {
+                till.merge(source: payment)
+              }
+            }";
+        assert!(warnings(libra, &Target::Move).is_empty());
+        let error = checked(&libra.replace(" mutates (till)", ""), &Target::Move)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            "Mutating till through the call to Libra.merge on line 9 in Shop.buy which does not declare it in mutates"
+        );
+    }
+}
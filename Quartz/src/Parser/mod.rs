@@ -5,15 +5,21 @@ extern crate nom_locate;
 use nom_locate::{position, LocatedSpan};
 
 use crate::environment::Environment;
+use crate::AstProcessor::Target;
+use crate::TargetStripper::strip_module;
 use nom::{branch::alt, bytes::complete::tag, combinator::map, multi::many0, sequence::preceded};
 use std::collections::HashSet;
 
 type ParseResult = (Option<Module>, Environment);
 
-type Span<'a> = LocatedSpan<&'a str>;
+// The extra field holds the number of standard library lines in front of the
+// program, so that line numbers are those of the program file
+type Span<'a> = LocatedSpan<&'a str, u32>;
 
-pub fn parse_program(i: &str) -> ParseResult {
-    let input = LocatedSpan::new(i);
+pub fn parse_program(stdlib: &str, program: &str, target: &Target) -> ParseResult {
+    let stdlib_lines = stdlib.matches('\n').count() as u32 + 1;
+    let i = &format!("{stdlib} \n {program}", stdlib = stdlib, program = program);
+    let input = LocatedSpan::new_extra(i.as_str(), stdlib_lines);
     let result = parse_module(input);
 
     let module = match result {
@@ -30,14 +36,17 @@ pub fn parse_program(i: &str) -> ParseResult {
         ..Default::default()
     };
     if module.is_some() {
-        let module = module.unwrap();
+        let mut module = module.unwrap();
+        if strip_module(&mut module, target).is_err() {
+            return (None, environment);
+        }
         environment.build(module.clone());
         return (Option::from(module), environment);
     }
     (module, environment)
 }
 
-fn parse_module(i: Span) -> nom::IResult<Span, Module> {
+pub fn parse_module(i: Span) -> nom::IResult<Span, Module> {
     let (i, _) = whitespace(i)?;
     let (i, declarations) = many0(nom::sequence::terminated(
         parse_top_level_declaration,
@@ -237,22 +246,25 @@ fn integer(input: Span) -> nom::IResult<Span, Literal> {
 }
 
 fn float(input: Span) -> nom::IResult<Span, Literal> {
-    let (i, float) = nom::combinator::map_res(
-        nom::combinator::recognize(nom::sequence::delimited(
-            nom::character::complete::digit1,
-            tag("."),
-            nom::character::complete::digit1,
-        )),
-        |s: Span| s.fragment().parse::<f64>(),
-    )(input)?;
-    Ok((i, Literal::FloatLiteral(float)))
+    let (i, float) = nom::combinator::recognize(nom::sequence::delimited(
+        nom::character::complete::digit1,
+        tag("."),
+        nom::character::complete::digit1,
+    ))(input)?;
+    Ok((i, Literal::FloatLiteral(float.fragment().to_string())))
+}
+
+// Lines of the standard library keep their position within it
+fn line_info(i: &Span) -> LineInfo {
+    let line = i.location_line();
+    LineInfo {
+        line: if line > i.extra { line - i.extra } else { line },
+        offset: i.location_offset(),
+    }
 }
 
 fn parse_identifier(i: Span) -> nom::IResult<Span, Identifier> {
-    let line_info = LineInfo {
-        line: i.location_line(),
-        offset: i.location_offset(),
-    };
+    let line_info = line_info(&i);
     let (i, head) = alt((nom::character::complete::alpha1, tag("_")))(i)?;
     let (i, tail) = nom::combinator::recognize(many0(alt((
         nom::character::complete::alphanumeric1,
@@ -289,10 +301,7 @@ fn parse_parameter_list(i: Span) -> nom::IResult<Span, Vec<Parameter>> {
 }
 
 fn parse_parameter(i: Span) -> nom::IResult<Span, Parameter> {
-    let line_info = LineInfo {
-        line: i.location_line(),
-        offset: i.location_offset(),
-    };
+    let line_info = line_info(&i);
     let (i, identifier) = parse_identifier(i)?;
     let (i, type_assigned) = parse_type_annotation(i)?;
     let (i, equal) = nom::combinator::opt(preceded(whitespace, equal_operator))(i)?;
@@ -331,6 +340,7 @@ fn parse_type(i: Span) -> nom::IResult<Span, Type> {
         parse_fixed_array_type,
         parse_array_type,
         parse_dictionary_type,
+        parse_fixed_point_type,
         parse_self_type,
         parse_basic_type,
         parse_inout_type,
@@ -339,6 +349,18 @@ fn parse_type(i: Span) -> nom::IResult<Span, Type> {
     ))(i)
 }
 
+fn parse_fixed_point_type(i: Span) -> nom::IResult<Span, Type> {
+    let (i, _) = tag("Fixed")(i)?;
+    let (i, _) = tag("<")(i)?;
+    let (i, decimals) = nom::combinator::map_res(nom::character::complete::digit1, |s: Span| {
+        s.fragment().parse::<u64>()
+    })(i)?;
+    let (i, _) = tag(">")(i)?;
+
+    let fixed_point_type = FixedPointType { decimals };
+    Ok((i, Type::FixedPointType(fixed_point_type)))
+}
+
 fn parse_self_type(i: Span) -> nom::IResult<Span, Type> {
     let (i, _) = tag("Self")(i)?;
     Ok((i, Type::SelfType))
@@ -525,6 +547,7 @@ pub fn parse_expression(i: Span) -> nom::IResult<Span, Expression> {
             Expression::InoutExpression(inout)
         }),
         map(parse_external_call, |e| Expression::ExternalCall(e)),
+        map(parse_attempt_expression, |a| Expression::AttemptExpression(a)),
         map(parse_cast_expression, |c| Expression::CastExpression(c)),
         map(parse_binary_expression, |be| {
             Expression::BinaryExpression(be)
@@ -559,6 +582,7 @@ fn parse_expression_left(i: Span) -> nom::IResult<Span, Expression> {
             Expression::InoutExpression(inout)
         }),
         map(parse_external_call, |e| Expression::ExternalCall(e)),
+        map(parse_attempt_expression, |a| Expression::AttemptExpression(a)),
         map(parse_cast_expression, |c| Expression::CastExpression(c)),
         map(tag("self"), |_| Expression::SelfExpression),
         map(parse_subscript_expression, |s| {
@@ -574,6 +598,9 @@ fn parse_expression_left(i: Span) -> nom::IResult<Span, Expression> {
             Expression::BracketedExpression(b)
         }),
         map(parse_array_literal, |a| Expression::ArrayLiteral(a)),
+        map(parse_dictionary_literal, |d| {
+            Expression::DictionaryLiteral(d)
+        }),
         map(parse_dictionary_empty_literal, |a| {
             Expression::DictionaryLiteral(a)
         }),
@@ -630,6 +657,7 @@ fn parse_dictionary_empty_literal(i: Span) -> nom::IResult<Span, DictionaryLiter
 }
 
 fn parse_dictionary_literal(i: Span) -> nom::IResult<Span, DictionaryLiteral> {
+    let (i, _) = left_square_bracket(i)?;
     let (i, elements) = nom::multi::separated_nonempty_list(
         tag(","),
         nom::sequence::terminated(
@@ -637,12 +665,14 @@ fn parse_dictionary_literal(i: Span) -> nom::IResult<Span, DictionaryLiteral> {
             nom::character::complete::space0,
         ),
     )(i)?;
+    let (i, _) = right_square_bracket(i)?;
     Ok((i, DictionaryLiteral { elements }))
 }
 
 fn parse_dictionary_element(i: Span) -> nom::IResult<Span, (Expression, Expression)> {
     let (i, expression1) = parse_expression_left(i)?;
     let (i, _) = colon(i)?;
+    let (i, _) = nom::character::complete::space0(i)?;
     let (i, expression2) = parse_expression(i)?;
     Ok((i, (expression1, expression2)))
 }
@@ -667,8 +697,8 @@ fn parse_literal(i: Span) -> nom::IResult<Span, Literal> {
     alt((
         address_literal,
         parse_boolean_literal,
-        integer,
         float,
+        integer,
         string_literal,
     ))(i)
 }
@@ -738,12 +768,12 @@ pub fn whitespace(i: Span) -> nom::IResult<Span, Span> {
         nom::character::complete::line_ending,
         parse_comment,
     )))(i)?;
-    Ok((i, LocatedSpan::new("")))
+    Ok((i, LocatedSpan::new_extra("", i.extra)))
 }
 
 fn multi_whitespace(i: Span) -> nom::IResult<Span, Span> {
     let (i, _) = many0(alt((nom::character::complete::multispace1, parse_comment)))(i)?;
-    Ok((i, LocatedSpan::new("")))
+    Ok((i, LocatedSpan::new_extra("", i.extra)))
 }
 
 fn parse_inout_expression(i: Span) -> nom::IResult<Span, InoutExpression> {
@@ -769,6 +799,7 @@ fn parse_bracketed_expression(i: Span) -> nom::IResult<Span, BracketedExpression
 fn parse_attempt_expression(i: Span) -> nom::IResult<Span, AttemptExpression> {
     let (i, _) = tag("try")(i)?;
     let (i, kind) = alt((bang, question))(i)?;
+    let (i, _) = whitespace(i)?;
     let (i, function_call) = parse_function_call(i)?;
     let attempt_expression = AttemptExpression {
         kind: kind.fragment().to_string(),
@@ -792,10 +823,7 @@ pub fn parse_binary_expression_precedence(
     i: Span,
     operator_precedence: i32,
 ) -> nom::IResult<Span, Expression> {
-    let line_info = LineInfo {
-        line: i.location_line(),
-        offset: i.location_offset(),
-    };
+    let line_info = line_info(&i);
     let (i, lhs_expression) = parse_expression_left(i)?;
     let mut lhs_expression = lhs_expression;
     let mut result = lhs_expression.clone();
@@ -861,6 +889,16 @@ fn get_operator_precedence(op: &BinOp) -> i32 {
 }
 
 fn parse_binary_op(i: Span) -> nom::IResult<Span, BinOp> {
+    alt((
+        implies_operator,
+        overflowing_plus_operator,
+        overflowing_minus_operator,
+        overflowing_times_operator,
+        parse_arithmetic_op,
+    ))(i)
+}
+
+fn parse_arithmetic_op(i: Span) -> nom::IResult<Span, BinOp> {
     alt((
         double_equal_operator,
         not_equal_operator,
@@ -875,6 +913,7 @@ fn parse_binary_op(i: Span) -> nom::IResult<Span, BinOp> {
         power_operator,
         times_operator,
         divide_operator,
+        percent_operator,
         dot_operator,
         equal_operator,
         less_than_operator,
@@ -904,6 +943,31 @@ fn times_operator(i: Span) -> nom::IResult<Span, BinOp> {
     Ok((i, BinOp::Times))
 }
 
+fn overflowing_plus_operator(i: Span) -> nom::IResult<Span, BinOp> {
+    let (i, _) = tag("&+")(i)?;
+    Ok((i, BinOp::OverflowingPlus))
+}
+
+fn overflowing_minus_operator(i: Span) -> nom::IResult<Span, BinOp> {
+    let (i, _) = tag("&-")(i)?;
+    Ok((i, BinOp::OverflowingMinus))
+}
+
+fn overflowing_times_operator(i: Span) -> nom::IResult<Span, BinOp> {
+    let (i, _) = tag("&*")(i)?;
+    Ok((i, BinOp::OverflowingTimes))
+}
+
+fn percent_operator(i: Span) -> nom::IResult<Span, BinOp> {
+    let (i, _) = tag("%")(i)?;
+    Ok((i, BinOp::Percent))
+}
+
+fn implies_operator(i: Span) -> nom::IResult<Span, BinOp> {
+    let (i, _) = implies(i)?;
+    Ok((i, BinOp::Implies))
+}
+
 fn divide_operator(i: Span) -> nom::IResult<Span, BinOp> {
     let (i, _) = tag("/")(i)?;
     Ok((i, BinOp::Divide))
@@ -930,12 +994,14 @@ fn not_equal_operator(i: Span) -> nom::IResult<Span, BinOp> {
 }
 
 fn parse_event_declaration(i: Span) -> nom::IResult<Span, EventDeclaration> {
+    let (i, attributes) = parse_target_attributes(i)?;
     let (i, _event_token) = tag("event")(i)?;
     let (i, _) = whitespace(i)?;
     let (i, identifier) = parse_identifier(i)?;
     let (i, _) = whitespace(i)?;
     let (i, parameter_list) = parse_parameter_list(i)?;
     let event_declaration = EventDeclaration {
+        attributes,
         identifier,
         parameter_list,
     };
@@ -943,6 +1009,7 @@ fn parse_event_declaration(i: Span) -> nom::IResult<Span, EventDeclaration> {
 }
 
 pub fn parse_contract_declaration(i: Span) -> nom::IResult<Span, TopLevelDeclaration> {
+    let (i, attributes) = parse_target_attributes(i)?;
     let (i, _contract_token) = tag("contract")(i)?;
     let (i, identifier) = preceded(nom::character::complete::space0, parse_identifier)(i)?;
     let (i, _) = whitespace(i)?;
@@ -956,6 +1023,7 @@ pub fn parse_contract_declaration(i: Span) -> nom::IResult<Span, TopLevelDeclara
     let (i, _) = whitespace(i)?;
     let (i, _) = right_brace(i)?;
     let contract = ContractDeclaration {
+        attributes,
         identifier,
         contract_members,
         conformances,
@@ -975,6 +1043,7 @@ pub fn parse_contract_member(i: Span) -> nom::IResult<Span, ContractMember> {
 }
 
 pub fn parse_variable_declaration_enclosing(i: Span) -> nom::IResult<Span, VariableDeclaration> {
+    let (i, attributes) = parse_target_attributes(i)?;
     let (i, _) = parse_modifiers(i)?;
     let (i, _) = whitespace(i)?;
     let (i, declaration_token) = alt((tag("var"), tag("let")))(i)?;
@@ -985,6 +1054,7 @@ pub fn parse_variable_declaration_enclosing(i: Span) -> nom::IResult<Span, Varia
     let (i, equal_token) = nom::combinator::opt(equal_operator)(i)?;
     if equal_token.is_none() {
         let variable_declaration = VariableDeclaration {
+            attributes,
             declaration_token,
             identifier,
             variable_type: type_annotation.type_assigned,
@@ -994,6 +1064,7 @@ pub fn parse_variable_declaration_enclosing(i: Span) -> nom::IResult<Span, Varia
     }
     let (i, expression) = preceded(nom::character::complete::space0, parse_expression)(i)?;
     let variable_declaration = VariableDeclaration {
+        attributes,
         declaration_token,
         identifier,
         variable_type: type_annotation.type_assigned,
@@ -1007,19 +1078,26 @@ pub fn parse_variable_declaration(i: Span) -> nom::IResult<Span, VariableDeclara
     let (i, _) = whitespace(i)?;
     let (i, declaration_token) = alt((tag("var"), tag("let")))(i)?;
     let declaration_token = Some(declaration_token.fragment().to_string());
-    let (i, identifier) = preceded(nom::character::complete::space0, parse_identifier)(i)?;
-    let (i, type_annotation) = parse_type_annotation(i)?;
+    let (i, identifier) = preceded(nom::character::complete::space1, parse_identifier)(i)?;
+    let (i, type_annotation) = nom::combinator::opt(parse_type_annotation)(i)?;
     let (i, _) = whitespace(i)?;
+    // Without an annotation the type is inferred by the TypeAssigner
+    let variable_type = match type_annotation {
+        Some(type_annotation) => type_annotation.type_assigned,
+        None => Type::Error,
+    };
     let variable_declaration = VariableDeclaration {
+        attributes: vec![],
         declaration_token,
         identifier,
-        variable_type: type_annotation.type_assigned,
+        variable_type,
         expression: None,
     };
     Ok((i, variable_declaration))
 }
 
 fn parse_enum_declaration(i: Span) -> nom::IResult<Span, TopLevelDeclaration> {
+    let (i, attributes) = parse_target_attributes(i)?;
     let (i, enum_token) = tag("enum")(i)?;
     let (i, identifier) = preceded(nom::character::complete::space0, parse_identifier)(i)?;
     let (i, type_annotation) = nom::combinator::opt(parse_type_annotation)(i)?;
@@ -1046,6 +1124,7 @@ fn parse_enum_declaration(i: Span) -> nom::IResult<Span, TopLevelDeclaration> {
     let (i, _) = whitespace(i)?;
     let (i, _) = right_brace(i)?;
     let enum_declaration = EnumDeclaration {
+        attributes,
         enum_token: enum_token.to_string(),
         identifier,
         type_assigned,
@@ -1070,7 +1149,7 @@ fn parse_enum_member(i: Span) -> nom::IResult<Span, EnumMember> {
         };
         return Ok((i, enum_member));
     }
-    let (i, expression) = parse_expression(i)?;
+    let (i, expression) = preceded(whitespace, parse_expression)(i)?;
     let enum_member = EnumMember {
         case_token: case_token.to_string(),
         identifier,
@@ -1092,6 +1171,7 @@ fn parse_type_states(i: Span) -> nom::IResult<Span, Vec<TypeState>> {
 pub fn parse_contract_behaviour_declaration(
     i: Span,
 ) -> nom::IResult<Span, ContractBehaviourDeclaration> {
+    let (i, attributes) = parse_target_attributes(i)?;
     let (i, identifier) = parse_identifier(i)?;
     let (i, _) = whitespace(i)?;
     let (i, at_token) = nom::combinator::opt(at)(i)?;
@@ -1114,6 +1194,7 @@ pub fn parse_contract_behaviour_declaration(
     ))(i)?;
     let (i, _) = right_brace(i)?;
     let contract_behaviour_declaration = ContractBehaviourDeclaration {
+        attributes,
         members,
         identifier,
         states: type_states,
@@ -1163,6 +1244,7 @@ fn parse_special_declaration(i: Span) -> nom::IResult<Span, SpecialDeclaration>
 fn parse_special_signature_declaration(i: Span) -> nom::IResult<Span, SpecialSignatureDeclaration> {
     let (i, attributes) = parse_attributes(i)?;
     let (i, modifiers) = parse_modifiers(i)?;
+    let line_info = line_info(&i);
     let (i, special_token) = alt((tag("init"), tag("fallback")))(i)?;
     let (i, parameters) = parse_parameter_list(i)?;
     let (i, _) = whitespace(i)?;
@@ -1173,6 +1255,7 @@ fn parse_special_signature_declaration(i: Span) -> nom::IResult<Span, SpecialSig
         mutates,
         parameters,
         special_token: special_token.to_string(),
+        line_info,
     };
     Ok((i, special_signature_declaration))
 }
@@ -1246,7 +1329,8 @@ fn parse_statements(i: Span) -> nom::IResult<Span, Vec<Statement>> {
 }
 
 pub fn parse_statement(i: Span) -> nom::IResult<Span, Statement> {
-    alt((
+    let (i, attributes) = parse_target_attributes(i)?;
+    let (i, statement) = alt((
         parse_return_statement,
         parse_become_statement,
         parse_emit_statement,
@@ -1254,7 +1338,15 @@ pub fn parse_statement(i: Span) -> nom::IResult<Span, Statement> {
         parse_if_statement,
         parse_docatch_statement,
         map(parse_expression, |e| Statement::Expression(e)),
-    ))(i)
+    ))(i)?;
+    if attributes.is_empty() {
+        return Ok((i, statement));
+    }
+    let target_statement = TargetStatement {
+        attributes,
+        statement: Box::new(statement),
+    };
+    Ok((i, Statement::TargetStatement(target_statement)))
 }
 
 fn parse_docatch_statement(i: Span) -> nom::IResult<Span, Statement> {
@@ -1337,10 +1429,7 @@ pub fn parse_emit_statement(i: Span) -> nom::IResult<Span, Statement> {
 }
 
 fn parse_become_statement(i: Span) -> nom::IResult<Span, Statement> {
-    let line_info = LineInfo {
-        line: i.location_line(),
-        offset: i.location_offset(),
-    };
+    let line_info = line_info(&i);
     let (i, _) = tag("become")(i)?;
     let (i, _) = nom::character::complete::space0(i)?;
     let (i, expression) = parse_expression(i)?;
@@ -1352,10 +1441,7 @@ fn parse_become_statement(i: Span) -> nom::IResult<Span, Statement> {
 }
 
 fn parse_return_statement(i: Span) -> nom::IResult<Span, Statement> {
-    let line_info = LineInfo {
-        line: i.location_line(),
-        offset: i.location_offset(),
-    };
+    let line_info = line_info(&i);
     let (i, _) = tag("return")(i)?;
     let (i, _) = nom::character::complete::space0(i)?;
     let (i, expression) = nom::combinator::opt(parse_expression)(i)?;
@@ -1390,10 +1476,7 @@ fn parse_mutates(i: Span) -> nom::IResult<Span, Vec<Identifier>> {
 }
 
 fn parse_enclosing_identifier(i: Span) -> nom::IResult<Span, Identifier> {
-    let line_info = LineInfo {
-        line: i.location_line(),
-        offset: i.location_offset(),
-    };
+    let line_info = line_info(&i);
     let (i, enclosing_type) = parse_identifier(i)?;
     let (i, _) = dot_operator(i)?;
     let (i, identifier) = parse_identifier(i)?;
@@ -1411,7 +1494,7 @@ fn parse_result(i: Span) -> nom::IResult<Span, Option<Type>> {
         return Ok((i, None));
     }
     let (i, _) = nom::character::complete::space0(i)?;
-    let (i, identifier) = parse_identifier_type(i)?;
+    let (i, identifier) = alt((parse_fixed_point_type, parse_identifier_type))(i)?;
     Ok((i, Some(identifier)))
 }
 
@@ -1443,13 +1526,31 @@ fn parse_attributes(i: Span) -> nom::IResult<Span, Vec<Attribute>> {
 fn parse_attribute(i: Span) -> nom::IResult<Span, Attribute> {
     let (i, at) = at(i)?;
     let (i, identifier) = parse_identifier(i)?;
+    let (i, arguments) = nom::combinator::opt(parse_identifier_group)(i)?;
+    let arguments = arguments
+        .unwrap_or_default()
+        .into_iter()
+        .map(|a| a.token)
+        .collect();
     let attribute = Attribute {
         at_token: at.to_string(),
         identifier_token: identifier.token,
+        arguments,
+        line_info: identifier.line_info,
     };
     Ok((i, attribute))
 }
 
+// Declarations and statements take target attributes and lint attributes
+fn parse_target_attributes(i: Span) -> nom::IResult<Span, Vec<Attribute>> {
+    many0(nom::sequence::terminated(
+        nom::combinator::verify(parse_attribute, |a: &Attribute| {
+            a.is_target() || a.is_lint()
+        }),
+        whitespace,
+    ))(i)
+}
+
 fn parse_protection_binding(i: Span) -> nom::IResult<Span, Identifier> {
     let (i, identifier) = parse_identifier(i)?;
     let (i, _) = left_arrow(i)?;
@@ -1466,9 +1567,11 @@ fn parse_caller_protection_group(i: Span) -> nom::IResult<Span, Vec<CallerProtec
 }
 
 fn parse_asset_declaration(i: Span) -> nom::IResult<Span, TopLevelDeclaration> {
+    let (i, attributes) = parse_target_attributes(i)?;
     let (i, _struct_token) = tag("asset")(i)?;
     let (i, _) = nom::character::complete::space0(i)?;
     let (i, identifier) = parse_identifier(i)?;
+    let (i, conformances) = parse_conformances(i)?;
     let (i, _) = nom::character::complete::space0(i)?;
     let (i, _) = left_brace(i)?;
     let (i, members) = many0(nom::sequence::terminated(
@@ -1478,13 +1581,16 @@ fn parse_asset_declaration(i: Span) -> nom::IResult<Span, TopLevelDeclaration> {
     let (i, _) = whitespace(i)?;
     let (i, _) = right_brace(i)?;
     let asset_declaration = AssetDeclaration {
+        attributes,
         identifier,
+        conformances,
         members,
     };
     Ok((i, TopLevelDeclaration::AssetDeclaration(asset_declaration)))
 }
 
 fn parse_struct_declaration(i: Span) -> nom::IResult<Span, TopLevelDeclaration> {
+    let (i, attributes) = parse_target_attributes(i)?;
     let (i, _struct_token) = tag("struct")(i)?;
     let (i, _) = nom::character::complete::space0(i)?;
     let (i, identifier) = parse_identifier(i)?;
@@ -1498,6 +1604,7 @@ fn parse_struct_declaration(i: Span) -> nom::IResult<Span, TopLevelDeclaration>
     let (i, _) = whitespace(i)?;
     let (i, _) = right_brace(i)?;
     let struct_declaration = StructDeclaration {
+        attributes,
         identifier,
         conformances,
         members,
@@ -1551,11 +1658,12 @@ fn parse_conformances(i: Span) -> nom::IResult<Span, Vec<Conformance>> {
 }
 
 fn parse_trait_declaration(i: Span) -> nom::IResult<Span, TopLevelDeclaration> {
+    let (i, attributes) = parse_target_attributes(i)?;
     let (i, modifiers) = many0(nom::sequence::terminated(
         preceded(whitespace, parse_trait_modifier),
         whitespace,
     ))(i)?;
-    let (i, trait_kind) = tag("external")(i)?;
+    let (i, trait_kind) = alt((tag("external"), tag("struct"), tag("asset")))(i)?;
     let (i, _) = nom::character::complete::space0(i)?;
     let (i, _) = tag("trait")(i)?;
     let (i, _) = nom::character::complete::space0(i)?;
@@ -1568,6 +1676,7 @@ fn parse_trait_declaration(i: Span) -> nom::IResult<Span, TopLevelDeclaration> {
     ))(i)?;
     let (i, _) = right_brace(i)?;
     let trait_declaration = TraitDeclaration {
+        attributes,
         trait_kind: trait_kind.to_string(),
         identifier,
         members,
@@ -1629,15 +1738,18 @@ fn is_basic_type(basic_type: &str) -> bool {
 mod tests {
 
     use super::nom::error::ErrorKind;
-    use crate::Parser::{parse_caller_binding, parse_return_statement, parse_type};
-    use crate::AST::{Identifier, ReturnStatement, Statement, Type};
+    use crate::Parser::{
+        parse_caller_binding, parse_fixed_point_type, parse_statement, parse_type,
+        parse_variable_declaration_enclosing,
+    };
+    use crate::AST::{Expression, FixedPointType, Identifier, Statement, Type};
     use nom_locate::{position, LocatedSpan};
     use sha3::Digest;
 
     #[test]
     fn test_parse_int_type() {
         let input = "Int";
-        let input = LocatedSpan::new(input);
+        let input = LocatedSpan::new_extra(input, 0);
         let result = parse_type(input);
         match result {
             Ok((c, b)) => assert_eq!(b, Type::Int),
@@ -1648,7 +1760,7 @@ mod tests {
     #[test]
     fn test_parse_address_type() {
         let input = "Address";
-        let input = LocatedSpan::new(input);
+        let input = LocatedSpan::new_extra(input, 0);
         let result = parse_type(input);
         match result {
             Ok((c, b)) => assert_eq!(b, Type::Address),
@@ -1659,7 +1771,7 @@ mod tests {
     #[test]
     fn test_parse_bool_type() {
         let input = "Bool";
-        let input = LocatedSpan::new(input);
+        let input = LocatedSpan::new_extra(input, 0);
         let result = parse_type(input);
         match result {
             Ok((c, b)) => assert_eq!(b, Type::Bool),
@@ -1670,7 +1782,7 @@ mod tests {
     #[test]
     fn test_parse_string_type() {
         let input = "String";
-        let input = LocatedSpan::new(input);
+        let input = LocatedSpan::new_extra(input, 0);
         let result = parse_type(input);
         match result {
             Ok((c, b)) => assert_eq!(b, Type::String),
@@ -1678,10 +1790,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_fixed_point_type() {
+        let input = LocatedSpan::new_extra("Fixed<2>", 0);
+        match parse_fixed_point_type(input) {
+            Ok((_, t)) => assert_eq!(t, Type::FixedPointType(FixedPointType { decimals: 2 })),
+            Err(_) => assert_eq!(1, 0),
+        }
+        let input = LocatedSpan::new_extra("Fixed<99999999999999999999>", 0);
+        assert!(parse_fixed_point_type(input).is_err());
+    }
+
     #[test]
     fn test_parse_caller_binding() {
         let input = "caller <-";
-        let input = LocatedSpan::new(input);
+        let input = LocatedSpan::new_extra(input, 0);
         let result = parse_caller_binding(input);
         match result {
             Ok((c, b)) => assert_eq!(
@@ -1695,4 +1818,65 @@ mod tests {
             Err(_) => assert_eq!(1, 0),
         }
     }
+
+    #[test]
+    fn test_parse_target_statement() {
+        let input = "@target(yul, evm) return";
+        let input = LocatedSpan::new_extra(input, 0);
+        let result = parse_statement(input);
+        match result {
+            Ok((_, Statement::TargetStatement(t))) => {
+                assert_eq!(t.attributes[0].identifier_token, "target");
+                assert_eq!(t.attributes[0].arguments, vec!["yul", "evm"]);
+            }
+            _ => assert_eq!(1, 0),
+        }
+    }
+
+    #[test]
+    fn test_parse_lint_attribute() {
+        let input = "@allow(unused_properties, shadowing) var total: Int = 0";
+        let input = LocatedSpan::new_extra(input, 0);
+        let result = parse_variable_declaration_enclosing(input);
+        match result {
+            Ok((_, v)) => {
+                assert_eq!(v.attributes[0].identifier_token, "allow");
+                assert_eq!(v.attributes[0].arguments, vec!["unused_properties", "shadowing"]);
+            }
+            Err(_) => assert_eq!(1, 0),
+        }
+    }
+
+    #[test]
+    fn test_parse_unannotated_declaration() {
+        let input = "let letter = 5";
+        let input = LocatedSpan::new_extra(input, 0);
+        let result = parse_statement(input);
+        match result {
+            Ok((_, Statement::Expression(Expression::BinaryExpression(b)))) => {
+                match *b.lhs_expression {
+                    Expression::VariableDeclaration(v) => {
+                        assert_eq!(v.identifier.token, "letter");
+                        assert_eq!(v.variable_type, Type::Error);
+                    }
+                    _ => assert_eq!(1, 0),
+                }
+            }
+            _ => assert_eq!(1, 0),
+        }
+    }
+
+    #[test]
+    fn test_parse_attempt_expression() {
+        let input = "try! reset()";
+        let input = LocatedSpan::new_extra(input, 0);
+        let result = parse_statement(input);
+        match result {
+            Ok((_, Statement::Expression(Expression::AttemptExpression(a)))) => {
+                assert_eq!(a.kind, "!");
+                assert_eq!(a.function_call.identifier.token, "reset");
+            }
+            _ => assert_eq!(1, 0),
+        }
+    }
 }
@@ -0,0 +1,687 @@
+use super::context::*;
+use super::environment::*;
+use super::visitor::*;
+use super::AST::*;
+use std::collections::HashMap;
+
+// Checks that assets and currencies are used linearly: every owned value is
+// consumed exactly once, is never implicitly copied out of storage, and never
+// silently dropped.
+pub struct ResourceChecker {
+    diagnostics: Vec<String>,
+    // The parameter types of the function each call resolves to, by the
+    // position of the call
+    callees: HashMap<LineInfo, Vec<Type>>,
+}
+
+impl ResourceChecker {
+    pub fn new() -> ResourceChecker {
+        ResourceChecker {
+            diagnostics: vec![],
+            callees: HashMap::new(),
+        }
+    }
+}
+
+impl Visitor for ResourceChecker {
+    fn finish_module(&mut self, _t: &mut Module, _ctx: &mut Context) -> VResult {
+        if self.diagnostics.is_empty() {
+            return Ok(());
+        }
+
+        for diagnostic in &self.diagnostics {
+            println!("{}", diagnostic);
+        }
+        Err(Box::from(self.diagnostics.join("\n")))
+    }
+
+    // Functions are checked once the calls in their body are resolved
+    fn finish_function_declaration(
+        &mut self,
+        _t: &mut FunctionDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        let enclosing = _ctx.enclosing_type_identifier().unwrap_or_default().token;
+        if Environment::is_stdlib_type(&enclosing) {
+            return Ok(());
+        }
+
+        let mut flow = ResourceFlow::new(
+            &_ctx.environment,
+            &self.callees,
+            enclosing,
+            _t.head.identifier.token.clone(),
+            false,
+        );
+        flow.function(&_t.head.parameters, &_t.body);
+        self.diagnostics.append(&mut flow.diagnostics);
+        Ok(())
+    }
+
+    fn finish_special_declaration(
+        &mut self,
+        _t: &mut SpecialDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        let enclosing = _ctx.enclosing_type_identifier().unwrap_or_default().token;
+        if Environment::is_stdlib_type(&enclosing) {
+            return Ok(());
+        }
+
+        let mut flow = ResourceFlow::new(
+            &_ctx.environment,
+            &self.callees,
+            enclosing,
+            _t.head.special_token.clone(),
+            _t.is_init(),
+        );
+        flow.function(&_t.head.parameters, &_t.body);
+        self.diagnostics.append(&mut flow.diagnostics);
+        Ok(())
+    }
+
+    fn start_function_call(&mut self, _t: &mut FunctionCall, _ctx: &mut Context) -> VResult {
+        let enclosing = match &_t.identifier.enclosing_type {
+            Some(enclosing) => enclosing.clone(),
+            None => _ctx.enclosing_type_identifier().unwrap_or_default().token,
+        };
+        let caller_protections = match &_ctx.ContractBehaviourDeclarationContext {
+            Some(behaviour) => behaviour.caller_protections.clone(),
+            None => vec![],
+        };
+        let scope = _ctx.ScopeContext.clone().unwrap_or_default();
+
+        let parameters = match _ctx.environment.match_function_call(
+            _t.clone(),
+            &enclosing,
+            caller_protections,
+            scope,
+        ) {
+            FunctionCallMatchResult::MatchedFunction(f)
+            | FunctionCallMatchResult::MatchedGlobalFunction(f) => f.declaration.head.parameters,
+            FunctionCallMatchResult::MatchedFunctionWithoutCaller(c) => {
+                match c.candidates.first() {
+                    Some(CallableInformation::FunctionInformation(f)) => {
+                        f.declaration.head.parameters.clone()
+                    }
+                    _ => return Ok(()),
+                }
+            }
+            FunctionCallMatchResult::MatchedInitializer(s) => s.declaration.head.parameters,
+            _ => return Ok(()),
+        };
+        let types = parameters.into_iter().map(|p| p.type_assignment).collect();
+        self.callees.insert(_t.identifier.line_info.clone(), types);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Resource {
+    identifier: String,
+    line_info: LineInfo,
+    // The possible states of the value on the paths reaching this point
+    may_hold: bool,
+    may_be_moved: bool,
+}
+
+#[derive(Clone, Debug)]
+struct FlowState {
+    resources: Vec<Resource>,
+    reachable: bool,
+}
+
+impl FlowState {
+    fn join(self, other: FlowState) -> FlowState {
+        if !self.reachable {
+            return other;
+        }
+        if !other.reachable {
+            return self;
+        }
+
+        let resources = self
+            .resources
+            .into_iter()
+            .zip(other.resources)
+            .map(|(a, b)| Resource {
+                identifier: a.identifier,
+                line_info: a.line_info,
+                may_hold: a.may_hold || b.may_hold,
+                may_be_moved: a.may_be_moved || b.may_be_moved,
+            })
+            .collect();
+
+        FlowState {
+            resources,
+            reachable: true,
+        }
+    }
+}
+
+struct ResourceFlow<'a> {
+    environment: &'a Environment,
+    callees: &'a HashMap<LineInfo, Vec<Type>>,
+    enclosing: TypeIdentifier,
+    function_name: String,
+    in_initialiser: bool,
+    state: FlowState,
+    diagnostics: Vec<String>,
+}
+
+impl<'a> ResourceFlow<'a> {
+    fn new(
+        environment: &'a Environment,
+        callees: &'a HashMap<LineInfo, Vec<Type>>,
+        enclosing: TypeIdentifier,
+        function_name: String,
+        in_initialiser: bool,
+    ) -> ResourceFlow<'a> {
+        ResourceFlow {
+            environment,
+            callees,
+            enclosing,
+            function_name,
+            in_initialiser,
+            state: FlowState {
+                resources: vec![],
+                reachable: true,
+            },
+            diagnostics: vec![],
+        }
+    }
+
+    fn diagnose(&mut self, message: String, line_info: &LineInfo) {
+        let message = format!(
+            "Resource error on line {}: {} in {}.{}",
+            line_info.line, message, self.enclosing, self.function_name
+        );
+        if !self.diagnostics.contains(&message) {
+            self.diagnostics.push(message);
+        }
+    }
+
+    fn is_asset_type(&self, t: &Type) -> bool {
+        match t {
+            Type::UserDefinedType(i) => {
+                self.environment.is_asset_declared(&i.token) || t.is_currency_type()
+            }
+            _ => false,
+        }
+    }
+
+    fn is_asset_type_name(&self, name: &str) -> bool {
+        self.is_asset_type(&Type::UserDefinedType(Identifier {
+            token: name.to_string(),
+            enclosing_type: None,
+            line_info: Default::default(),
+        }))
+    }
+
+    fn find(&self, identifier: &str) -> Option<usize> {
+        self.state
+            .resources
+            .iter()
+            .rposition(|r| r.identifier == identifier)
+    }
+
+    fn property_type(&self, identifier: &Identifier) -> Option<Type> {
+        let enclosing = match &identifier.enclosing_type {
+            Some(t) => t.clone(),
+            None => self.enclosing.clone(),
+        };
+        self.environment
+            .property(identifier.token.clone(), &enclosing)
+            .map(|p| p.get_type().clone())
+    }
+
+    fn function(&mut self, parameters: &[Parameter], body: &[Statement]) {
+        for parameter in parameters {
+            if self.is_asset_type(&parameter.type_assignment) {
+                self.state.resources.push(Resource {
+                    identifier: parameter.identifier.token.clone(),
+                    line_info: parameter.identifier.line_info.clone(),
+                    may_hold: true,
+                    may_be_moved: false,
+                });
+            }
+        }
+
+        self.block(body);
+        if self.state.reachable {
+            self.exit(None);
+        }
+    }
+
+    // Reports the assets still held at a return statement, or at the end of the
+    // function where they are reported on the line they were declared
+    fn exit(&mut self, line_info: Option<&LineInfo>) {
+        let resources = self.state.resources.clone();
+        for resource in resources {
+            if resource.may_hold {
+                self.diagnose(
+                    format!("Asset {} is not consumed before returning", resource.identifier),
+                    line_info.unwrap_or(&resource.line_info),
+                );
+            }
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        let depth = self.state.resources.len();
+        for statement in statements {
+            if !self.state.reachable {
+                break;
+            }
+            self.statement(statement);
+        }
+
+        if self.state.reachable {
+            let out_of_scope: Vec<Resource> = self.state.resources[depth..].to_vec();
+            for resource in out_of_scope {
+                if resource.may_hold {
+                    self.diagnose(
+                        format!(
+                            "Asset {} is not consumed before it goes out of scope",
+                            resource.identifier
+                        ),
+                        &resource.line_info,
+                    );
+                }
+            }
+        }
+        self.state.resources.truncate(depth);
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::ReturnStatement(r) => {
+                if let Some(expression) = &r.expression {
+                    self.expression(expression, true);
+                }
+                self.exit(Some(&r.line_info));
+                self.state.reachable = false;
+            }
+            Statement::Expression(e) => {
+                if let Expression::FunctionCall(f) = e {
+                    if self.is_asset_type_name(&f.identifier.token) {
+                        self.diagnose(
+                            format!(
+                                "Asset created by {} initialiser is never stored",
+                                f.identifier.token
+                            ),
+                            &f.identifier.line_info,
+                        );
+                    }
+                }
+                self.expression(e, false);
+            }
+            Statement::BecomeStatement(b) => self.expression(&b.expression, false),
+            Statement::EmitStatement(e) => self.call(&e.function_call),
+            Statement::ForStatement(f) => {
+                self.expression(&f.iterable, false);
+
+                // A second pass over the body finds values moved on one iteration and
+                // used on the next
+                let entry = self.state.clone();
+                self.block(&f.body);
+                let once = self.state.clone();
+                self.state = entry.clone().join(once.clone());
+                self.block(&f.body);
+                let twice = self.state.clone();
+                self.state = entry.join(once).join(twice);
+            }
+            Statement::IfStatement(i) => {
+                self.expression(&i.condition, false);
+
+                let entry = self.state.clone();
+                self.block(&i.body);
+                let then_state = self.state.clone();
+                self.state = entry;
+                self.block(&i.else_body);
+                let else_state = self.state.clone();
+                self.state = then_state.join(else_state);
+            }
+            Statement::DoCatchStatement(d) => {
+                let entry = self.state.clone();
+                self.block(&d.do_body);
+                let do_state = self.state.clone();
+                self.state = entry.join(do_state.clone());
+                self.block(&d.catch_body);
+                let catch_state = self.state.clone();
+                self.state = do_state.join(catch_state);
+            }
+            Statement::TargetStatement(t) => self.statement(&t.statement),
+        }
+    }
+
+    // Walks an expression, `by_value` is set where the value of the expression is
+    // taken rather than only read
+    fn expression(&mut self, expression: &Expression, by_value: bool) {
+        match expression {
+            Expression::Identifier(i) => self.identifier(i, by_value),
+            Expression::BinaryExpression(b) => match b.op {
+                BinOp::Dot => {
+                    match &*b.lhs_expression {
+                        Expression::SelfExpression => {}
+                        lhs => self.expression(lhs, false),
+                    }
+
+                    match &*b.rhs_expression {
+                        Expression::FunctionCall(f) => self.call(f),
+                        Expression::Identifier(i) => {
+                            if by_value {
+                                if let Expression::SelfExpression = *b.lhs_expression {
+                                    self.property_copy(i);
+                                }
+                            }
+                        }
+                        rhs => self.expression(rhs, false),
+                    }
+                }
+                BinOp::Equal => self.assignment(&b.lhs_expression, &b.rhs_expression),
+                _ => {
+                    self.expression(&b.lhs_expression, false);
+                    self.expression(&b.rhs_expression, false);
+                }
+            },
+            Expression::InoutExpression(i) => self.expression(&i.expression, false),
+            Expression::ExternalCall(e) => {
+                for argument in &e.arguments {
+                    self.expression(&argument.expression, true);
+                }
+                self.expression(
+                    &Expression::BinaryExpression(e.function_call.clone()),
+                    false,
+                );
+            }
+            Expression::FunctionCall(f) => self.call(f),
+            Expression::VariableDeclaration(v) => self.declaration(v),
+            Expression::BracketedExpression(b) => self.expression(&b.expression, by_value),
+            Expression::AttemptExpression(a) => self.call(&a.function_call),
+            Expression::Literal(_) => {}
+            Expression::ArrayLiteral(a) => {
+                for element in &a.elements {
+                    self.expression(element, true);
+                }
+            }
+            Expression::DictionaryLiteral(d) => {
+                for (key, value) in &d.elements {
+                    self.expression(key, false);
+                    self.expression(value, true);
+                }
+            }
+            Expression::SelfExpression => {}
+            Expression::SubscriptExpression(s) => {
+                self.expression(&s.index_expression, false);
+                if by_value && self.find(&s.base_expression.token).is_none() {
+                    let element_type = match self.property_type(&s.base_expression) {
+                        Some(Type::DictionaryType(d)) => Some(*d.value_type),
+                        Some(Type::ArrayType(a)) => Some(*a.key_type),
+                        Some(Type::FixedSizedArrayType(a)) => Some(*a.key_type),
+                        _ => None,
+                    };
+                    if let Some(element_type) = element_type {
+                        if self.is_asset_type(&element_type) {
+                            self.diagnose(
+                                format!(
+                                    "Implicit copy of asset stored in {}",
+                                    s.base_expression.token
+                                ),
+                                &s.base_expression.line_info,
+                            );
+                        }
+                    }
+                } else {
+                    self.identifier(&s.base_expression, false);
+                }
+            }
+            Expression::RangeExpression(r) => {
+                self.expression(&r.start_expression, false);
+                self.expression(&r.end_expression, false);
+            }
+            Expression::RawAssembly(_, _) => {}
+            Expression::CastExpression(c) => self.expression(&c.expression, false),
+            Expression::Sequence(s) => {
+                for expression in s {
+                    self.expression(expression, false);
+                }
+            }
+        }
+    }
+
+    fn identifier(&mut self, identifier: &Identifier, by_value: bool) {
+        if identifier.enclosing_type.is_some() {
+            if by_value {
+                self.property_copy(identifier);
+            }
+            return;
+        }
+
+        match self.find(&identifier.token) {
+            Some(index) => {
+                let resource = self.state.resources[index].clone();
+                if resource.may_be_moved {
+                    self.diagnose(
+                        format!("Asset {} is used after being moved", resource.identifier),
+                        &identifier.line_info,
+                    );
+                }
+
+                if by_value {
+                    let resource = &mut self.state.resources[index];
+                    resource.may_hold = false;
+                    resource.may_be_moved = true;
+                }
+            }
+            None => {
+                if by_value {
+                    self.property_copy(identifier);
+                }
+            }
+        }
+    }
+
+    fn property_copy(&mut self, identifier: &Identifier) {
+        if let Some(property_type) = self.property_type(identifier) {
+            if self.is_asset_type(&property_type) {
+                self.diagnose(
+                    format!("Implicit copy of asset property {}", identifier.token),
+                    &identifier.line_info,
+                );
+            }
+        }
+    }
+
+    fn call(&mut self, call: &FunctionCall) {
+        // Passing an asset as an inout asset parameter empties it, unless the
+        // callee also takes an Int amount and so may move only part of it
+        let parameters = self.callees.get(&call.identifier.line_info);
+        let transfers_all = parameters.is_some_and(|p| !p.contains(&Type::Int));
+
+        for (position, argument) in call.arguments.iter().enumerate() {
+            match &argument.expression {
+                Expression::InoutExpression(i) => {
+                    self.expression(&i.expression, false);
+
+                    let is_emptied = transfers_all
+                        && match parameters.and_then(|p| p.get(position)) {
+                            Some(Type::InoutType(inout)) => self.is_asset_type(&inout.key_type),
+                            _ => false,
+                        };
+                    if let Expression::Identifier(identifier) = &*i.expression {
+                        if let Some(index) = self.find(&identifier.token) {
+                            if is_emptied {
+                                self.state.resources[index].may_hold = false;
+                            }
+                        }
+                    }
+                }
+                expression => self.expression(expression, true),
+            }
+        }
+    }
+
+    fn declaration(&mut self, declaration: &VariableDeclaration) {
+        let initialised = match &declaration.expression {
+            Some(expression) => {
+                self.expression(expression, true);
+                true
+            }
+            None => false,
+        };
+
+        if self.is_asset_type(&declaration.variable_type) {
+            self.state.resources.push(Resource {
+                identifier: declaration.identifier.token.clone(),
+                line_info: declaration.identifier.line_info.clone(),
+                may_hold: initialised,
+                may_be_moved: !initialised,
+            });
+        }
+    }
+
+    fn assignment(&mut self, lhs: &Expression, rhs: &Expression) {
+        self.expression(rhs, true);
+
+        match lhs {
+            Expression::VariableDeclaration(v) => {
+                self.declaration(v);
+                if let Some(index) = self.find(&v.identifier.token) {
+                    let resource = &mut self.state.resources[index];
+                    resource.may_hold = true;
+                    resource.may_be_moved = false;
+                }
+            }
+            Expression::Identifier(i)
+                if i.enclosing_type.is_none() && self.find(&i.token).is_some() =>
+            {
+                let index = self.find(&i.token).unwrap();
+                if self.state.resources[index].may_hold {
+                    self.diagnose(
+                        format!(
+                            "Asset {} is overwritten while it still holds a value",
+                            i.token
+                        ),
+                        &i.line_info,
+                    );
+                }
+                let resource = &mut self.state.resources[index];
+                resource.may_hold = true;
+                resource.may_be_moved = false;
+            }
+            Expression::Identifier(i) => self.property_assignment(i),
+            Expression::BinaryExpression(b) if b.op == BinOp::Dot => {
+                match (&*b.lhs_expression, &*b.rhs_expression) {
+                    (Expression::SelfExpression, Expression::Identifier(i)) => {
+                        self.property_assignment(i)
+                    }
+                    _ => self.expression(lhs, false),
+                }
+            }
+            _ => self.expression(lhs, false),
+        }
+    }
+
+    fn property_assignment(&mut self, identifier: &Identifier) {
+        if self.in_initialiser {
+            return;
+        }
+
+        if let Some(property_type) = self.property_type(identifier) {
+            if self.is_asset_type(&property_type) {
+                self.diagnose(
+                    format!(
+                        "Assigning to asset property {} discards its current value, transfer into it instead",
+                        identifier.token
+                    ),
+                    &identifier.line_info,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::AstProcessor::tests::checked;
+    use crate::AstProcessor::Target;
+
+    #[test]
+    fn test_resource_errors() {
+        let program = "
+            contract Bank {
+              var balance: Wei = Wei()
+            }
+            Bank :: caller <- (any) {
+              public init() {}
+
+              @payable
+              func drop(coin: Wei) {}
+
+              @payable
+              func keep(coin: Wei) mutates (balance) {
+                balance.transfer(source: &coin)
+              }
+
+              @payable
+              func twice(coin: Wei) mutates (balance) {
+                keep(coin: coin)
+                keep(coin: coin)
+              }
+
+              @payable
+              func leak(coin: Wei, flag: Bool) mutates (balance) {
+                if flag {
+                  balance.transfer(source: &coin)
+                }
+              }
+            }";
+        let error = checked(program, &Target::Ether).err().unwrap();
+        let errors: Vec<&str> = error.lines().collect();
+        assert_eq!(
+            errors,
+            vec![
+                "Resource error on line 9: Asset coin is not consumed before returning in Bank.drop",
+                "Resource error on line 19: Asset coin is used after being moved in Bank.twice",
+                "Resource error on line 23: Asset coin is not consumed before returning in Bank.leak",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_consumption_by_parameter_type() {
+        // Consumption follows the parameters of the callee, not the labels
+        let program = "
+            contract Bank {
+              var balance: Wei = Wei()
+            }
+            Bank :: caller <- (any) {
+              public init() {}
+
+              func stash(coin: inout Wei) mutates (balance) {
+                balance.transfer(source: &coin)
+              }
+
+              @payable
+              func deposit(payment: Wei) mutates (balance) {
+                stash(coin: &payment)
+              }
+
+              func isBelow(source: inout Wei, limit: Int) -> Bool {
+                return source.getRawValue() < limit
+              }
+
+              @payable
+              func check(payment: Wei) -> Bool {
+                return isBelow(source: &payment, limit: 10)
+              }
+            }";
+        let error = checked(program, &Target::Ether).err().unwrap();
+        assert_eq!(
+            error,
+            "Resource error on line 23: Asset payment is not consumed before returning in Bank.check"
+        );
+    }
+}
@@ -238,7 +238,11 @@ impl Visitor for SemanticAnalysis {
                 .parameters
                 .clone()
                 .into_iter()
-                .filter(|p| p.is_dynamic() && !p.is_payable())
+                .filter(|p| {
+                    p.is_dynamic()
+                        && !p.is_payable()
+                        && !_ctx.environment.is_enum_declared(&p.type_assignment.name())
+                })
                 .collect();
             if !parameters.is_empty() {
                 println!("Public Function has dynamic parameters");
@@ -257,14 +261,10 @@ impl Visitor for SemanticAnalysis {
             }
         }
 
-        let statements = _t.body.clone();
+        // Statements after a return or become are left to the unreachable_code lint
         let mut return_statements = Vec::new();
         let mut become_statements = Vec::new();
 
-        let remaining = statements
-            .into_iter()
-            .skip_while(|s| !isReturnOrBecomeStatement(s.clone()));
-
         for statement in _t.body.clone() {
             match statement {
                 Statement::ReturnStatement(ret) => return_statements.push(ret),
@@ -273,12 +273,6 @@ impl Visitor for SemanticAnalysis {
             }
         }
 
-        let remainingAfterEnd = remaining.filter(|s| !isReturnOrBecomeStatement(s.clone()));
-        if remainingAfterEnd.count() > 0 {
-            println!("Statements after Return");
-            return Err(Box::from("".to_owned()));
-        }
-
         if _t.head.result_type.is_some() {
             if return_statements.is_empty() {
                 let err = _t.head.identifier.token.clone();
@@ -343,9 +337,17 @@ impl Visitor for SemanticAnalysis {
             return Err(Box::from("".to_owned()));
         }
 
+        let is_enum_case = _t.enclosing_type.is_some()
+            && _ctx
+                .environment
+                .is_enum_declared(_t.enclosing_type.as_ref().unwrap());
+
         if _ctx.IsPropertyDefaultAssignment
             && !_ctx.environment.is_struct_declared(&_t.token)
             && !_ctx.environment.is_asset_declared(&_t.token)
+            && !_ctx.environment.is_enum_declared(&_t.token)
+            && !_ctx.environment.is_struct_trait_declared(&_t.token)
+            && !is_enum_case
         {
             if _ctx.enclosing_type_identifier().is_some() {
                 if _ctx.environment.is_property_defined(
@@ -488,11 +490,43 @@ impl Visitor for SemanticAnalysis {
         Ok(())
     }
 
+    fn start_type(&mut self, _t: &mut Type, _ctx: &mut Context) -> VResult {
+        if let Type::FixedPointType(f) = _t {
+            if f.decimals > FixedPointType::MAX_DECIMALS {
+                println!(
+                    "Fixed supports at most {} decimals",
+                    FixedPointType::MAX_DECIMALS
+                );
+                return Err(Box::from("".to_owned()));
+            }
+        }
+        Ok(())
+    }
+
     fn start_conformance(&mut self, _t: &mut Conformance, _ctx: &mut Context) -> VResult {
-        if !_ctx.environment.is_trait_declared(&_t.name()) {
+        let is_struct_trait = _ctx.environment.is_struct_trait_declared(&_t.name());
+        if !_ctx.environment.is_trait_declared(&_t.name()) && !is_struct_trait {
             println!("Undeclared Trait Used");
             return Err(Box::from("".to_owned()));
         }
+
+        if is_struct_trait {
+            let enclosing = _ctx.enclosing_type_identifier().unwrap_or_default().token;
+            let missing = _ctx
+                .environment
+                .missing_trait_functions(&enclosing, &_t.name());
+            if !missing.is_empty() {
+                for function in missing {
+                    println!(
+                        "{} does not implement {} required by {}",
+                        enclosing,
+                        function,
+                        _t.name()
+                    );
+                }
+                return Err(Box::from("".to_owned()));
+            }
+        }
         Ok(())
     }
 
@@ -501,9 +535,12 @@ impl Visitor for SemanticAnalysis {
         _t: &mut AttemptExpression,
         _ctx: &mut Context,
     ) -> VResult {
-        if _t.is_soft() {}
-
-        Ok(())
+        // Neither target generates the runtime caller check yet
+        println!(
+            "Attempt expression try{} on line {} is not supported",
+            _t.kind, _t.function_call.identifier.line_info.line
+        );
+        Err(Box::from("".to_owned()))
     }
 
     fn start_binary_expression(
@@ -526,6 +563,31 @@ impl Visitor for SemanticAnalysis {
     }
 
     fn start_function_call(&mut self, _t: &mut FunctionCall, _ctx: &mut Context) -> VResult {
+        if !_ctx.in_function_or_special()
+            || _ctx.IsExternalFunctionCall
+            || Environment::is_runtime_function_call(_t)
+        {
+            return Ok(());
+        }
+
+        let enclosing = match &_t.identifier.enclosing_type {
+            Some(enclosing) => enclosing.clone(),
+            None => _ctx.enclosing_type_identifier().unwrap_or_default().token,
+        };
+        let caller_protections = match &_ctx.ContractBehaviourDeclarationContext {
+            Some(behaviour) => behaviour.caller_protections.clone(),
+            None => vec![],
+        };
+        let scope = _ctx.ScopeContext.clone().unwrap_or_default();
+
+        if let Some(diagnostic) =
+            _ctx.environment
+                .function_call_diagnostic(_t, &enclosing, caller_protections, scope)
+        {
+            let message = diagnostic.to_string();
+            println!("{}", message);
+            return Err(Box::from(message));
+        }
         Ok(())
     }
 
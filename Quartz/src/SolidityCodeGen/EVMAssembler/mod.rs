@@ -0,0 +1,1113 @@
+use super::*;
+use std::collections::HashMap;
+
+// Assembles the creation and runtime code of a Yul object
+pub fn assemble(object: &YulObject) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let runtime = object.objects.iter().find(|o| o.name == "runtime");
+    let runtime = match runtime {
+        Some(r) => r,
+        None => return Err(format!("Yul object {} has no runtime object", object.name)),
+    };
+
+    Ok((assemble_object(object)?, assemble_object(runtime)?))
+}
+
+fn assemble_object(object: &YulObject) -> Result<Vec<u8>, String> {
+    let mut data: Vec<(String, Vec<u8>)> = vec![];
+    for o in &object.objects {
+        data.push((o.name.clone(), assemble_object(o)?));
+    }
+
+    let mut code_gen = EVMCodeGen {
+        items: vec![],
+        labels: 0,
+        functions: HashMap::new(),
+        stack: vec![],
+        loops: vec![],
+        function_exit: None,
+    };
+    let items = code_gen.generate(&object.code)?;
+
+    let mut code = EVMCodeGen::link(items, &data)?;
+    for (_, d) in data {
+        code.extend(d);
+    }
+    Ok(code)
+}
+
+// Parses the Yul source of runtime functions and inline fragments
+pub struct YulParser {
+    pub tokens: Vec<String>,
+    pub position: usize,
+}
+
+impl YulParser {
+    pub fn tokenise(code: &str) -> Result<Vec<String>, String> {
+        let chars: Vec<char> = code.chars().collect();
+        let mut tokens = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if c.is_whitespace() {
+                i += 1;
+            } else if c == '/' && i + 1 < chars.len() && chars[i + 1] == '/' {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            } else if c == '/' && i + 1 < chars.len() && chars[i + 1] == '*' {
+                i += 2;
+                while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                    i += 1;
+                }
+                i += 2;
+            } else if c == '"' {
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += 1;
+                }
+                i += 1;
+                tokens.push(chars[start..i].iter().collect());
+            } else if c == ':' && i + 1 < chars.len() && chars[i + 1] == '=' {
+                tokens.push(":=".to_string());
+                i += 2;
+            } else if c == '-' && i + 1 < chars.len() && chars[i + 1] == '>' {
+                tokens.push("->".to_string());
+                i += 2;
+            } else if "{}(),:".contains(c) {
+                tokens.push(c.to_string());
+                i += 1;
+            } else if c.is_ascii_alphanumeric() || c == '_' || c == '$' || c == '.' {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric()
+                        || chars[i] == '_'
+                        || chars[i] == '$'
+                        || chars[i] == '.')
+                {
+                    i += 1;
+                }
+                tokens.push(chars[start..i].iter().collect());
+            } else {
+                return Err(format!("Unexpected character {} in Yul code", c));
+            }
+        }
+        Ok(tokens)
+    }
+
+    fn peek(&self) -> Option<String> {
+        self.tokens.get(self.position).cloned()
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        let token = self.peek();
+        self.position += 1;
+        match token {
+            Some(t) => Ok(t),
+            None => Err("Unexpected end of Yul code".to_string()),
+        }
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(format!(
+                "Expected {} in Yul code but found {}",
+                expected, token
+            ));
+        }
+        Ok(())
+    }
+
+    // Generated objects are assembled from the Yul AST, so only tests read them back
+    #[cfg(test)]
+    pub fn parse_object(&mut self) -> Result<YulObject, String> {
+        self.expect("object")?;
+        let name = self.next()?;
+        let name = name.trim_matches('"').to_string();
+        self.expect("{")?;
+        self.expect("code")?;
+        let code = self.parse_block()?;
+
+        let mut objects = vec![];
+        while self.peek() == Some("object".to_string()) {
+            objects.push(self.parse_object()?);
+        }
+        self.expect("}")?;
+
+        Ok(YulObject {
+            name,
+            code,
+            objects,
+        })
+    }
+
+    pub fn parse_block(&mut self) -> Result<YulBlock, String> {
+        self.expect("{")?;
+        let mut statements = vec![];
+        while self.peek() != Some("}".to_string()) {
+            statements.push(self.parse_statement()?);
+        }
+        self.expect("}")?;
+        Ok(YulBlock { statements })
+    }
+
+    pub fn parse_statements(&mut self) -> Result<Vec<YulStatement>, String> {
+        let mut statements = vec![];
+        while self.peek().is_some() {
+            statements.push(self.parse_statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn parse_typed_identifiers(&mut self, terminator: &str) -> Result<Vec<String>, String> {
+        let mut identifiers = vec![];
+        while self.peek().is_some() && self.peek() != Some(terminator.to_string()) {
+            identifiers.push(self.next()?);
+            if self.peek() == Some(":".to_string()) {
+                self.next()?;
+                self.next()?;
+            }
+            if self.peek() == Some(",".to_string()) {
+                self.next()?;
+            } else {
+                break;
+            }
+        }
+        Ok(identifiers)
+    }
+
+    fn parse_statement(&mut self) -> Result<YulStatement, String> {
+        let token = match self.peek() {
+            Some(token) => token,
+            None => return Err("Unexpected end of Yul code".to_string()),
+        };
+        let statement = match token.as_str() {
+            "{" => YulStatement::Block(self.parse_block()?),
+            "function" => {
+                self.next()?;
+                let identifier = self.next()?;
+                self.expect("(")?;
+                let arguments = self.parse_typed_identifiers(")")?;
+                self.expect(")")?;
+                let returns = if self.peek() == Some("->".to_string()) {
+                    self.next()?;
+                    self.parse_typed_identifiers("{")?
+                } else {
+                    vec![]
+                };
+                let body = self.parse_block()?;
+                YulStatement::FunctionDefinition(YulFunctionDefinition {
+                    identifier,
+                    arguments: arguments.into_iter().map(|a| (a, YulType::Any)).collect(),
+                    returns: returns.into_iter().map(|r| (r, YulType::Any)).collect(),
+                    body,
+                })
+            }
+            "let" => {
+                self.next()?;
+                let identifiers = self.parse_typed_identifiers(":=")?;
+                if identifiers.len() != 1 {
+                    return Err(
+                        "Multiple variable declarations are not supported by the assembler"
+                            .to_string(),
+                    );
+                }
+                let expression = if self.peek() == Some(":=".to_string()) {
+                    self.next()?;
+                    Some(Box::from(self.parse_expression()?))
+                } else {
+                    None
+                };
+                YulStatement::Expression(YulExpression::VariableDeclaration(
+                    YulVariableDeclaration {
+                        declaration: identifiers[0].clone(),
+                        declaration_type: YulType::Any,
+                        expression,
+                    },
+                ))
+            }
+            "if" => {
+                self.next()?;
+                let expression = self.parse_expression()?;
+                let block = self.parse_block()?;
+                YulStatement::If(YulIf { expression, block })
+            }
+            "switch" => {
+                self.next()?;
+                let expression = self.parse_expression()?;
+                let mut cases = vec![];
+                while self.peek() == Some("case".to_string()) {
+                    self.next()?;
+                    let literal = match self.parse_expression()? {
+                        YulExpression::Literal(l) => l,
+                        e => {
+                            return Err(format!(
+                                "Expected a literal in switch case but found {}",
+                                e
+                            ))
+                        }
+                    };
+                    cases.push((literal, self.parse_block()?));
+                }
+                let default = if self.peek() == Some("default".to_string()) {
+                    self.next()?;
+                    Some(self.parse_block()?)
+                } else {
+                    None
+                };
+                YulStatement::Switch(YulSwitch {
+                    expression,
+                    cases,
+                    default,
+                })
+            }
+            "for" => {
+                self.next()?;
+                let initialise = self.parse_block()?;
+                let condition = self.parse_expression()?;
+                let step = self.parse_block()?;
+                let body = self.parse_block()?;
+                YulStatement::For(YulForLoop {
+                    initialise,
+                    condition,
+                    step,
+                    body,
+                })
+            }
+            "break" => {
+                self.next()?;
+                YulStatement::Break
+            }
+            "continue" => {
+                self.next()?;
+                YulStatement::Continue
+            }
+            "leave" => {
+                self.next()?;
+                YulStatement::Leave
+            }
+            _ => {
+                let expression = self.parse_expression()?;
+                let next = self.peek();
+                if next == Some(",".to_string()) || next == Some(":=".to_string()) {
+                    let mut identifiers = vec![format!("{}", expression)];
+                    while self.peek() == Some(",".to_string()) {
+                        self.next()?;
+                        identifiers.push(self.next()?);
+                    }
+                    self.expect(":=")?;
+                    let expression = self.parse_expression()?;
+                    return Ok(YulStatement::Expression(YulExpression::Assignment(
+                        YulAssignment {
+                            identifiers,
+                            expression: Box::from(expression),
+                        },
+                    )));
+                }
+                YulStatement::Expression(expression)
+            }
+        };
+        Ok(statement)
+    }
+
+    pub fn parse_expression(&mut self) -> Result<YulExpression, String> {
+        let token = self.next()?;
+        let first = token.chars().next().unwrap_or_default();
+
+        let expression = if token.starts_with('"') {
+            YulExpression::Literal(YulLiteral::String(token.trim_matches('"').to_string()))
+        } else if token == "true" || token == "false" {
+            YulExpression::Literal(YulLiteral::Bool(token == "true"))
+        } else if token.starts_with("0x") {
+            YulExpression::Literal(YulLiteral::Hex(token))
+        } else if first.is_ascii_digit() {
+            match token.parse::<u64>() {
+                Ok(n) => YulExpression::Literal(YulLiteral::Num(n)),
+                Err(_) => YulExpression::Literal(YulLiteral::Hex(format!(
+                    "0x{}",
+                    encode(EVMCodeGen::decimal_value(&token)?)
+                ))),
+            }
+        } else if self.peek() == Some("(".to_string()) {
+            self.next()?;
+            let mut arguments = vec![];
+            while self.peek() != Some(")".to_string()) {
+                arguments.push(self.parse_expression()?);
+                if self.peek() == Some(",".to_string()) {
+                    self.next()?;
+                }
+            }
+            self.expect(")")?;
+            return Ok(YulExpression::FunctionCall(YulFunctionCall {
+                name: token,
+                arguments,
+            }));
+        } else {
+            return Ok(YulExpression::Identifier(token));
+        };
+
+        if self.peek() == Some(":".to_string()) {
+            self.next()?;
+            self.next()?;
+        }
+        Ok(expression)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum AsmItem {
+    Op(u8),
+    Push(Vec<u8>),
+    PushLabel(usize),
+    Label(usize),
+    PushDataOffset(String),
+    PushDataSize(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct EVMFunction {
+    pub label: usize,
+    pub arguments: usize,
+    pub returns: usize,
+}
+
+pub struct EVMCodeGen {
+    pub items: Vec<AsmItem>,
+    pub labels: usize,
+    pub functions: HashMap<String, EVMFunction>,
+    pub stack: Vec<Option<String>>,
+    pub loops: Vec<(usize, usize, usize)>,
+    pub function_exit: Option<(usize, usize)>,
+}
+
+impl EVMCodeGen {
+    pub fn generate(&mut self, code: &YulBlock) -> Result<Vec<AsmItem>, String> {
+        let mut definitions = vec![];
+        self.collect_functions(&code.statements, &mut definitions);
+
+        self.generate_block(code)?;
+        self.items.push(AsmItem::Op(0x00));
+
+        for definition in definitions {
+            self.generate_function(&definition)?;
+        }
+        Ok(self.items.clone())
+    }
+
+    fn collect_functions(
+        &mut self,
+        statements: &Vec<YulStatement>,
+        definitions: &mut Vec<YulFunctionDefinition>,
+    ) {
+        for statement in statements {
+            match statement {
+                YulStatement::FunctionDefinition(f) => {
+                    if !self.functions.contains_key(&f.identifier) {
+                        let label = self.new_label();
+                        self.functions.insert(
+                            f.identifier.clone(),
+                            EVMFunction {
+                                label,
+                                arguments: f.arguments.len(),
+                                returns: f.returns.len(),
+                            },
+                        );
+                        definitions.push(f.clone());
+                    }
+                    self.collect_functions(&f.body.statements, definitions);
+                }
+                YulStatement::Block(b) => self.collect_functions(&b.statements, definitions),
+                YulStatement::If(i) => self.collect_functions(&i.block.statements, definitions),
+                YulStatement::Switch(s) => {
+                    for (_, b) in &s.cases {
+                        self.collect_functions(&b.statements, definitions);
+                    }
+                    if let Some(d) = &s.default {
+                        self.collect_functions(&d.statements, definitions);
+                    }
+                }
+                YulStatement::For(f) => {
+                    self.collect_functions(&f.initialise.statements, definitions);
+                    self.collect_functions(&f.step.statements, definitions);
+                    self.collect_functions(&f.body.statements, definitions);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn new_label(&mut self) -> usize {
+        self.labels += 1;
+        self.labels
+    }
+
+    fn pop_to(&mut self, height: usize) {
+        while self.stack.len() > height {
+            self.items.push(AsmItem::Op(0x50));
+            self.stack.pop();
+        }
+    }
+
+    fn jump_out(&mut self, label: usize, height: usize) {
+        for _ in height..self.stack.len() {
+            self.items.push(AsmItem::Op(0x50));
+        }
+        self.items.push(AsmItem::PushLabel(label));
+        self.items.push(AsmItem::Op(0x56));
+    }
+
+    fn swap(&mut self, depth: usize) -> Result<(), String> {
+        if depth > 16 {
+            return Err("Stack too deep when assembling Yul code".to_string());
+        }
+        self.items.push(AsmItem::Op(0x8f + depth as u8));
+        let top = self.stack.len() - 1;
+        self.stack.swap(top, top - depth);
+        Ok(())
+    }
+
+    fn generate_function(&mut self, definition: &YulFunctionDefinition) -> Result<(), String> {
+        let function = self.functions[&definition.identifier].clone();
+
+        self.stack = vec![Some("@return".to_string())];
+        for (argument, _) in definition.arguments.iter().rev() {
+            self.stack.push(Some(argument.clone()));
+        }
+
+        self.items.push(AsmItem::Label(function.label));
+        for (r, _) in &definition.returns {
+            self.items.push(AsmItem::Push(vec![0]));
+            self.stack.push(Some(r.clone()));
+        }
+
+        let exit = self.new_label();
+        self.function_exit = Some((exit, self.stack.len()));
+        self.generate_block(&definition.body)?;
+        self.function_exit = None;
+
+        self.items.push(AsmItem::Label(exit));
+        let mut target: Vec<Option<String>> = definition
+            .returns
+            .clone()
+            .into_iter()
+            .map(|(r, _)| Some(r))
+            .collect();
+        target.push(Some("@return".to_string()));
+        self.shuffle(target)?;
+        self.items.push(AsmItem::Op(0x56));
+        Ok(())
+    }
+
+    fn shuffle(&mut self, target: Vec<Option<String>>) -> Result<(), String> {
+        loop {
+            let junk = self.stack.iter().rposition(|s| !target.contains(s));
+            let junk = match junk {
+                Some(j) => j,
+                None => break,
+            };
+            let depth = self.stack.len() - 1 - junk;
+            if depth > 0 {
+                self.swap(depth)?;
+            }
+            self.items.push(AsmItem::Op(0x50));
+            self.stack.pop();
+        }
+
+        for (position, wanted) in target.iter().enumerate() {
+            if &self.stack[position] == wanted {
+                continue;
+            }
+            let top = self.stack.len() - 1;
+            if &self.stack[top] != wanted {
+                let source = self.stack.iter().position(|s| s == wanted);
+                let source = match source {
+                    Some(s) => s,
+                    None => return Err("Return variable missing from the stack".to_string()),
+                };
+                self.swap(top - source)?;
+            }
+            if position != top {
+                self.swap(top - position)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_block(&mut self, block: &YulBlock) -> Result<(), String> {
+        let height = self.stack.len();
+        for statement in &block.statements {
+            self.generate_statement(statement)?;
+        }
+        self.pop_to(height);
+        Ok(())
+    }
+
+    fn generate_statement(&mut self, statement: &YulStatement) -> Result<(), String> {
+        match statement {
+            YulStatement::Block(b) => self.generate_block(b)?,
+            YulStatement::FunctionDefinition(_) => {}
+            YulStatement::If(i) => {
+                let end = self.new_label();
+                self.generate_value(&i.expression)?;
+                self.items.push(AsmItem::Op(0x15));
+                self.items.push(AsmItem::PushLabel(end));
+                self.items.push(AsmItem::Op(0x57));
+                self.stack.pop();
+                self.generate_block(&i.block)?;
+                self.items.push(AsmItem::Label(end));
+            }
+            YulStatement::Switch(s) => {
+                let end = self.new_label();
+                self.generate_value(&s.expression)?;
+
+                let mut cases = vec![];
+                for (literal, block) in &s.cases {
+                    let label = self.new_label();
+                    self.items.push(AsmItem::Op(0x80));
+                    self.items
+                        .push(AsmItem::Push(EVMCodeGen::literal_bytes(literal)?));
+                    self.items.push(AsmItem::Op(0x14));
+                    self.items.push(AsmItem::PushLabel(label));
+                    self.items.push(AsmItem::Op(0x57));
+                    cases.push((label, block));
+                }
+
+                let default = self.new_label();
+                self.items.push(AsmItem::PushLabel(default));
+                self.items.push(AsmItem::Op(0x56));
+
+                for (label, block) in cases {
+                    self.items.push(AsmItem::Label(label));
+                    self.generate_block(block)?;
+                    self.items.push(AsmItem::PushLabel(end));
+                    self.items.push(AsmItem::Op(0x56));
+                }
+
+                self.items.push(AsmItem::Label(default));
+                if let Some(d) = &s.default {
+                    self.generate_block(d)?;
+                }
+                self.items.push(AsmItem::Label(end));
+                self.pop_to(self.stack.len() - 1);
+            }
+            YulStatement::For(f) => {
+                let height = self.stack.len();
+                for statement in &f.initialise.statements {
+                    self.generate_statement(statement)?;
+                }
+
+                let start = self.new_label();
+                let step = self.new_label();
+                let end = self.new_label();
+
+                self.items.push(AsmItem::Label(start));
+                self.generate_value(&f.condition)?;
+                self.items.push(AsmItem::Op(0x15));
+                self.items.push(AsmItem::PushLabel(end));
+                self.items.push(AsmItem::Op(0x57));
+                self.stack.pop();
+
+                self.loops.push((step, end, self.stack.len()));
+                self.generate_block(&f.body)?;
+                self.loops.pop();
+
+                self.items.push(AsmItem::Label(step));
+                self.generate_block(&f.step)?;
+                self.items.push(AsmItem::PushLabel(start));
+                self.items.push(AsmItem::Op(0x56));
+
+                self.items.push(AsmItem::Label(end));
+                self.pop_to(height);
+            }
+            YulStatement::Break | YulStatement::Continue => {
+                let (step, end, height) = match self.loops.last() {
+                    Some(l) => *l,
+                    None => return Err(format!("{} outside of a for loop", statement)),
+                };
+                let target = if let YulStatement::Break = statement {
+                    end
+                } else {
+                    step
+                };
+                self.jump_out(target, height);
+            }
+            YulStatement::Leave => {
+                let (exit, height) = match self.function_exit {
+                    Some(e) => e,
+                    None => return Err("leave outside of a function".to_string()),
+                };
+                self.jump_out(exit, height);
+            }
+            YulStatement::Expression(e) => self.generate_expression_statement(e)?,
+            YulStatement::Noop => {}
+            YulStatement::Inline(i) => self.generate_inline(i)?,
+        }
+        Ok(())
+    }
+
+    // Inline code is emitted by the code generator as Yul source
+    fn generate_inline(&mut self, code: &str) -> Result<(), String> {
+        let mut parser = YulParser {
+            tokens: YulParser::tokenise(code)?,
+            position: 0,
+        };
+        for statement in parser.parse_statements()? {
+            self.generate_statement(&statement)?;
+        }
+        Ok(())
+    }
+
+    fn generate_expression_statement(&mut self, expression: &YulExpression) -> Result<(), String> {
+        match expression {
+            YulExpression::VariableDeclaration(v) => {
+                if let Some(e) = &v.expression {
+                    self.generate_value(e)?;
+                } else {
+                    self.items.push(AsmItem::Push(vec![0]));
+                    self.stack.push(None);
+                }
+                let top = self.stack.len() - 1;
+                self.stack[top] = Some(v.declaration.clone());
+            }
+            YulExpression::Assignment(a) => {
+                let values = self.generate_expression(&a.expression)?;
+                if values != a.identifiers.len() {
+                    return Err(format!(
+                        "Assignment of {} values to {}",
+                        values,
+                        a.identifiers.join(", ")
+                    ));
+                }
+                for identifier in a.identifiers.iter().rev() {
+                    let depth = self.depth_of(identifier)? - 1;
+                    if depth > 16 {
+                        return Err(format!("Stack too deep when assigning to {}", identifier));
+                    }
+                    self.items.push(AsmItem::Op(0x8f + depth as u8));
+                    self.items.push(AsmItem::Op(0x50));
+                    self.stack.pop();
+                }
+            }
+            YulExpression::Inline(i) => self.generate_inline(i)?,
+            _ => {
+                let values = self.generate_expression(expression)?;
+                let height = self.stack.len() - values;
+                self.pop_to(height);
+            }
+        }
+        Ok(())
+    }
+
+    fn depth_of(&self, identifier: &str) -> Result<usize, String> {
+        let position = self
+            .stack
+            .iter()
+            .rposition(|s| *s == Some(identifier.to_string()));
+        match position {
+            Some(p) => Ok(self.stack.len() - p),
+            None => Err(format!("Undeclared Yul variable {}", identifier)),
+        }
+    }
+
+    fn generate_value(&mut self, expression: &YulExpression) -> Result<(), String> {
+        let values = self.generate_expression(expression)?;
+        if values != 1 {
+            return Err(format!(
+                "Expected a single value from Yul expression {}",
+                expression
+            ));
+        }
+        Ok(())
+    }
+
+    fn generate_expression(&mut self, expression: &YulExpression) -> Result<usize, String> {
+        match expression {
+            YulExpression::Literal(l) => {
+                self.items
+                    .push(AsmItem::Push(EVMCodeGen::literal_bytes(l)?));
+                self.stack.push(None);
+                Ok(1)
+            }
+            // The code generator also writes literals and expressions as identifiers
+            YulExpression::Identifier(i) if !EVMCodeGen::is_identifier(i) => {
+                self.generate_expression(&YulExpression::Inline(i.clone()))
+            }
+            YulExpression::Identifier(i) => {
+                let depth = self.depth_of(i)?;
+                if depth > 16 {
+                    return Err(format!("Stack too deep when accessing {}", i));
+                }
+                self.items.push(AsmItem::Op(0x7f + depth as u8));
+                self.stack.push(None);
+                Ok(1)
+            }
+            YulExpression::FunctionCall(f) => self.generate_function_call(f),
+            YulExpression::Catchable(v, _) => self.generate_expression(v),
+            YulExpression::Inline(i) => {
+                let mut parser = YulParser {
+                    tokens: YulParser::tokenise(i)?,
+                    position: 0,
+                };
+                let expression = parser.parse_expression()?;
+                self.generate_expression(&expression)
+            }
+            YulExpression::Noop => Ok(0),
+            e => Err(format!("Cannot assemble {} as a Yul expression", e)),
+        }
+    }
+
+    fn generate_function_call(&mut self, call: &YulFunctionCall) -> Result<usize, String> {
+        let name = call.name.as_str();
+        if name == "datasize" || name == "dataoffset" {
+            let object = match call.arguments.first() {
+                Some(YulExpression::Literal(YulLiteral::String(s))) => s.clone(),
+                _ => return Err(format!("Expected an object name in {}", name)),
+            };
+            if name == "datasize" {
+                self.items.push(AsmItem::PushDataSize(object));
+            } else {
+                self.items.push(AsmItem::PushDataOffset(object));
+            }
+            self.stack.push(None);
+            return Ok(1);
+        }
+
+        let name = if name == "datacopy" { "codecopy" } else { name };
+
+        if let Some((opcode, arguments, returns)) = EVMCodeGen::builtin(name) {
+            if arguments != call.arguments.len() {
+                return Err(EVMCodeGen::arity_error(name, arguments, call));
+            }
+            for argument in call.arguments.iter().rev() {
+                self.generate_value(argument)?;
+            }
+            self.items.push(AsmItem::Op(opcode));
+            let height = self.stack.len() - arguments;
+            self.stack.truncate(height);
+            for _ in 0..returns {
+                self.stack.push(None);
+            }
+            return Ok(returns);
+        }
+
+        let function = match self.functions.get(name) {
+            Some(f) => f.clone(),
+            None => return Err(format!("Call to undefined Yul function {}", name)),
+        };
+        if function.arguments != call.arguments.len() {
+            return Err(EVMCodeGen::arity_error(name, function.arguments, call));
+        }
+
+        let return_label = self.new_label();
+        self.items.push(AsmItem::PushLabel(return_label));
+        self.stack.push(None);
+        for argument in call.arguments.iter().rev() {
+            self.generate_value(argument)?;
+        }
+        self.items.push(AsmItem::PushLabel(function.label));
+        self.items.push(AsmItem::Op(0x56));
+        self.items.push(AsmItem::Label(return_label));
+
+        let height = self.stack.len() - function.arguments - 1;
+        self.stack.truncate(height);
+        for _ in 0..function.returns {
+            self.stack.push(None);
+        }
+        Ok(function.returns)
+    }
+
+    pub fn is_identifier(token: &str) -> bool {
+        let mut chars = token.chars();
+        let first = match chars.next() {
+            Some(c) => c,
+            None => return false,
+        };
+        (first.is_ascii_alphabetic() || first == '_' || first == '$')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$' || c == '.')
+            && token != "true"
+            && token != "false"
+    }
+
+    fn arity_error(name: &str, expected: usize, call: &YulFunctionCall) -> String {
+        format!(
+            "{} takes {} arguments but is called with {} in {}",
+            name,
+            expected,
+            call.arguments.len(),
+            call
+        )
+    }
+
+    pub fn link(items: Vec<AsmItem>, data: &Vec<(String, Vec<u8>)>) -> Result<Vec<u8>, String> {
+        let mut labels: HashMap<usize, usize> = HashMap::new();
+        let mut size = 0;
+        for item in &items {
+            match item {
+                AsmItem::Op(_) => size += 1,
+                AsmItem::Push(b) => size += 1 + b.len(),
+                AsmItem::Label(l) => {
+                    labels.insert(*l, size);
+                    size += 1
+                }
+                _ => size += 3,
+            }
+        }
+
+        let mut data_offsets: HashMap<String, (usize, usize)> = HashMap::new();
+        let mut offset = size;
+        for (name, d) in data {
+            data_offsets.insert(name.clone(), (offset, d.len()));
+            offset += d.len();
+        }
+
+        let push_word = |code: &mut Vec<u8>, value: usize| {
+            if value > 0xffff {
+                return Err("Assembled code is too large".to_string());
+            }
+            code.push(0x61);
+            code.push((value >> 8) as u8);
+            code.push(value as u8);
+            Ok(())
+        };
+
+        let mut code = vec![];
+        for item in items {
+            match item {
+                AsmItem::Op(o) => code.push(o),
+                AsmItem::Push(b) => {
+                    code.push(0x5f + b.len() as u8);
+                    code.extend(b);
+                }
+                AsmItem::Label(_) => code.push(0x5b),
+                AsmItem::PushLabel(l) => push_word(&mut code, labels[&l])?,
+                AsmItem::PushDataOffset(n) => match data_offsets.get(&n) {
+                    Some((o, _)) => push_word(&mut code, *o)?,
+                    None => return Err(format!("Unknown Yul object {}", n)),
+                },
+                AsmItem::PushDataSize(n) => match data_offsets.get(&n) {
+                    Some((_, s)) => push_word(&mut code, *s)?,
+                    None => return Err(format!("Unknown Yul object {}", n)),
+                },
+            }
+        }
+        Ok(code)
+    }
+
+    pub fn literal_bytes(literal: &YulLiteral) -> Result<Vec<u8>, String> {
+        let value: Vec<u8> = match literal {
+            YulLiteral::Num(n) => n.to_be_bytes().to_vec(),
+            YulLiteral::Bool(b) => vec![*b as u8],
+            YulLiteral::Hex(h) => {
+                let digits = h.trim_start_matches("0x");
+                if digits.len() > 64 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!("Literal {} is not a word", h));
+                }
+                let digits = format!(
+                    "{:0>width$}",
+                    digits,
+                    width = digits.len() + digits.len() % 2
+                );
+                (0..digits.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+                    .collect()
+            }
+            YulLiteral::String(s) => {
+                if s.len() > 32 {
+                    return Err(format!("String literal {} does not fit in a word", s));
+                }
+                let mut bytes = s.as_bytes().to_vec();
+                bytes.resize(32, 0);
+                bytes
+            }
+            YulLiteral::Decimal(_, _) => return Err("Float currently not supported".to_string()),
+        };
+
+        let value: Vec<u8> = value.into_iter().skip_while(|b| *b == 0).collect();
+        if value.is_empty() {
+            Ok(vec![0])
+        } else {
+            Ok(value)
+        }
+    }
+
+    pub fn decimal_value(digits: &str) -> Result<Vec<u8>, String> {
+        let mut value = vec![0u8; 32];
+        for digit in digits.chars() {
+            let mut carry = match digit.to_digit(10) {
+                Some(d) => d,
+                None => return Err(format!("Literal {} is not a number", digits)),
+            };
+            for byte in value.iter_mut().rev() {
+                let product = (*byte as u32) * 10 + carry;
+                *byte = product as u8;
+                carry = product >> 8;
+            }
+            if carry != 0 {
+                return Err(format!("Literal {} does not fit in a word", digits));
+            }
+        }
+        Ok(value)
+    }
+
+    fn builtin(name: &str) -> Option<(u8, usize, usize)> {
+        let builtin = match name {
+            "stop" => (0x00, 0, 0),
+            "add" => (0x01, 2, 1),
+            "mul" => (0x02, 2, 1),
+            "sub" => (0x03, 2, 1),
+            "div" => (0x04, 2, 1),
+            "sdiv" => (0x05, 2, 1),
+            "mod" => (0x06, 2, 1),
+            "smod" => (0x07, 2, 1),
+            "addmod" => (0x08, 3, 1),
+            "mulmod" => (0x09, 3, 1),
+            "exp" => (0x0a, 2, 1),
+            "signextend" => (0x0b, 2, 1),
+            "lt" => (0x10, 2, 1),
+            "gt" => (0x11, 2, 1),
+            "slt" => (0x12, 2, 1),
+            "sgt" => (0x13, 2, 1),
+            "eq" => (0x14, 2, 1),
+            "iszero" => (0x15, 1, 1),
+            "and" => (0x16, 2, 1),
+            "or" => (0x17, 2, 1),
+            "xor" => (0x18, 2, 1),
+            "not" => (0x19, 1, 1),
+            "byte" => (0x1a, 2, 1),
+            "shl" => (0x1b, 2, 1),
+            "shr" => (0x1c, 2, 1),
+            "sar" => (0x1d, 2, 1),
+            "keccak256" => (0x20, 2, 1),
+            "address" => (0x30, 0, 1),
+            "balance" => (0x31, 1, 1),
+            "origin" => (0x32, 0, 1),
+            "caller" => (0x33, 0, 1),
+            "callvalue" => (0x34, 0, 1),
+            "calldataload" => (0x35, 1, 1),
+            "calldatasize" => (0x36, 0, 1),
+            "calldatacopy" => (0x37, 3, 0),
+            "codesize" => (0x38, 0, 1),
+            "codecopy" => (0x39, 3, 0),
+            "gasprice" => (0x3a, 0, 1),
+            "extcodesize" => (0x3b, 1, 1),
+            "extcodecopy" => (0x3c, 4, 0),
+            "returndatasize" => (0x3d, 0, 1),
+            "returndatacopy" => (0x3e, 3, 0),
+            "extcodehash" => (0x3f, 1, 1),
+            "blockhash" => (0x40, 1, 1),
+            "coinbase" => (0x41, 0, 1),
+            "timestamp" => (0x42, 0, 1),
+            "number" => (0x43, 0, 1),
+            "difficulty" => (0x44, 0, 1),
+            "gaslimit" => (0x45, 0, 1),
+            "chainid" => (0x46, 0, 1),
+            "selfbalance" => (0x47, 0, 1),
+            "pop" => (0x50, 1, 0),
+            "mload" => (0x51, 1, 1),
+            "mstore" => (0x52, 2, 0),
+            "mstore8" => (0x53, 2, 0),
+            "sload" => (0x54, 1, 1),
+            "sstore" => (0x55, 2, 0),
+            "msize" => (0x59, 0, 1),
+            "gas" => (0x5a, 0, 1),
+            "log0" => (0xa0, 2, 0),
+            "log1" => (0xa1, 3, 0),
+            "log2" => (0xa2, 4, 0),
+            "log3" => (0xa3, 5, 0),
+            "log4" => (0xa4, 6, 0),
+            "create" => (0xf0, 3, 1),
+            "call" => (0xf1, 7, 1),
+            "callcode" => (0xf2, 7, 1),
+            "return" => (0xf3, 2, 0),
+            "delegatecall" => (0xf4, 6, 1),
+            "create2" => (0xf5, 4, 1),
+            "staticcall" => (0xfa, 6, 1),
+            "revert" => (0xfd, 2, 0),
+            "invalid" => (0xfe, 0, 0),
+            "selfdestruct" => (0xff, 1, 0),
+            _ => return None,
+        };
+        Some(builtin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::SolidityCodeGen::EVMAssembler::{assemble, YulParser};
+    use hex::encode;
+
+    fn assemble_source(source: &str) -> (Vec<u8>, Vec<u8>) {
+        let mut parser = YulParser {
+            tokens: YulParser::tokenise(source).unwrap(),
+            position: 0,
+        };
+        let object = parser.parse_object().unwrap();
+        assemble(&object).unwrap()
+    }
+
+    #[test]
+    fn test_assemble_object() {
+        let source = "object \"Double\" {
+            code {
+                datacopy(0, dataoffset(\"runtime\"), datasize(\"runtime\"))
+                return(0, datasize(\"runtime\"))
+            }
+            object \"runtime\" {
+                code {
+                    function double(x) -> r { r := add(x, x) }
+                    mstore(0, double(21))
+                    return(0, 32)
+                }
+            }
+        }";
+        let (creation, runtime) = assemble_source(source);
+
+        assert_eq!(
+            encode(&runtime),
+            "610009601561001356\
+             5b60005260206000f300\
+             5b600081820190505b90509056"
+        );
+        assert_eq!(
+            encode(&creation),
+            format!("6100206100106000396100206000f300{}", encode(&runtime))
+        );
+
+        // Every pushed label is the offset of a jumpdest
+        let mut offset = 0;
+        while offset < runtime.len() {
+            let opcode = runtime[offset];
+            if opcode == 0x61 {
+                let target = (runtime[offset + 1] as usize) << 8 | runtime[offset + 2] as usize;
+                assert_eq!(runtime[target], 0x5b);
+            }
+            offset += if (0x60..=0x7f).contains(&opcode) {
+                opcode as usize - 0x5e
+            } else {
+                1
+            };
+        }
+    }
+
+    #[test]
+    fn test_assemble_arity_mismatch() {
+        let source = "object \"Bad\" {
+            code { return(0, datasize(\"runtime\")) }
+            object \"runtime\" {
+                code {
+                    function f(a, b) -> r { r := add(a, b) }
+                    pop(f(1))
+                }
+            }
+        }";
+        let mut parser = YulParser {
+            tokens: YulParser::tokenise(source).unwrap(),
+            position: 0,
+        };
+        let object = parser.parse_object().unwrap();
+        assert!(assemble(&object).is_err());
+    }
+}
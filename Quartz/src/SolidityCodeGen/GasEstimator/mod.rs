@@ -0,0 +1,679 @@
+use super::EVMAssembler::{EVMCodeGen, YulParser};
+use super::*;
+use std::collections::HashMap;
+
+// Worst case gas of the public functions of a contract, estimated from its Yul
+// object. Opcodes are priced as of the Berlin fork at their most expensive: every
+// slot and account is cold and every store sets a zero slot. Memory expansion
+// is counted for the runtime allocator, and loops are only bounded when they
+// count up to a literal.
+
+const TRANSACTION: u64 = 21000;
+const CALLDATA_BYTE: u64 = 16;
+const PUSH: u64 = 3;
+const DUP: u64 = 3;
+const SWAP: u64 = 3;
+const POP: u64 = 2;
+const JUMP: u64 = 8;
+const JUMPI: u64 = 10;
+const JUMPDEST: u64 = 1;
+const COLD_ACCOUNT: u64 = 2600;
+const COLD_SLOAD: u64 = 2100;
+// A store to a cold slot that held zero, the most a store can cost
+const SSTORE: u64 = COLD_SLOAD + 20000;
+// The free memory pointer starts after the scratch space and the zero slot
+const FREE_MEMORY_START: u64 = 0x60;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GasCost {
+    Bounded(u64),
+    // Why no bound is known
+    Unbounded(String),
+}
+
+impl GasCost {
+    fn add(self, other: GasCost) -> GasCost {
+        match (self, other) {
+            (GasCost::Bounded(a), GasCost::Bounded(b)) => GasCost::Bounded(a.saturating_add(b)),
+            (GasCost::Unbounded(r), _) | (_, GasCost::Unbounded(r)) => GasCost::Unbounded(r),
+        }
+    }
+
+    fn add_gas(self, gas: u64) -> GasCost {
+        self.add(GasCost::Bounded(gas))
+    }
+
+    fn max(self, other: GasCost) -> GasCost {
+        match (self, other) {
+            (GasCost::Bounded(a), GasCost::Bounded(b)) => GasCost::Bounded(a.max(b)),
+            (GasCost::Unbounded(r), _) | (_, GasCost::Unbounded(r)) => GasCost::Unbounded(r),
+        }
+    }
+
+    fn times(self, count: u64) -> GasCost {
+        match self {
+            GasCost::Bounded(a) => GasCost::Bounded(a.saturating_mul(count)),
+            unbounded => unbounded,
+        }
+    }
+
+    fn json(&self) -> String {
+        match self {
+            GasCost::Bounded(gas) => format!("{}", gas),
+            GasCost::Unbounded(_) => "null".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for GasCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GasCost::Bounded(gas) => write!(f, "{}", gas),
+            GasCost::Unbounded(_) => write!(f, "unbounded"),
+        }
+    }
+}
+
+pub struct GasEstimate {
+    pub name: String,
+    pub signature: String,
+    pub selector: String,
+    pub execution: GasCost,
+    // The execution with the base cost of a transaction and of its calldata
+    pub total: GasCost,
+}
+
+impl GasEstimate {
+    fn unbounded(&self) -> Option<&String> {
+        match &self.execution {
+            GasCost::Unbounded(reason) => Some(reason),
+            GasCost::Bounded(_) => None,
+        }
+    }
+}
+
+pub fn estimate(contract: &SolidityContract) -> Vec<GasEstimate> {
+    let object = contract.yul_object();
+    let runtime = object.objects.iter().find(|o| o.name == "runtime");
+    let runtime: &YulObject = match runtime {
+        Some(r) => r,
+        None => panic!("Yul object {} has no runtime object", object.name),
+    };
+
+    let mut estimator = GasEstimator::new(&runtime.code);
+
+    let functions: Vec<SolidityFunction> = contract
+        .functions()
+        .into_iter()
+        .filter(|f| f.declaration.is_public())
+        .collect();
+
+    functions
+        .into_iter()
+        .map(|f| {
+            let selector = f.selector_hash();
+            let execution = estimator.execution(&runtime.code, &selector);
+
+            // Calldata holds the selector and a word for each argument
+            let calldata = 4 + 32 * f.declaration.head.parameters.len() as u64;
+            let total = execution
+                .clone()
+                .add_gas(TRANSACTION + CALLDATA_BYTE * calldata);
+
+            GasEstimate {
+                name: f.declaration.head.identifier.token.clone(),
+                signature: f.mangled_signature(),
+                selector,
+                execution,
+                total,
+            }
+        })
+        .collect()
+}
+
+pub fn table(contract: &str, estimates: &[GasEstimate]) -> String {
+    let mut rows = vec![
+        format!("Gas estimates for {}", contract),
+        format!(
+            "{:<40} {:<10} {:>10} {:>10}",
+            "Function", "Selector", "Execution", "Total"
+        ),
+    ];
+    for estimate in estimates {
+        let mut row = format!(
+            "{:<40} {:<10} {:>10} {:>10}",
+            estimate.signature,
+            estimate.selector,
+            format!("{}", estimate.execution),
+            format!("{}", estimate.total)
+        );
+        if let Some(reason) = estimate.unbounded() {
+            row = format!("{}  ({})", row, reason);
+        }
+        rows.push(row);
+    }
+    rows.join("\n") + "\n"
+}
+
+pub fn json(contract: &str, estimates: &[GasEstimate]) -> String {
+    let functions: Vec<String> = estimates
+        .iter()
+        .map(|e| {
+            let unbounded = match e.unbounded() {
+                Some(reason) => format!("\"{}\"", reason),
+                None => "null".to_string(),
+            };
+            format!(
+                "    {{ \"name\": \"{name}\", \"signature\": \"{signature}\", \"selector\": \"{selector}\", \"execution\": {execution}, \"total\": {total}, \"unbounded\": {unbounded} }}",
+                name = e.name,
+                signature = e.signature,
+                selector = e.selector,
+                execution = e.execution.json(),
+                total = e.total.json(),
+                unbounded = unbounded
+            )
+        })
+        .collect();
+    let functions = functions.join(",\n");
+
+    format!(
+        "{{\n  \"contract\": \"{contract}\",\n  \"functions\": [\n{functions}\n  ]\n}}\n",
+        contract = contract,
+        functions = functions
+    )
+}
+
+struct GasEstimator {
+    definitions: HashMap<String, YulFunctionDefinition>,
+    // The gas of each function and the memory it allocates
+    costs: HashMap<String, (GasCost, GasCost)>,
+    // Functions being estimated, to find recursion
+    stack: Vec<String>,
+    // The dispatcher case of the function being estimated
+    selector: String,
+    // Bytes allocated so far, at most
+    memory: GasCost,
+}
+
+impl GasEstimator {
+    fn new(code: &YulBlock) -> GasEstimator {
+        let mut estimator = GasEstimator {
+            definitions: HashMap::new(),
+            costs: HashMap::new(),
+            stack: vec![],
+            selector: String::new(),
+            memory: GasCost::Bounded(0),
+        };
+        estimator.collect_functions(&code.statements);
+        estimator
+    }
+
+    // The gas of the dispatcher case with the given selector, with the
+    // expansion of memory to everything it allocates
+    fn execution(&mut self, code: &YulBlock, selector: &str) -> GasCost {
+        self.selector = selector.to_string();
+        self.memory = GasCost::Bounded(0);
+        let cost = self.block(code);
+        let expansion = match self.memory.clone().add_gas(FREE_MEMORY_START) {
+            GasCost::Bounded(bytes) => {
+                let words = bytes.div_ceil(32);
+                GasCost::Bounded(3 * words + words * words / 512)
+            }
+            unbounded => unbounded,
+        };
+        cost.add(expansion)
+    }
+
+    // Runs an estimate on its own, returning its gas and the memory it allocates
+    fn measure<F>(&mut self, estimate: F) -> (GasCost, GasCost)
+    where
+        F: FnOnce(&mut GasEstimator) -> GasCost,
+    {
+        let outer = std::mem::replace(&mut self.memory, GasCost::Bounded(0));
+        let cost = estimate(self);
+        let allocated = std::mem::replace(&mut self.memory, outer);
+        (cost, allocated)
+    }
+
+    fn allocate(&mut self, memory: GasCost) {
+        self.memory = self.memory.clone().add(memory);
+    }
+
+    fn collect_functions(&mut self, statements: &[YulStatement]) {
+        for statement in statements {
+            match statement {
+                YulStatement::FunctionDefinition(f) => {
+                    self.definitions
+                        .entry(f.identifier.clone())
+                        .or_insert_with(|| f.clone());
+                    self.collect_functions(&f.body.statements);
+                }
+                YulStatement::Block(b) => self.collect_functions(&b.statements),
+                YulStatement::If(i) => self.collect_functions(&i.block.statements),
+                YulStatement::Switch(s) => {
+                    for (_, b) in &s.cases {
+                        self.collect_functions(&b.statements);
+                    }
+                    if let Some(d) = &s.default {
+                        self.collect_functions(&d.statements);
+                    }
+                }
+                YulStatement::For(f) => {
+                    self.collect_functions(&f.initialise.statements);
+                    self.collect_functions(&f.step.statements);
+                    self.collect_functions(&f.body.statements);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Where the code being estimated is, for the reasons of unbounded costs
+    fn location(&self) -> String {
+        match self.stack.last() {
+            Some(function) => function.clone(),
+            None => "the dispatcher".to_string(),
+        }
+    }
+
+    fn block(&mut self, block: &YulBlock) -> GasCost {
+        let mut cost = self.statements(&block.statements);
+
+        // Variables declared in the block are popped at its end
+        for statement in &block.statements {
+            if let YulStatement::Expression(YulExpression::VariableDeclaration(_)) = statement {
+                cost = cost.add_gas(POP);
+            }
+        }
+        cost
+    }
+
+    fn statements(&mut self, statements: &[YulStatement]) -> GasCost {
+        let mut cost = GasCost::Bounded(0);
+        for statement in statements {
+            cost = cost.add(self.statement(statement));
+        }
+        cost
+    }
+
+    fn statement(&mut self, statement: &YulStatement) -> GasCost {
+        match statement {
+            YulStatement::Block(b) => self.block(b),
+            YulStatement::FunctionDefinition(_) => GasCost::Bounded(0),
+            YulStatement::If(i) => self
+                .expression(&i.expression)
+                .add_gas(3 + PUSH + JUMPI)
+                .add(self.block(&i.block))
+                .add_gas(JUMPDEST),
+            YulStatement::Switch(s) => self.switch(s),
+            YulStatement::For(f) => self.for_loop(f),
+            YulStatement::Break | YulStatement::Continue | YulStatement::Leave => {
+                GasCost::Bounded(PUSH + JUMP)
+            }
+            YulStatement::Expression(e) => self.expression_statement(e),
+            YulStatement::Noop => GasCost::Bounded(0),
+            YulStatement::Inline(i) => self.inline(i),
+        }
+    }
+
+    fn inline(&mut self, code: &str) -> GasCost {
+        let statements = YulParser::tokenise(code).and_then(|tokens| {
+            YulParser {
+                tokens,
+                position: 0,
+            }
+            .parse_statements()
+        });
+        match statements {
+            Ok(statements) => self.statements(&statements),
+            Err(e) => GasCost::Unbounded(e),
+        }
+    }
+
+    // The dispatcher only runs the case of the function being estimated, other
+    // switches may run any of their cases
+    fn switch(&mut self, switch: &YulSwitch) -> GasCost {
+        let mut cost = self.expression(&switch.expression);
+        let check = DUP + PUSH + 3 + PUSH + JUMPI;
+        let exit = PUSH + JUMP + JUMPDEST + POP;
+
+        let mut worst = GasCost::Bounded(0);
+        let mut worst_memory = GasCost::Bounded(0);
+        for (literal, block) in &switch.cases {
+            cost = cost.add_gas(check);
+            let (body, memory) = self.measure(|e| e.block(block));
+            let body = body.add_gas(JUMPDEST);
+            if let YulLiteral::Hex(h) = literal {
+                if *h == self.selector {
+                    self.allocate(memory);
+                    return cost.add(body).add_gas(exit);
+                }
+            }
+            worst = worst.max(body);
+            worst_memory = worst_memory.max(memory);
+        }
+
+        let (default, memory) = match &switch.default {
+            Some(d) => self.measure(|e| e.block(d)),
+            None => (GasCost::Bounded(0), GasCost::Bounded(0)),
+        };
+        self.allocate(worst_memory.max(memory));
+        cost.add_gas(PUSH + JUMP + JUMPDEST)
+            .add(worst.max(default))
+            .add_gas(exit)
+    }
+
+    fn for_loop(&mut self, for_loop: &YulForLoop) -> GasCost {
+        let iterations = match GasEstimator::loop_bound(for_loop) {
+            Some(iterations) => iterations,
+            None => {
+                return GasCost::Unbounded(format!(
+                    "loop with an unknown bound in {}",
+                    self.location()
+                ))
+            }
+        };
+
+        let initialise = self.statements(&for_loop.initialise.statements);
+        let (condition, condition_memory) = self.measure(|e| {
+            e.expression(&for_loop.condition)
+                .add_gas(3 + PUSH + JUMPI + JUMPDEST)
+        });
+        let (iteration, iteration_memory) = self.measure(|e| {
+            e.block(&for_loop.body)
+                .add(e.block(&for_loop.step))
+                .add_gas(JUMPDEST + PUSH + JUMP)
+        });
+        self.allocate(condition_memory.times(iterations + 1));
+        self.allocate(iteration_memory.times(iterations));
+
+        initialise
+            .add(condition.times(iterations + 1))
+            .add(iteration.times(iterations))
+            .add_gas(JUMPDEST)
+    }
+
+    // The number of iterations of for { let i := a } lt(i, b) { i := add(i, 1) }
+    // where a and b are literals
+    fn loop_bound(for_loop: &YulForLoop) -> Option<u64> {
+        let (variable, start) = match for_loop.initialise.statements.as_slice() {
+            [YulStatement::Expression(YulExpression::VariableDeclaration(v))] => {
+                let start = v.expression.as_ref()?;
+                (v.declaration.clone(), GasEstimator::literal_value(start)?)
+            }
+            _ => return None,
+        };
+
+        let end = match &for_loop.condition {
+            YulExpression::FunctionCall(c) if c.name == "lt" && c.arguments.len() == 2 => {
+                match &c.arguments[0] {
+                    YulExpression::Identifier(i) if *i == variable => {}
+                    _ => return None,
+                }
+                GasEstimator::literal_value(&c.arguments[1])?
+            }
+            _ => return None,
+        };
+
+        let is_increment = match for_loop.step.statements.as_slice() {
+            [YulStatement::Expression(YulExpression::Assignment(a))] => {
+                a.identifiers == vec![variable.clone()]
+                    && match &*a.expression {
+                        YulExpression::FunctionCall(c) if c.name == "add" => {
+                            let arguments: Vec<String> =
+                                c.arguments.iter().map(|a| format!("{}", a)).collect();
+                            arguments == vec![variable.clone(), "1".to_string()]
+                                || arguments == vec!["1".to_string(), variable.clone()]
+                        }
+                        _ => false,
+                    }
+            }
+            _ => false,
+        };
+        if !is_increment {
+            return None;
+        }
+        Some(end.saturating_sub(start))
+    }
+
+    fn literal_value(expression: &YulExpression) -> Option<u64> {
+        match expression {
+            YulExpression::Literal(YulLiteral::Num(n)) => Some(*n),
+            YulExpression::Literal(YulLiteral::Bool(b)) => Some(*b as u64),
+            YulExpression::Literal(YulLiteral::Hex(h)) => {
+                u64::from_str_radix(h.trim_start_matches("0x"), 16).ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn expression_statement(&mut self, expression: &YulExpression) -> GasCost {
+        match expression {
+            YulExpression::VariableDeclaration(v) => match &v.expression {
+                Some(e) => self.expression(e),
+                None => GasCost::Bounded(PUSH),
+            },
+            YulExpression::Assignment(a) => self
+                .expression(&a.expression)
+                .add_gas((SWAP + POP) * a.identifiers.len() as u64),
+            YulExpression::Inline(i) => self.inline(i),
+            e => self.expression(e),
+        }
+    }
+
+    fn expression(&mut self, expression: &YulExpression) -> GasCost {
+        match expression {
+            YulExpression::Literal(_) => GasCost::Bounded(PUSH),
+            YulExpression::Identifier(i) if !EVMCodeGen::is_identifier(i) => self.inline(i),
+            YulExpression::Identifier(_) => GasCost::Bounded(DUP),
+            YulExpression::FunctionCall(f) => self.call(f),
+            YulExpression::Catchable(v, _) => self.expression(v),
+            YulExpression::VariableDeclaration(_) | YulExpression::Assignment(_) => {
+                self.expression_statement(expression)
+            }
+            YulExpression::Inline(i) => self.inline(i),
+            YulExpression::Noop => GasCost::Bounded(0),
+        }
+    }
+
+    fn call(&mut self, call: &YulFunctionCall) -> GasCost {
+        let mut cost = GasCost::Bounded(0);
+        for argument in &call.arguments {
+            cost = cost.add(self.expression(argument));
+        }
+
+        if let Some(opcode) = self.opcode(&call.name, &call.arguments) {
+            return cost.add(opcode);
+        }
+
+        if call.name == SolidityRuntimeFunction::AllocateMemory.mangle_runtime() {
+            let size = call.arguments.get(0).and_then(GasEstimator::literal_value);
+            let memory = match size {
+                Some(size) => GasCost::Bounded(size),
+                None => GasCost::Unbounded(format!(
+                    "memory allocation of an unknown size in {}",
+                    self.location()
+                )),
+            };
+            self.allocate(memory);
+        }
+
+        // The return label and the function are pushed, and the function jumps
+        // back once it is done
+        let function = self.function(&call.name);
+        cost.add_gas(PUSH + PUSH + JUMP + JUMPDEST)
+            .add(function)
+            .add_gas(JUMP + JUMPDEST)
+    }
+
+    fn function(&mut self, name: &str) -> GasCost {
+        if let Some((cost, memory)) = self.costs.get(name).cloned() {
+            self.allocate(memory);
+            return cost;
+        }
+        if self.stack.iter().any(|f| f == name) {
+            return GasCost::Unbounded(format!("{} is recursive", name));
+        }
+        let definition = match self.definitions.get(name) {
+            Some(definition) => definition.clone(),
+            None => return GasCost::Unbounded(format!("{} is not defined", name)),
+        };
+
+        self.stack.push(name.to_string());
+        let (cost, memory) = self.measure(|e| {
+            GasCost::Bounded(PUSH * definition.returns.len() as u64).add(e.block(&definition.body))
+        });
+        self.stack.pop();
+
+        self.allocate(memory.clone());
+        self.costs.insert(name.to_string(), (cost.clone(), memory));
+        cost
+    }
+
+    // The cost of a builtin, apart from its arguments
+    fn opcode(&self, name: &str, arguments: &[YulExpression]) -> Option<GasCost> {
+        let literal = |index: usize| arguments.get(index).and_then(GasEstimator::literal_value);
+        let unknown =
+            |what: &str| GasCost::Unbounded(format!("{} {} in {}", name, what, self.location()));
+        // Copies and hashes cost per word of their size, logs per byte
+        let words = |index: usize, gas: u64| match literal(index) {
+            Some(size) => GasCost::Bounded(size.div_ceil(32) * gas),
+            None => unknown("of an unknown size"),
+        };
+        let bytes = |index: usize, gas: u64| match literal(index) {
+            Some(size) => GasCost::Bounded(size * gas),
+            None => unknown("of an unknown size"),
+        };
+        // Calls cost the gas they forward
+        let forwarded = |index: usize| match literal(index) {
+            Some(gas) => GasCost::Bounded(gas),
+            None => unknown("forwards all gas"),
+        };
+
+        let cost = match name {
+            "stop" | "return" | "revert" | "invalid" => GasCost::Bounded(0),
+            "address" | "origin" | "caller" | "callvalue" | "calldatasize" | "codesize"
+            | "gasprice" | "returndatasize" | "coinbase" | "timestamp" | "number"
+            | "difficulty" | "gaslimit" | "chainid" | "pop" | "msize" | "gas" | "datasize"
+            | "dataoffset" => GasCost::Bounded(2),
+            "add" | "sub" | "lt" | "gt" | "slt" | "sgt" | "eq" | "iszero" | "and" | "or"
+            | "xor" | "not" | "byte" | "shl" | "shr" | "sar" | "calldataload" | "mload"
+            | "mstore" | "mstore8" => GasCost::Bounded(3),
+            "mul" | "div" | "sdiv" | "mod" | "smod" | "signextend" | "selfbalance" => {
+                GasCost::Bounded(5)
+            }
+            "addmod" | "mulmod" => GasCost::Bounded(8),
+            "blockhash" => GasCost::Bounded(20),
+            // A full word exponent
+            "exp" => GasCost::Bounded(10 + 50 * 32),
+            "keccak256" => words(1, 6).add_gas(30),
+            "calldatacopy" | "codecopy" | "datacopy" | "returndatacopy" => words(2, 3).add_gas(3),
+            "extcodecopy" => words(3, 3).add_gas(COLD_ACCOUNT),
+            "balance" | "extcodesize" | "extcodehash" => GasCost::Bounded(COLD_ACCOUNT),
+            "sload" => GasCost::Bounded(COLD_SLOAD),
+            "sstore" => GasCost::Bounded(SSTORE),
+            "log0" | "log1" | "log2" | "log3" | "log4" => {
+                let topics = name[3..].parse::<u64>().unwrap();
+                bytes(1, 8).add_gas(375 + 375 * topics)
+            }
+            "call" | "callcode" => {
+                let value = match literal(2) {
+                    Some(0) => 0,
+                    // Sending value, possibly to a new account
+                    _ => 9000 + 25000,
+                };
+                forwarded(0).add_gas(COLD_ACCOUNT + value)
+            }
+            "delegatecall" | "staticcall" => forwarded(0).add_gas(COLD_ACCOUNT),
+            "create" | "create2" => unknown("runs unknown code"),
+            "selfdestruct" => GasCost::Bounded(5000 + COLD_ACCOUNT + 25000),
+            _ => return None,
+        };
+        Some(cost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{json, table, GasCost, GasEstimate, GasEstimator};
+    use crate::SolidityCodeGen::EVMAssembler::YulParser;
+    use crate::SolidityCodeGen::YulObject;
+
+    fn runtime(code: &str) -> YulObject {
+        let source = format!(
+            "object \"C\" {{ code {{ }} object \"runtime\" {{ code {{ {} }} }} }}",
+            code
+        );
+        let mut parser = YulParser {
+            tokens: YulParser::tokenise(&source).unwrap(),
+            position: 0,
+        };
+        let object = parser.parse_object().unwrap();
+        object.objects[0].clone()
+    }
+
+    #[test]
+    fn test_unbounded_loop() {
+        let runtime = runtime(
+            "function f(n) {
+                for { let i := 0 } lt(i, n) { i := add(i, 1) } { sstore(i, 1) }
+            }
+            function g() {
+                for { let i := 0 } lt(i, 4) { i := add(i, 1) } { sstore(i, 1) }
+            }
+            switch shr(224, calldataload(0))
+            case 0x11111111 { f(calldataload(4)) }
+            case 0x22222222 { g() }
+            default { revert(0, 0) }",
+        );
+        let mut estimator = GasEstimator::new(&runtime.code);
+
+        let execution = estimator.execution(&runtime.code, "0x11111111");
+        assert_eq!(
+            execution,
+            GasCost::Unbounded("loop with an unknown bound in f".to_string())
+        );
+        match estimator.execution(&runtime.code, "0x22222222") {
+            GasCost::Bounded(_) => {}
+            unbounded => panic!("{:?}", unbounded),
+        }
+
+        let estimates = vec![GasEstimate {
+            name: "f".to_string(),
+            signature: "f(uint256)".to_string(),
+            selector: "0x11111111".to_string(),
+            execution: execution.clone(),
+            total: execution.add_gas(21000),
+        }];
+        assert!(table("C", &estimates).contains("unbounded  (loop with an unknown bound in f)"));
+        assert!(json("C", &estimates).contains(
+            "\"execution\": null, \"total\": null, \"unbounded\": \"loop with an unknown bound in f\""
+        ));
+    }
+
+    #[test]
+    fn test_memory_expansion() {
+        let runtime = runtime(
+            "function Quartz$AllocateMemory(size) -> ret {
+                ret := mload(0x40)
+                mstore(0x40, add(ret, size))
+            }
+            switch shr(224, calldataload(0))
+            case 0x11111111 { pop(Quartz$AllocateMemory(32)) }
+            case 0x22222222 { pop(Quartz$AllocateMemory(1024)) }
+            default { revert(0, 0) }",
+        );
+        let mut estimator = GasEstimator::new(&runtime.code);
+
+        let small = estimator.execution(&runtime.code, "0x11111111");
+        let large = estimator.execution(&runtime.code, "0x22222222");
+        // 4 words of memory against 35, and one more case of the dispatcher
+        match (small, large) {
+            (GasCost::Bounded(small), GasCost::Bounded(large)) => {
+                assert_eq!(large - small, (105 + 35 * 35 / 512) - 12 + 22)
+            }
+            costs => panic!("{:?}", costs),
+        }
+    }
+}
@@ -7,6 +7,11 @@ use crate::AST::*;
 pub(crate) struct SolidityPreProcessor {}
 
 impl Visitor for SolidityPreProcessor {
+    fn start_module(&mut self, _t: &mut Module, _ctx: &mut Context) -> VResult {
+        _ctx.environment.resolve_currency(&"Wei".to_string());
+        Ok(())
+    }
+
     fn start_function_declaration(
         &mut self,
         _t: &mut FunctionDeclaration,
@@ -53,7 +58,9 @@ impl Visitor for SolidityPreProcessor {
         let dynamic_params = _t.head.parameters.clone();
         let dynamic_params: Vec<Parameter> = dynamic_params
             .into_iter()
-            .filter(|p| p.is_dynamic())
+            .filter(|p| {
+                p.is_dynamic() && !_ctx.environment.is_enum_declared(&p.type_assignment.name())
+            })
             .collect();
 
         let mut offset = 0;
@@ -69,6 +76,17 @@ impl Visitor for SolidityPreProcessor {
         Ok(())
     }
 
+    fn finish_function_declaration(
+        &mut self,
+        _t: &mut FunctionDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        if _ctx.ScopeContext.is_some() {
+            _t.ScopeContext = _ctx.ScopeContext.clone();
+        }
+        Ok(())
+    }
+
     fn start_expression(&mut self, _t: &mut Expression, _ctx: &mut Context) -> VResult {
         let expression = _t.clone();
         if let Expression::BinaryExpression(b) = expression {
@@ -76,14 +94,19 @@ impl Visitor for SolidityPreProcessor {
                 if let Expression::Identifier(lhs) = *b.lhs_expression.clone() {
                     if let Expression::Identifier(rhs) = *b.rhs_expression.clone() {
                         if _ctx.environment.is_enum_declared(&lhs.token) {
-                            unimplemented!()
+                            let property = _ctx.environment.property(rhs.token, &lhs.token);
+                            if property.is_some() {
+                                *_t = property.unwrap().property.get_value().unwrap();
+                                return Ok(());
+                            }
                         }
                     }
                 }
             } else if let BinOp::Equal = b.op {
                 if let Expression::FunctionCall(f) = *b.rhs_expression.clone() {
                     let mut function_call = f.clone();
-                    if _ctx.environment.is_initiliase_call(f) {
+                    function_call.resolve_currency(&"Wei".to_string());
+                    if _ctx.environment.is_initiliase_call(function_call.clone()) {
                         let inout = Expression::InoutExpression(InoutExpression {
                             ampersand_token: "&".to_string(),
                             expression: b.lhs_expression.clone(),
@@ -153,6 +176,27 @@ impl Visitor for SolidityPreProcessor {
             _ctx.FunctionCallReceiverTrail = trail;
         }
 
+        if let BinOp::Equal = _t.op {
+            if _ctx.is_struct_declaration_context() && is_filled_collection_literal(&_t.rhs_expression) {
+                // Struct values may live in memory, where only locals hold collections.
+                // Filled dictionary literals are only supported as contract property
+                // defaults on ethereum
+                let is_local = match *_t.lhs_expression {
+                    Expression::VariableDeclaration(_) => true,
+                    Expression::Identifier(ref i) => i.enclosing_type.is_none(),
+                    _ => false,
+                };
+                if !is_local {
+                    let message = format!(
+                        "Type error on line {}: non-empty array and dictionary literals can not be assigned to struct properties on the ethereum target",
+                        _t.line_info.line
+                    );
+                    println!("{}", message);
+                    return Err(Box::from(message));
+                }
+            }
+        }
+
         let op = _t.op.clone();
 
         if let BinOp::LessThanOrEqual = op {
@@ -199,7 +243,19 @@ impl Visitor for SolidityPreProcessor {
         _t: &mut VariableDeclaration,
         _ctx: &mut Context,
     ) -> VResult {
+        _t.variable_type.resolve_currency(&"Wei".to_string());
         if _ctx.in_function_or_special() {
+            // Dictionaries are hashed into storage, so there are no local ones and
+            // dictionary literals are only supported as contract property defaults
+            if let Type::DictionaryType(_) = _t.variable_type {
+                let message = format!(
+                    "Type error on line {}: dictionaries can only be declared as properties on the ethereum target",
+                    _t.identifier.line_info.line
+                );
+                println!("{}", message);
+                return Err(Box::from(message));
+            }
+
             if _ctx.scope_context().is_some() {
                 let context_ref = _ctx.ScopeContext.as_mut().unwrap();
                 context_ref.local_variables.push(_t.clone());
@@ -219,6 +275,7 @@ impl Visitor for SolidityPreProcessor {
     }
 
     fn start_function_call(&mut self, _t: &mut FunctionCall, _ctx: &mut Context) -> VResult {
+        _t.resolve_currency(&"Wei".to_string());
         if is_ether_runtime_function_call(_t) {
             return Ok(());
         }
@@ -376,7 +433,9 @@ impl Visitor for SolidityPreProcessor {
                     panic!("Can not handle Type Error")
                 }
 
-                if !arg_type.is_dynamic_type() {
+                if !arg_type.is_dynamic_type()
+                    || _ctx.environment.is_enum_declared(&arg_type.name())
+                {
                     continue;
                 }
 
@@ -392,6 +451,15 @@ impl Visitor for SolidityPreProcessor {
                             enclosing_type: None,
                             line_info: Default::default(),
                         });
+                    } else if _ctx.StructDeclarationContext.is_some() {
+                        is_mem = Expression::Identifier(Identifier {
+                            token: mangle_mem("QuartzSelf".to_string()),
+                            enclosing_type: None,
+                            line_info: Default::default(),
+                        });
+                    } else {
+                        // Contract properties live in storage
+                        is_mem = Expression::Literal(Literal::BooleanLiteral(false));
                     }
                 } else if let Expression::InoutExpression(i) = arg.expression.clone() {
                     if let Expression::SelfExpression = *i.expression.clone() {
@@ -427,6 +495,25 @@ impl Visitor for SolidityPreProcessor {
         Ok(())
     }
 
+    fn start_type(&mut self, _t: &mut Type, _ctx: &mut Context) -> VResult {
+        _t.resolve_currency(&"Wei".to_string());
+        Ok(())
+    }
+
+    fn start_special_declaration(
+        &mut self,
+        _t: &mut SpecialDeclaration,
+        _ctx: &mut Context,
+    ) -> VResult {
+        if _ctx.ContractBehaviourDeclarationContext.is_some() && _t.is_init() {
+            let default_assignments = default_assignments(_ctx);
+            for d in default_assignments.into_iter().rev() {
+                _t.body.insert(0, d);
+            }
+        }
+        Ok(())
+    }
+
     fn start_struct_member(&mut self, _t: &mut StructMember, _ctx: &mut Context) -> VResult {
         let member = _t.clone();
 
@@ -598,6 +685,14 @@ pub fn construct_parameter(name: String, t: Type) -> Parameter {
 }
 
 pub fn is_global_function_call(function_call: FunctionCall, ctx: &Context) -> bool {
+    // Calls on a receiver, such as funds.send(to: a, amount: x), belong to its type
+    // even when a global function accepts the same arguments
+    if let Some(receiver) = ctx.FunctionCallReceiverTrail.last() {
+        if *receiver != Expression::SelfExpression {
+            return false;
+        }
+    }
+
     let enclosing = ctx.enclosing_type_identifier().clone();
     let enclosing = enclosing.unwrap();
     let enclosing = enclosing.token.clone();
@@ -646,10 +741,18 @@ pub fn default_assignments(ctx: &Context) -> Vec<Statement> {
                 lhs_expression: Box::new(Expression::Identifier(identifier)),
                 rhs_expression: Box::new(p.get_value().unwrap()),
                 op: BinOp::Equal,
-                line_info: Default::default(),
+                line_info: p.get_identifier().line_info,
             }))
         })
         .collect();
 
     return statements;
 }
+
+fn is_filled_collection_literal(expression: &Expression) -> bool {
+    match expression {
+        Expression::ArrayLiteral(a) => !a.elements.is_empty(),
+        Expression::DictionaryLiteral(d) => !d.elements.is_empty(),
+        _ => false,
+    }
+}
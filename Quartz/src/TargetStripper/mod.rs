@@ -0,0 +1,413 @@
+use super::AstProcessor::Target;
+use super::AST::*;
+use std::collections::HashMap;
+
+// Removes the declarations, members and statements whose @ether, @libra or
+// @target(...) attributes do not select the target being compiled, so that the
+// environment is only built from code for that target.
+pub fn strip_module(
+    module: &mut Module,
+    target: &Target,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let errors = &mut vec![];
+    let removed = removed_properties(module, target);
+    module
+        .declarations
+        .retain(|d| selected(top_level_attributes(d), target, errors));
+
+    for declaration in &mut module.declarations {
+        match declaration {
+            TopLevelDeclaration::ContractDeclaration(c) => {
+                c.contract_members.retain(|m| match m {
+                    ContractMember::VariableDeclaration(v) => {
+                        selected(&v.attributes, target, errors)
+                    }
+                    ContractMember::EventDeclaration(e) => selected(&e.attributes, target, errors),
+                });
+            }
+            TopLevelDeclaration::ContractBehaviourDeclaration(c) => {
+                strip_behaviour(c, target, errors)
+            }
+            TopLevelDeclaration::StructDeclaration(s) => {
+                s.members.retain(|m| match m {
+                    StructMember::VariableDeclaration(v) => selected(&v.attributes, target, errors),
+                    StructMember::FunctionDeclaration(f) => {
+                        selected(&f.head.attributes, target, errors)
+                    }
+                    StructMember::SpecialDeclaration(s) => {
+                        selected(&s.head.attributes, target, errors)
+                    }
+                });
+                for member in &mut s.members {
+                    match member {
+                        StructMember::FunctionDeclaration(f) => strip_function(f, target, errors),
+                        StructMember::SpecialDeclaration(s) => strip_special(s, target, errors),
+                        StructMember::VariableDeclaration(_) => {}
+                    }
+                }
+            }
+            TopLevelDeclaration::AssetDeclaration(a) => {
+                a.members.retain(|m| match m {
+                    AssetMember::VariableDeclaration(v) => selected(&v.attributes, target, errors),
+                    AssetMember::FunctionDeclaration(f) => {
+                        selected(&f.head.attributes, target, errors)
+                    }
+                    AssetMember::SpecialDeclaration(s) => {
+                        selected(&s.head.attributes, target, errors)
+                    }
+                });
+                for member in &mut a.members {
+                    match member {
+                        AssetMember::FunctionDeclaration(f) => strip_function(f, target, errors),
+                        AssetMember::SpecialDeclaration(s) => strip_special(s, target, errors),
+                        AssetMember::VariableDeclaration(_) => {}
+                    }
+                }
+            }
+            TopLevelDeclaration::TraitDeclaration(t) => {
+                t.members.retain(|m| match m {
+                    TraitMember::FunctionDeclaration(f) => {
+                        selected(&f.head.attributes, target, errors)
+                    }
+                    TraitMember::SpecialDeclaration(s) => {
+                        selected(&s.head.attributes, target, errors)
+                    }
+                    TraitMember::FunctionSignatureDeclaration(f) => {
+                        selected(&f.attributes, target, errors)
+                    }
+                    TraitMember::SpecialSignatureDeclaration(s) => {
+                        selected(&s.attributes, target, errors)
+                    }
+                    TraitMember::ContractBehaviourDeclaration(c) => {
+                        selected(&c.attributes, target, errors)
+                    }
+                    TraitMember::EventDeclaration(e) => selected(&e.attributes, target, errors),
+                });
+                for member in &mut t.members {
+                    match member {
+                        TraitMember::FunctionDeclaration(f) => strip_function(f, target, errors),
+                        TraitMember::SpecialDeclaration(s) => strip_special(s, target, errors),
+                        TraitMember::FunctionSignatureDeclaration(f) => {
+                            f.attributes.retain(|a| !a.is_target())
+                        }
+                        TraitMember::SpecialSignatureDeclaration(s) => {
+                            s.attributes.retain(|a| !a.is_target())
+                        }
+                        TraitMember::ContractBehaviourDeclaration(c) => {
+                            strip_behaviour(c, target, errors)
+                        }
+                        TraitMember::EventDeclaration(_) => {}
+                    }
+                }
+            }
+            TopLevelDeclaration::EnumDeclaration(_) => {}
+        }
+    }
+
+    // Functions can not declare that they mutate properties of another target
+    for declaration in &mut module.declarations {
+        match declaration {
+            TopLevelDeclaration::ContractBehaviourDeclaration(c) => {
+                if let Some(removed) = removed.get(&c.identifier.token) {
+                    for member in &mut c.members {
+                        match member {
+                            ContractBehaviourMember::FunctionDeclaration(f) => {
+                                strip_mutates(&mut f.head.mutates, removed)
+                            }
+                            ContractBehaviourMember::SpecialDeclaration(s) => {
+                                strip_mutates(&mut s.head.mutates, removed)
+                            }
+                            ContractBehaviourMember::FunctionSignatureDeclaration(f) => {
+                                strip_mutates(&mut f.mutates, removed)
+                            }
+                            ContractBehaviourMember::SpecialSignatureDeclaration(s) => {
+                                strip_mutates(&mut s.mutates, removed)
+                            }
+                        }
+                    }
+                }
+            }
+            TopLevelDeclaration::StructDeclaration(s) => {
+                if let Some(removed) = removed.get(&s.identifier.token) {
+                    for member in &mut s.members {
+                        match member {
+                            StructMember::FunctionDeclaration(f) => {
+                                strip_mutates(&mut f.head.mutates, removed)
+                            }
+                            StructMember::SpecialDeclaration(s) => {
+                                strip_mutates(&mut s.head.mutates, removed)
+                            }
+                            StructMember::VariableDeclaration(_) => {}
+                        }
+                    }
+                }
+            }
+            TopLevelDeclaration::AssetDeclaration(a) => {
+                if let Some(removed) = removed.get(&a.identifier.token) {
+                    for member in &mut a.members {
+                        match member {
+                            AssetMember::FunctionDeclaration(f) => {
+                                strip_mutates(&mut f.head.mutates, removed)
+                            }
+                            AssetMember::SpecialDeclaration(s) => {
+                                strip_mutates(&mut s.head.mutates, removed)
+                            }
+                            AssetMember::VariableDeclaration(_) => {}
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !errors.is_empty() {
+        for error in errors.iter() {
+            println!("{}", error);
+        }
+        return Err(Box::from(errors.join("\n")));
+    }
+    Ok(())
+}
+
+// Unknown target names are recorded as errors and select nothing
+fn selected(attributes: &[Attribute], target: &Target, errors: &mut Vec<String>) -> bool {
+    for attribute in attributes {
+        for name in attribute.targets() {
+            if !Target::is_target_name(&name) {
+                errors.push(format!(
+                    "Target error on line {}: unknown target {}, expected one of ether, libra, move, yul or evm",
+                    attribute.line_info.line, name
+                ));
+            }
+        }
+    }
+    target.is_selected_by(attributes)
+}
+
+// The properties of each type that are only declared for other targets
+fn removed_properties(module: &Module, target: &Target) -> HashMap<String, Vec<String>> {
+    let mut removed = HashMap::new();
+    for declaration in &module.declarations {
+        let (identifier, properties): (&Identifier, Vec<&VariableDeclaration>) = match declaration {
+            TopLevelDeclaration::ContractDeclaration(c) => (
+                &c.identifier,
+                c.contract_members
+                    .iter()
+                    .filter_map(|m| match m {
+                        ContractMember::VariableDeclaration(v) => Some(v),
+                        ContractMember::EventDeclaration(_) => None,
+                    })
+                    .collect(),
+            ),
+            TopLevelDeclaration::StructDeclaration(s) => (
+                &s.identifier,
+                s.members
+                    .iter()
+                    .filter_map(|m| match m {
+                        StructMember::VariableDeclaration(v) => Some(v),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            TopLevelDeclaration::AssetDeclaration(a) => (
+                &a.identifier,
+                a.members
+                    .iter()
+                    .filter_map(|m| match m {
+                        AssetMember::VariableDeclaration(v) => Some(v),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => continue,
+        };
+
+        let (kept, stripped): (Vec<&VariableDeclaration>, Vec<&VariableDeclaration>) = properties
+            .into_iter()
+            .partition(|v| target.is_selected_by(&v.attributes));
+        let names: Vec<String> = stripped
+            .iter()
+            .map(|v| v.identifier.token.clone())
+            .filter(|name| !kept.iter().any(|v| &v.identifier.token == name))
+            .collect();
+        if !names.is_empty() {
+            removed.insert(identifier.token.clone(), names);
+        }
+    }
+    removed
+}
+
+fn strip_mutates(mutates: &mut Vec<Identifier>, removed: &[String]) {
+    mutates.retain(|m| !removed.contains(&m.token));
+}
+
+fn top_level_attributes(declaration: &TopLevelDeclaration) -> &Vec<Attribute> {
+    match declaration {
+        TopLevelDeclaration::ContractDeclaration(c) => &c.attributes,
+        TopLevelDeclaration::ContractBehaviourDeclaration(c) => &c.attributes,
+        TopLevelDeclaration::StructDeclaration(s) => &s.attributes,
+        TopLevelDeclaration::AssetDeclaration(a) => &a.attributes,
+        TopLevelDeclaration::EnumDeclaration(e) => &e.attributes,
+        TopLevelDeclaration::TraitDeclaration(t) => &t.attributes,
+    }
+}
+
+fn strip_behaviour(
+    behaviour: &mut ContractBehaviourDeclaration,
+    target: &Target,
+    errors: &mut Vec<String>,
+) {
+    behaviour.members.retain(|m| match m {
+        ContractBehaviourMember::FunctionDeclaration(f) => {
+            selected(&f.head.attributes, target, errors)
+        }
+        ContractBehaviourMember::SpecialDeclaration(s) => {
+            selected(&s.head.attributes, target, errors)
+        }
+        ContractBehaviourMember::FunctionSignatureDeclaration(f) => {
+            selected(&f.attributes, target, errors)
+        }
+        ContractBehaviourMember::SpecialSignatureDeclaration(s) => {
+            selected(&s.attributes, target, errors)
+        }
+    });
+    for member in &mut behaviour.members {
+        match member {
+            ContractBehaviourMember::FunctionDeclaration(f) => strip_function(f, target, errors),
+            ContractBehaviourMember::SpecialDeclaration(s) => strip_special(s, target, errors),
+            ContractBehaviourMember::FunctionSignatureDeclaration(f) => {
+                f.attributes.retain(|a| !a.is_target())
+            }
+            ContractBehaviourMember::SpecialSignatureDeclaration(s) => {
+                s.attributes.retain(|a| !a.is_target())
+            }
+        }
+    }
+}
+
+// Target attributes are dropped from the signatures that remain, so that they do
+// not take part in signature matching
+fn strip_function(function: &mut FunctionDeclaration, target: &Target, errors: &mut Vec<String>) {
+    function.head.attributes.retain(|a| !a.is_target());
+    strip_statements(&mut function.body, target, errors);
+}
+
+fn strip_special(special: &mut SpecialDeclaration, target: &Target, errors: &mut Vec<String>) {
+    special.head.attributes.retain(|a| !a.is_target());
+    strip_statements(&mut special.body, target, errors);
+}
+
+fn strip_statements(statements: &mut Vec<Statement>, target: &Target, errors: &mut Vec<String>) {
+    let mut retained = vec![];
+    for statement in statements.drain(..) {
+        let statement = match statement {
+            Statement::TargetStatement(t) => {
+                if !selected(&t.attributes, target, errors) {
+                    continue;
+                }
+                *t.statement
+            }
+            statement => statement,
+        };
+        retained.push(statement);
+    }
+
+    for statement in &mut retained {
+        match statement {
+            Statement::IfStatement(i) => {
+                strip_statements(&mut i.body, target, errors);
+                strip_statements(&mut i.else_body, target, errors);
+            }
+            Statement::ForStatement(f) => strip_statements(&mut f.body, target, errors),
+            Statement::DoCatchStatement(d) => {
+                strip_statements(&mut d.do_body, target, errors);
+                strip_statements(&mut d.catch_body, target, errors);
+            }
+            _ => {}
+        }
+    }
+    *statements = retained;
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::strip_module;
+    use crate::AstProcessor::tests::checked;
+    use crate::AstProcessor::Target;
+    use crate::Parser::parse_module;
+    use crate::AST::{ContractBehaviourMember, TopLevelDeclaration};
+    use nom_locate::LocatedSpan;
+
+    const COUNTER: &str = "
+        contract Counter {
+          var count: Int = 0
+          @libra
+          var events: Int = 0
+        }
+        Counter :: caller <- (any) {
+          public init() {}
+
+          public func increment() mutates (count, events) {
+            count += 1
+            @libra events += 1
+          }
+
+          @ether
+          public func reset() mutates (count) {
+            count = 0
+          }
+        }";
+
+    // The functions of the behaviour of a checked program, with what they mutate
+    fn functions(target: &Target) -> Vec<(String, Vec<String>)> {
+        let (module, _) = checked(COUNTER, target).unwrap();
+        let mut functions = vec![];
+        for declaration in module.declarations {
+            if let TopLevelDeclaration::ContractBehaviourDeclaration(c) = declaration {
+                for member in c.members {
+                    if let ContractBehaviourMember::FunctionDeclaration(f) = member {
+                        let mutates = f.head.mutates.iter().map(|m| m.token.clone()).collect();
+                        functions.push((f.head.identifier.token, mutates));
+                    }
+                }
+            }
+        }
+        functions
+    }
+
+    #[test]
+    fn test_strip_target() {
+        assert_eq!(
+            functions(&Target::Ether),
+            vec![
+                ("increment".to_string(), vec!["count".to_string()]),
+                ("reset".to_string(), vec!["count".to_string()]),
+            ]
+        );
+        assert_eq!(
+            functions(&Target::Move),
+            vec![(
+                "increment".to_string(),
+                vec!["count".to_string(), "events".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unknown_target() {
+        // Unknown targets are reported while parsing, before there is a module to check
+        let program = "
+            contract Counter {
+              @target(solana)
+              var count: Int = 0
+            }";
+        let (_, mut module) = parse_module(LocatedSpan::new_extra(program, 0)).unwrap();
+        let error = strip_module(&mut module, &Target::Ether).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Target error on line 3: unknown target solana, expected one of ether, libra, move, yul or evm"
+        );
+    }
+}
@@ -11,6 +11,10 @@ impl Visitor for TypeAssigner {
         _t: &mut VariableDeclaration,
         _ctx: &mut Context,
     ) -> VResult {
+        if let Some(expression) = _t.expression.as_mut() {
+            assign_fixed_point_literal(expression, &_t.variable_type);
+        }
+
         if _ctx.in_function_or_special() {
             if _ctx.scope_context().is_some() {
                 let context_ref = _ctx.ScopeContext.as_mut().unwrap();
@@ -30,6 +34,95 @@ impl Visitor for TypeAssigner {
         Ok(())
     }
 
+    fn start_binary_expression(
+        &mut self,
+        _t: &mut BinaryExpression,
+        _ctx: &mut Context,
+    ) -> VResult {
+        if let BinOp::Dot = _t.op {
+            return Ok(());
+        }
+
+        let enclosing = _ctx.enclosing_type_identifier().unwrap_or_default();
+        let scope = _ctx.ScopeContext.clone().unwrap_or_default();
+
+        // A local declared without a type annotation takes the type of its
+        // initial value, before it is added to the scope
+        if let BinOp::Equal = _t.op {
+            if let Expression::VariableDeclaration(v) = &mut *_t.lhs_expression {
+                if let Type::Error = v.variable_type {
+                    v.variable_type = _ctx.environment.get_expression_type(
+                        *_t.rhs_expression.clone(),
+                        &enclosing.token,
+                        vec![],
+                        vec![],
+                        scope.clone(),
+                    );
+                }
+            }
+        }
+
+        if let Expression::Literal(_) = *_t.rhs_expression {
+            let lhs_type = _ctx.environment.get_expression_type(
+                *_t.lhs_expression.clone(),
+                &enclosing.token,
+                vec![],
+                vec![],
+                scope,
+            );
+            assign_fixed_point_literal(&mut _t.rhs_expression, &lhs_type);
+        } else if let Expression::Literal(_) = *_t.lhs_expression {
+            let rhs_type = _ctx.environment.get_expression_type(
+                *_t.rhs_expression.clone(),
+                &enclosing.token,
+                vec![],
+                vec![],
+                scope,
+            );
+            assign_fixed_point_literal(&mut _t.lhs_expression, &rhs_type);
+        }
+        Ok(())
+    }
+
+    fn start_function_call(&mut self, _t: &mut FunctionCall, _ctx: &mut Context) -> VResult {
+        if _t.identifier.enclosing_type.is_none() {
+            let enclosing = _ctx.enclosing_type_identifier().unwrap_or_default();
+            assign_fixed_point_arguments(_t, &enclosing.token, _ctx);
+        }
+        Ok(())
+    }
+
+    fn start_for_statement(&mut self, _t: &mut ForStatement, _ctx: &mut Context) -> VResult {
+        if let Type::Error = _t.variable.variable_type {
+            let enclosing = _ctx.enclosing_type_identifier().unwrap_or_default();
+            let iterable_type = _ctx.environment.get_expression_type(
+                _t.iterable.clone(),
+                &enclosing.token,
+                vec![],
+                vec![],
+                _ctx.ScopeContext.clone().unwrap_or_default(),
+            );
+            _t.variable.variable_type = match iterable_type {
+                Type::ArrayType(a) => *a.key_type,
+                Type::FixedSizedArrayType(a) => *a.key_type,
+                Type::DictionaryType(d) => *d.value_type,
+                Type::RangeType(r) => *r.key_type,
+                _ => Type::Error,
+            };
+        }
+        Ok(())
+    }
+
+    fn start_return_statement(&mut self, _t: &mut ReturnStatement, _ctx: &mut Context) -> VResult {
+        if let Some(function) = _ctx.FunctionDeclarationContext.as_ref() {
+            let result_type = function.declaration.get_result_type();
+            if let (Some(expression), Some(result_type)) = (_t.expression.as_mut(), result_type) {
+                assign_fixed_point_literal(expression, &result_type);
+            }
+        }
+        Ok(())
+    }
+
     fn finish_binary_expression(
         &mut self,
         _t: &mut BinaryExpression,
@@ -64,7 +157,48 @@ impl Visitor for TypeAssigner {
             } else {
                 _t.rhs_expression.assign_enclosing_type(&lhs_type.name());
             }
+
+            if let Expression::FunctionCall(f) = &mut *_t.rhs_expression {
+                assign_fixed_point_arguments(f, &lhs_type.name(), _ctx);
+            }
         }
         Ok(())
     }
 }
+
+// Numeric literals used where a Fixed value is expected are written out with
+// that type's number of decimals, so that they are stored at the right scale
+fn assign_fixed_point_literal(expression: &mut Expression, expected_type: &Type) {
+    let expected_type = match expected_type {
+        Type::InoutType(i) => *i.key_type.clone(),
+        _ => expected_type.clone(),
+    };
+
+    if let Type::FixedPointType(f) = expected_type {
+        if let Expression::Literal(l) = expression {
+            if let Some(literal) = f.literal(l) {
+                *expression = Expression::Literal(literal);
+            }
+        }
+    }
+}
+
+fn assign_fixed_point_arguments(call: &mut FunctionCall, enclosing: &TypeIdentifier, ctx: &Context) {
+    let type_info = ctx.environment.types.get(enclosing);
+    let functions = type_info.and_then(|t| t.functions.get(&call.identifier.token));
+    let functions = match functions {
+        Some(functions) => functions,
+        None => return,
+    };
+
+    let candidate = functions
+        .iter()
+        .find(|f| f.get_parameter_types().len() == call.arguments.len())
+        .or_else(|| functions.first());
+    if let Some(candidate) = candidate {
+        let parameter_types = candidate.get_parameter_types();
+        for (argument, parameter_type) in call.arguments.iter_mut().zip(parameter_types) {
+            assign_fixed_point_literal(&mut argument.expression, &parameter_type);
+        }
+    }
+}
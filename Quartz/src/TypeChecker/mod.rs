@@ -2,16 +2,101 @@ use super::context::*;
 use super::environment::*;
 use super::visitor::*;
 use super::AST::*;
+use std::fmt;
 
-pub struct TypeChecker {}
+pub struct TypeChecker {
+    diagnostics: Vec<TypeError>,
+}
+
+impl TypeChecker {
+    pub fn new() -> TypeChecker {
+        TypeChecker {
+            diagnostics: vec![],
+        }
+    }
+
+    fn expression_type(&self, expression: &Expression, _ctx: &Context) -> Type {
+        let enclosing = _ctx.enclosing_type_identifier().unwrap_or_default();
+        let enclosing = enclosing.token;
+        let expression_type = _ctx.environment.get_expression_type(
+            expression.clone(),
+            &enclosing,
+            vec![],
+            vec![],
+            _ctx.ScopeContext.clone().unwrap_or_default(),
+        );
+
+        // External signatures may name types that were never declared, such as
+        // Move's uint64, so those are left unchecked
+        if let Type::UserDefinedType(u) = &expression_type {
+            if !_ctx.environment.is_type_declared(&u.token) {
+                return Type::Error;
+            }
+        }
+        expression_type
+    }
+
+    fn expect(&mut self, position: String, expected: Type, actual: Type, line_info: LineInfo) {
+        if !is_assignable(&expected, &actual) {
+            self.diagnostics.push(TypeError::Mismatch {
+                position,
+                expected,
+                actual,
+                line_info,
+            });
+        }
+    }
+
+    // Decimal literals are stored as 64 bit integers scaled by their decimals
+    fn check_fixed_point_literal(&mut self, expression: &Expression, line_info: &LineInfo) {
+        if let Expression::Literal(Literal::FloatLiteral(f)) = expression {
+            if !FixedPointType::fits(f) {
+                self.diagnostics.push(TypeError::FixedPointLiteralOutOfRange {
+                    literal: f.clone(),
+                    line_info: line_info.clone(),
+                });
+            }
+        }
+    }
+
+    fn expect_numeric(&mut self, position: String, actual: Type, line_info: LineInfo) {
+        if !is_numeric(&actual) {
+            self.diagnostics.push(TypeError::Mismatch {
+                position,
+                expected: Type::Int,
+                actual,
+                line_info,
+            });
+        }
+    }
+}
 
 impl Visitor for TypeChecker {
+    fn finish_module(&mut self, _t: &mut Module, _ctx: &mut Context) -> VResult {
+        if self.diagnostics.is_empty() {
+            return Ok(());
+        }
+
+        let messages: Vec<String> = self.diagnostics.iter().map(|d| d.to_string()).collect();
+        for message in &messages {
+            println!("{}", message);
+        }
+        Err(Box::from(messages.join("\n")))
+    }
+
     fn start_variable_declaration(
         &mut self,
         _t: &mut VariableDeclaration,
         _ctx: &mut Context,
     ) -> VResult {
         if _ctx.in_function_or_special() {
+            if !is_known(&_t.variable_type) {
+                self.diagnostics.push(TypeError::UninferredType {
+                    variable: _t.identifier.token.clone(),
+                    line_info: _t.identifier.line_info.clone(),
+                });
+            }
+
             if _ctx.scope_context().is_some() {
                 let context_ref = _ctx.ScopeContext.as_mut().unwrap();
                 context_ref.local_variables.push(_t.clone());
@@ -26,6 +111,16 @@ impl Visitor for TypeChecker {
                 let context_ref = _ctx.SpecialDeclarationContext.as_mut().unwrap();
                 context_ref.local_variables.push(_t.clone());
             }
+        } else if let Some(expression) = &_t.expression {
+            // Default values of properties
+            self.check_fixed_point_literal(expression, &_t.identifier.line_info);
+            let actual = self.expression_type(expression, _ctx);
+            self.expect(
+                format!("Property {}", _t.identifier.token),
+                _t.variable_type.clone(),
+                actual,
+                _t.identifier.line_info.clone(),
+            );
         }
         Ok(())
     }
@@ -55,20 +150,451 @@ impl Visitor for TypeChecker {
         _t: &mut BinaryExpression,
         _ctx: &mut Context,
     ) -> VResult {
-        let enclosing = _ctx.enclosing_type_identifier().unwrap_or_default();
-        let enclosing = enclosing.token;
-        let lhs_type = _ctx.environment.get_expression_type(
-            *_t.lhs_expression.clone(),
-            &enclosing,
-            vec![],
-            vec![],
-            _ctx.ScopeContext.clone().unwrap_or_default(),
-        );
+        let lhs_type = self.expression_type(&_t.lhs_expression, _ctx);
+        if let BinOp::Dot = _t.op {
+            _t.rhs_expression.assign_enclosing_type(&lhs_type.name());
+            return Ok(());
+        }
+
+        let rhs_type = self.expression_type(&_t.rhs_expression, _ctx);
+        let line_info = _t.line_info.clone();
+        self.check_fixed_point_literal(&_t.lhs_expression, &line_info);
+        self.check_fixed_point_literal(&_t.rhs_expression, &line_info);
         match _t.op {
-            BinOp::Dot => _t.rhs_expression.assign_enclosing_type(&lhs_type.name()),
-            BinOp::Equal => {}
-            _ => {}
+            BinOp::Equal => {
+                self.expect("Assignment".to_string(), lhs_type, rhs_type, line_info);
+            }
+            BinOp::DoubleEqual | BinOp::NotEqual => {
+                if !is_assignable(&lhs_type, &rhs_type) && !is_assignable(&rhs_type, &lhs_type) {
+                    self.diagnostics.push(TypeError::Mismatch {
+                        position: format!("Right operand of {}", _t.op),
+                        expected: lhs_type,
+                        actual: rhs_type,
+                        line_info,
+                    });
+                }
+            }
+            BinOp::And | BinOp::Or | BinOp::Implies => {
+                let position = format!("Left operand of {}", _t.op);
+                self.expect(position, Type::Bool, lhs_type, line_info.clone());
+                let position = format!("Right operand of {}", _t.op);
+                self.expect(position, Type::Bool, rhs_type, line_info);
+            }
+            _ => {
+                // Arithmetic, comparisons and shorthand assignments work on numbers
+                let op = if _t.op.is_assignment_shorthand() {
+                    _t.op.get_assignment_shorthand()
+                } else {
+                    _t.op.clone()
+                };
+                let (lhs, rhs) = (strip_inout(&lhs_type), strip_inout(&rhs_type));
+                if let Err(message) = FixedPointType::operation_scale(&op, &lhs, &rhs) {
+                    self.diagnostics.push(TypeError::InvalidFixedPointOperation {
+                        message,
+                        line_info: line_info.clone(),
+                    });
+                }
+                let position = format!("Left operand of {}", _t.op);
+                self.expect_numeric(position, lhs_type, line_info.clone());
+                let position = format!("Right operand of {}", _t.op);
+                self.expect_numeric(position, rhs_type, line_info);
+            }
+        }
+        Ok(())
+    }
+
+    fn start_return_statement(&mut self, _t: &mut ReturnStatement, _ctx: &mut Context) -> VResult {
+        if !_ctx.is_function_declaration_context() {
+            return Ok(());
+        }
+
+        let result_type = _ctx
+            .FunctionDeclarationContext
+            .as_ref()
+            .unwrap()
+            .declaration
+            .get_result_type();
+        let line_info = _t.line_info.clone();
+        if let Some(expression) = &_t.expression {
+            self.check_fixed_point_literal(expression, &line_info);
+        }
+        match (&_t.expression, result_type) {
+            (Some(expression), Some(result_type)) => {
+                let actual = self.expression_type(expression, _ctx);
+                self.expect("Return value".to_string(), result_type, actual, line_info);
+            }
+            (Some(expression), None) => {
+                let actual = self.expression_type(expression, _ctx);
+                if actual != Type::Error {
+                    self.diagnostics
+                        .push(TypeError::UnexpectedReturnValue { actual, line_info });
+                }
+            }
+            (None, Some(expected)) => {
+                self.diagnostics.push(TypeError::MissingReturnValue {
+                    expected,
+                    line_info,
+                });
+            }
+            (None, None) => {}
+        }
+        Ok(())
+    }
+
+    fn start_function_call(&mut self, _t: &mut FunctionCall, _ctx: &mut Context) -> VResult {
+        for argument in &_t.arguments {
+            self.check_fixed_point_literal(&argument.expression, &_t.identifier.line_info);
+        }
+        Ok(())
+    }
+
+    fn start_if_statement(&mut self, _t: &mut IfStatement, _ctx: &mut Context) -> VResult {
+        let actual = self.expression_type(&_t.condition, _ctx);
+        let line_info = _t.condition.get_line_info();
+        self.expect("If condition".to_string(), Type::Bool, actual, line_info);
+        Ok(())
+    }
+
+    fn start_for_statement(&mut self, _t: &mut ForStatement, _ctx: &mut Context) -> VResult {
+        let iterable = self.expression_type(&_t.iterable, _ctx);
+        let line_info = _t.variable.identifier.line_info.clone();
+        let element_type = match strip_inout(&iterable) {
+            Type::ArrayType(a) => *a.key_type,
+            Type::FixedSizedArrayType(a) => *a.key_type,
+            Type::RangeType(r) => *r.key_type,
+            Type::DictionaryType(d) => *d.value_type,
+            Type::Error => Type::Error,
+            _ => {
+                self.diagnostics.push(TypeError::NotIterable {
+                    actual: iterable,
+                    line_info,
+                });
+                return Ok(());
+            }
+        };
+
+        let position = format!("Loop variable {}", _t.variable.identifier.token);
+        self.expect(
+            position,
+            _t.variable.variable_type.clone(),
+            element_type,
+            line_info,
+        );
+        Ok(())
+    }
+
+    fn start_subscript_expression(
+        &mut self,
+        _t: &mut SubscriptExpression,
+        _ctx: &mut Context,
+    ) -> VResult {
+        let base = Expression::Identifier(_t.base_expression.clone());
+        let base_type = self.expression_type(&base, _ctx);
+        let index_type = self.expression_type(&_t.index_expression, _ctx);
+        let line_info = _t.base_expression.line_info.clone();
+        let key_type = match strip_inout(&base_type) {
+            Type::ArrayType(_) | Type::FixedSizedArrayType(_) => Type::Int,
+            Type::DictionaryType(d) => *d.key_type,
+            Type::Error => return Ok(()),
+            _ => {
+                self.diagnostics.push(TypeError::NotSubscriptable {
+                    actual: base_type,
+                    line_info,
+                });
+                return Ok(());
+            }
+        };
+
+        let position = format!("Subscript of {}", _t.base_expression.token);
+        self.expect(position, key_type, index_type, line_info);
+        Ok(())
+    }
+
+    fn start_cast_expression(&mut self, _t: &mut CastExpression, _ctx: &mut Context) -> VResult {
+        let from = self.expression_type(&_t.expression, _ctx);
+        let to = _t.cast_type.clone();
+        let is_valid = is_assignable(&to, &from) || (is_scalar(&from) && is_scalar(&to));
+        if !is_valid {
+            self.diagnostics.push(TypeError::InvalidCast {
+                from,
+                to,
+                line_info: _t.expression.get_line_info(),
+            });
         }
         Ok(())
     }
 }
+
+// A type error, naming the types involved
+pub enum TypeError {
+    Mismatch {
+        position: String,
+        expected: Type,
+        actual: Type,
+        line_info: LineInfo,
+    },
+    UnexpectedReturnValue {
+        actual: Type,
+        line_info: LineInfo,
+    },
+    MissingReturnValue {
+        expected: Type,
+        line_info: LineInfo,
+    },
+    NotIterable {
+        actual: Type,
+        line_info: LineInfo,
+    },
+    NotSubscriptable {
+        actual: Type,
+        line_info: LineInfo,
+    },
+    InvalidCast {
+        from: Type,
+        to: Type,
+        line_info: LineInfo,
+    },
+    UninferredType {
+        variable: String,
+        line_info: LineInfo,
+    },
+    InvalidFixedPointOperation {
+        message: String,
+        line_info: LineInfo,
+    },
+    FixedPointLiteralOutOfRange {
+        literal: String,
+        line_info: LineInfo,
+    },
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeError::Mismatch {
+                position,
+                expected,
+                actual,
+                line_info,
+            } => write!(
+                f,
+                "Type error on line {}: {} expects {} but found {}",
+                line_info.line, position, expected, actual
+            ),
+            TypeError::UnexpectedReturnValue { actual, line_info } => write!(
+                f,
+                "Type error on line {}: function without a result type returns {}",
+                line_info.line, actual
+            ),
+            TypeError::MissingReturnValue {
+                expected,
+                line_info,
+            } => write!(
+                f,
+                "Type error on line {}: return expects a value of type {}",
+                line_info.line, expected
+            ),
+            TypeError::NotIterable { actual, line_info } => write!(
+                f,
+                "Type error on line {}: for loop expects an array, dictionary or range but found {}",
+                line_info.line, actual
+            ),
+            TypeError::NotSubscriptable { actual, line_info } => write!(
+                f,
+                "Type error on line {}: subscript expects an array or dictionary but found {}",
+                line_info.line, actual
+            ),
+            TypeError::InvalidCast {
+                from,
+                to,
+                line_info,
+            } => write!(
+                f,
+                "Type error on line {}: cannot cast {} to {}",
+                line_info.line, from, to
+            ),
+            TypeError::UninferredType {
+                variable,
+                line_info,
+            } => write!(
+                f,
+                "Type error on line {}: the type of {} cannot be inferred, add a type annotation",
+                line_info.line, variable
+            ),
+            TypeError::InvalidFixedPointOperation { message, line_info } => write!(
+                f,
+                "Type error on line {}: {}",
+                line_info.line, message
+            ),
+            TypeError::FixedPointLiteralOutOfRange { literal, line_info } => write!(
+                f,
+                "Type error on line {}: the fixed point literal {} is too large",
+                line_info.line, literal
+            ),
+        }
+    }
+}
+
+fn strip_inout(t: &Type) -> Type {
+    match t {
+        Type::InoutType(i) => strip_inout(&i.key_type),
+        _ => t.clone(),
+    }
+}
+
+// Whether a value of type `actual` may be used where `expected` is required.
+// Types the environment cannot work out are not reported again here.
+fn is_assignable(expected: &Type, actual: &Type) -> bool {
+    match (strip_inout(expected), strip_inout(actual)) {
+        (Type::Error, _) | (_, Type::Error) => true,
+        (Type::SelfType, _) | (_, Type::SelfType) => true,
+        (Type::QuartzType(_), _) | (_, Type::QuartzType(_)) => true,
+        (Type::Solidity(_), a) => is_scalar(&a),
+        (e, Type::Solidity(_)) => is_scalar(&e),
+        (Type::FixedPointType(e), Type::FixedPointType(a)) => e.decimals == a.decimals,
+        (Type::ArrayType(e), Type::ArrayType(a)) => is_assignable(&e.key_type, &a.key_type),
+        (Type::ArrayType(e), Type::FixedSizedArrayType(a)) => {
+            is_assignable(&e.key_type, &a.key_type)
+        }
+        (Type::FixedSizedArrayType(e), Type::ArrayType(a)) => {
+            is_assignable(&e.key_type, &a.key_type)
+        }
+        (Type::FixedSizedArrayType(e), Type::FixedSizedArrayType(a)) => {
+            e.size == a.size && is_assignable(&e.key_type, &a.key_type)
+        }
+        (Type::DictionaryType(e), Type::DictionaryType(a)) => {
+            is_assignable(&e.key_type, &a.key_type) && is_assignable(&e.value_type, &a.value_type)
+        }
+        (Type::RangeType(e), Type::RangeType(a)) => is_assignable(&e.key_type, &a.key_type),
+        (Type::UserDefinedType(e), Type::UserDefinedType(a)) => e.token == a.token,
+        (Type::Bool, Type::Bool) => true,
+        (Type::Int, Type::Int) => true,
+        (Type::String, Type::String) => true,
+        (Type::Address, Type::Address) => true,
+        _ => false,
+    }
+}
+
+fn is_numeric(t: &Type) -> bool {
+    match strip_inout(t) {
+        Type::Int => true,
+        Type::FixedPointType(_) => true,
+        Type::Solidity(_) => true,
+        Type::Error => true,
+        _ => false,
+    }
+}
+
+// Whether a declared or inferred type is fully known
+fn is_known(t: &Type) -> bool {
+    match t {
+        Type::Error => false,
+        Type::InoutType(i) => is_known(&i.key_type),
+        Type::ArrayType(a) => is_known(&a.key_type),
+        Type::FixedSizedArrayType(a) => is_known(&a.key_type),
+        Type::DictionaryType(d) => is_known(&d.key_type) && is_known(&d.value_type),
+        _ => true,
+    }
+}
+
+// Values that casts may convert between
+fn is_scalar(t: &Type) -> bool {
+    match strip_inout(t) {
+        Type::Address => true,
+        Type::Bool => true,
+        Type::String => true,
+        _ => is_numeric(t),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::AstProcessor::tests::checked;
+    use crate::AstProcessor::Target;
+
+    #[test]
+    fn test_type_errors() {
+        let program = "
+            contract Shop {
+              var price: Int = true
+              var names: [Address: String] = [:]
+            }
+            Shop :: caller <- (any) {
+              public init() {}
+
+              public func check(open: Bool) -> Int mutates (price) {
+                if price {}
+                let name: String = names[price]
+                price = open + 1
+                let total: Int = cast names to Int
+                return open
+              }
+
+              public func nothing() {
+                return price
+              }
+            }";
+        let error = checked(program, &Target::Ether).err().unwrap();
+        let errors: Vec<&str> = error.lines().collect();
+        assert_eq!(
+            errors,
+            vec![
+                "Type error on line 3: Property price expects Int but found Bool",
+                "Type error on line 10: If condition expects Bool but found Int",
+                "Type error on line 11: Subscript of names expects Address but found Int",
+                "Type error on line 12: Left operand of + expects Int but found Bool",
+                "Type error on line 13: cannot cast [Address: String] to Int",
+                "Type error on line 14: Return value expects Int but found Bool",
+                "Type error on line 18: function without a result type returns Int",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_well_typed() {
+        let program = "
+            contract Shop {
+              var price: Int = 0
+              var rate: Fixed<2> = 0.5
+              var names: [Address: String] = [:]
+            }
+            Shop :: caller <- (any) {
+              public init() {}
+
+              public func check(open: Bool, buyer: Address) -> Int mutates (price, rate) {
+                if open {
+                  price = price * 2 + 1
+                }
+                let name: String = names[buyer]
+                rate = rate * 2
+                return price
+              }
+            }";
+        assert!(checked(program, &Target::Ether).is_ok());
+    }
+
+    #[test]
+    fn test_fixed_point_literal_range() {
+        let program = "
+            contract Vault {
+              var rate: Fixed<2> = 1.5
+            }
+            Vault :: caller <- (any) {
+              public init() {}
+
+              public func set() mutates (rate) {
+                rate = 184467440737095516.15
+              }
+
+              public func overflow() mutates (rate) {
+                rate = 184467440737095516.16
+              }
+            }";
+        let error = checked(program, &Target::Ether).err().unwrap();
+        assert_eq!(
+            error,
+            "Type error on line 13: the fixed point literal 184467440737095516.16 is too large"
+        );
+    }
+}
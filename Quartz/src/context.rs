@@ -74,7 +74,7 @@ impl Context {
         self.ContractBehaviourDeclarationContext.is_some()
     }
 
-    fn is_struct_declaration_context(&self) -> bool {
+    pub(crate) fn is_struct_declaration_context(&self) -> bool {
         self.StructDeclarationContext.is_some()
     }
 
@@ -114,6 +114,7 @@ pub struct ContractDeclarationContext {
 
 #[derive(Debug, Clone)]
 pub struct ContractBehaviourDeclarationContext {
+    pub attributes: Vec<Attribute>,
     pub identifier: Identifier,
     pub caller: Option<Identifier>,
     pub caller_protections: Vec<CallerProtection>,
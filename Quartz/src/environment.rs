@@ -1,7 +1,10 @@
 use super::context::*;
+use super::Linter::LintLevel;
+use super::MoveCodeGen::MoveSource::CoinModule;
 use super::SemanticAnalysis::*;
 use super::AST::*;
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug, Default, Clone)]
 pub struct Environment {
@@ -10,8 +13,18 @@ pub struct Environment {
     pub enum_declarations: Vec<Identifier>,
     pub event_declarations: Vec<Identifier>,
     pub trait_declarations: Vec<Identifier>,
+    pub struct_trait_declarations: Vec<Identifier>,
     pub asset_declarations: Vec<Identifier>,
     pub types: HashMap<TypeIdentifier, TypeInfo>,
+    pub packed_storage: bool,
+    // Gas estimates of public functions are printed and written with the code
+    pub gas_report: bool,
+    // The call graph is printed and written as dot or json when set
+    pub call_graph_format: String,
+    // The modules that coins map to on the move target
+    pub coin_module: CoinModule,
+    // Lint levels set on the command line
+    pub lint_levels: HashMap<String, LintLevel>,
 }
 
 #[derive(Debug, Clone)]
@@ -55,6 +68,21 @@ pub struct Candidates {
     pub(crate) candidates: Vec<CallableInformation>,
 }
 
+#[derive(Debug, Clone)]
+pub struct StorageSlot {
+    pub property: String,
+    pub property_type: Type,
+    pub slot: u64,
+    pub byte_offset: u64,
+    pub byte_size: u64,
+}
+
+impl StorageSlot {
+    pub fn is_packed(&self) -> bool {
+        self.byte_size < 32
+    }
+}
+
 impl Environment {
     pub fn build(&mut self, module: Module) {
         for declaration in module.declarations {
@@ -117,6 +145,13 @@ impl Environment {
             },
         );
 
+        for conformance in &s.conformances {
+            self.add_conformance(
+                &s.identifier.token.clone(),
+                &conformance.identifier.token.clone(),
+            )
+        }
+
         let members = &s.members;
         for member in members {
             match member {
@@ -146,6 +181,13 @@ impl Environment {
             },
         );
 
+        for conformance in &a.conformances {
+            self.add_conformance(
+                &a.identifier.token.clone(),
+                &conformance.identifier.token.clone(),
+            )
+        }
+
         let members = &a.members;
         for member in members {
             match member {
@@ -166,6 +208,9 @@ impl Environment {
 
     pub fn add_trait_declaration(&mut self, t: &TraitDeclaration) {
         let identifier = t.identifier.clone();
+        if !t.is_external() {
+            return self.add_struct_trait_declaration(t);
+        }
         &self.trait_declarations.push(identifier);
 
         let special = Environment::external_trait_init();
@@ -213,6 +258,37 @@ impl Environment {
         }
     }
 
+    // Struct and asset traits only list the functions their conforming types provide
+    fn add_struct_trait_declaration(&mut self, t: &TraitDeclaration) {
+        let identifier = t.identifier.clone();
+        self.struct_trait_declarations.push(identifier);
+
+        self.types.insert(
+            t.identifier.token.clone(),
+            TypeInfo {
+                ..Default::default()
+            },
+        );
+
+        for member in t.members.clone() {
+            match member {
+                TraitMember::FunctionDeclaration(f) => {
+                    self.add_function(&f, &t.identifier.token, vec![])
+                }
+                TraitMember::FunctionSignatureDeclaration(f) => {
+                    self.add_function_signature(&f, &t.identifier.token, vec![], false)
+                }
+                TraitMember::SpecialSignatureDeclaration(s) => {
+                    self.add_init_sig(s, &t.identifier.token, vec![], false)
+                }
+                _ => panic!(
+                    "Trait {} may only declare functions",
+                    t.identifier.token
+                ),
+            }
+        }
+    }
+
     fn add_contract_behaviour_declaration(&mut self, c: &ContractBehaviourDeclaration) {
         let members = &c.members;
         let caller_protections = &c.caller_protections.clone();
@@ -232,7 +308,7 @@ impl Environment {
 
     fn add_enum_declaration(&mut self, e: &EnumDeclaration) {
         let identifier = e.identifier.clone();
-        &self.trait_declarations.push(identifier);
+        self.enum_declarations.push(identifier);
 
         &self.types.insert(
             e.identifier.token.clone(),
@@ -240,6 +316,45 @@ impl Environment {
                 ..Default::default()
             },
         );
+
+        match e.type_assigned.clone() {
+            None => {}
+            Some(Type::Int) => {}
+            Some(t) => panic!(
+                "Enum {} must have Int raw values, found {}",
+                e.identifier.token,
+                t.name()
+            ),
+        }
+
+        // Cases without an explicit raw value count up from the previous one
+        let mut next_value = 0;
+        for member in &e.members {
+            let hidden_value = match member.hidden_value.clone() {
+                None => Expression::Literal(Literal::IntLiteral(next_value)),
+                Some(Expression::Literal(Literal::IntLiteral(i))) => {
+                    Expression::Literal(Literal::IntLiteral(i))
+                }
+                Some(_) => panic!(
+                    "Raw value of {}.{} must be an integer literal",
+                    e.identifier.token, member.identifier.token
+                ),
+            };
+            if let Expression::Literal(Literal::IntLiteral(i)) = hidden_value {
+                next_value = i + 1;
+            }
+
+            let member = EnumMember {
+                hidden_value: Some(hidden_value),
+                enum_type: Type::UserDefinedType(e.identifier.clone()),
+                ..member.clone()
+            };
+            self.add_property(
+                Property::EnumCase(member.clone()),
+                &member.identifier.token,
+                &e.identifier.token,
+            );
+        }
     }
 
     fn add_conformance(&mut self, t: &TypeIdentifier, conformance_identifier: &TypeIdentifier) {
@@ -545,6 +660,20 @@ impl Environment {
         identifier: &TypeIdentifier,
         t: &TypeIdentifier,
     ) {
+        // Default values of Fixed properties are stored with exactly their declared decimals
+        let mut property = property;
+        if let Property::VariableDeclaration(v) = &mut property {
+            if let Type::FixedPointType(f) = v.variable_type.clone() {
+                if let Some(expression) = v.expression.as_mut() {
+                    if let Expression::Literal(l) = &**expression {
+                        if let Some(literal) = f.literal(l) {
+                            **expression = Expression::Literal(literal);
+                        }
+                    }
+                }
+            }
+        }
+
         let type_info = &self.types.get_mut(t);
         if type_info.is_some() {
             &self
@@ -611,6 +740,12 @@ impl Environment {
         self.types.get_mut(t).unwrap().public_initializer.is_some()
     }
 
+    // Types declared by the standard library that is prepended to every
+    // program. It implements the currency primitives on top of the runtime.
+    pub fn is_stdlib_type(t: &TypeIdentifier) -> bool {
+        t == "Quartz_Global" || t == "Wei" || t == "Libra" || t == "Libra_Coin" || t == "Currency"
+    }
+
     pub fn is_contract_declared(&self, t: &TypeIdentifier) -> bool {
         let contract = &self.contract_declarations.iter().find(|&x| x.token.eq(t));
         if contract.is_none() {
@@ -659,6 +794,122 @@ impl Environment {
         true
     }
 
+    pub fn is_struct_trait_declared(&self, t: &TypeIdentifier) -> bool {
+        let identifier = &self
+            .struct_trait_declarations
+            .iter()
+            .find(|&x| x.token.eq(t));
+        if identifier.is_none() {
+            return false;
+        }
+        true
+    }
+
+    // Functions and initialisers required by a struct trait that a conforming type
+    // does not declare with the same labels and types
+    pub fn missing_trait_functions(
+        &self,
+        t: &TypeIdentifier,
+        trait_identifier: &TypeIdentifier,
+    ) -> Vec<String> {
+        let required = match self.types.get(trait_identifier) {
+            Some(type_info) => type_info.clone(),
+            None => return vec![],
+        };
+        let provided = match self.types.get(t) {
+            Some(type_info) => type_info.clone(),
+            None => Default::default(),
+        };
+
+        let expect = |parameters: &Vec<Parameter>| -> Vec<Parameter> {
+            let mut parameters = parameters.clone();
+            for parameter in &mut parameters {
+                parameter.type_assignment.substitute_type(trait_identifier, t);
+            }
+            parameters
+        };
+        let describe = |name: &str, parameters: &Vec<Parameter>| -> String {
+            let labels: Vec<String> = parameters
+                .iter()
+                .map(|p| format!("{}:", p.identifier.token))
+                .collect();
+            format!("{}({})", name, labels.join(""))
+        };
+
+        let mut missing = vec![];
+        for (name, functions) in &required.functions {
+            for function in functions {
+                if !function.is_signature {
+                    continue;
+                }
+
+                let parameters = expect(&function.declaration.head.parameters);
+                let mut result_type = function.get_result_type();
+                if let Some(result_type) = result_type.as_mut() {
+                    result_type.substitute_type(trait_identifier, t);
+                }
+
+                let implemented = provided.functions.get(name).is_some_and(|candidates| {
+                    candidates.iter().any(|c| {
+                        let result_name = c.get_result_type().map(|r| r.name());
+                        result_name == result_type.as_ref().map(|r| r.name())
+                            && parameters_match(&c.declaration.head.parameters, &parameters)
+                    })
+                });
+                if !implemented {
+                    missing.push(describe(name, &parameters));
+                }
+            }
+        }
+
+        for initialiser in &required.initialisers {
+            let parameters = expect(&initialiser.declaration.head.parameters);
+            let implemented = provided
+                .initialisers
+                .iter()
+                .any(|i| parameters_match(&i.declaration.head.parameters, &parameters));
+            if !implemented {
+                missing.push(describe("init", &parameters));
+            }
+        }
+
+        missing.sort();
+        missing
+    }
+
+    // Uses of the Currency trait as a type refer to the coin of the target from here on
+    pub fn resolve_currency(&mut self, currency: &TypeIdentifier) {
+        for type_info in self.types.values_mut() {
+            for property in type_info.properties.values_mut() {
+                if let Property::VariableDeclaration(v) = &mut property.property {
+                    v.variable_type.resolve_currency(currency);
+                }
+            }
+
+            for functions in type_info.functions.values_mut() {
+                for function in functions {
+                    let head = &mut function.declaration.head;
+                    for parameter in &mut head.parameters {
+                        parameter.type_assignment.resolve_currency(currency);
+                    }
+                    if let Some(result_type) = head.result_type.as_mut() {
+                        result_type.resolve_currency(currency);
+                    }
+                }
+            }
+
+            let specials = type_info
+                .initialisers
+                .iter_mut()
+                .chain(type_info.fallbacks.iter_mut());
+            for special in specials {
+                for parameter in &mut special.declaration.head.parameters {
+                    parameter.type_assignment.resolve_currency(currency);
+                }
+            }
+        }
+    }
+
     pub fn is_asset_declared(&self, t: &TypeIdentifier) -> bool {
         let identifier = &self.asset_declarations.iter().find(|&x| x.token.eq(t));
         if identifier.is_none() {
@@ -970,7 +1221,9 @@ impl Environment {
         return result;
     }
 
-    fn compatible_caller_protections(
+    // Every protection the caller may hold has to be accepted by one of the
+    // target's protections
+    pub fn compatible_caller_protections(
         &self,
         source: Vec<CallerProtection>,
         target: Vec<CallerProtection>,
@@ -979,10 +1232,11 @@ impl Environment {
             return true;
         }
         for caller_protection in source {
-            for parent in &target {
-                if !caller_protection.is_sub_protection(parent.clone()) {
-                    return false;
-                }
+            if !target
+                .iter()
+                .any(|parent| caller_protection.is_sub_protection(parent.clone()))
+            {
+                return false;
             }
         }
         true
@@ -1005,7 +1259,10 @@ impl Environment {
             .parameters
             .clone()
             .into_iter()
-            .map(|p| p.as_variable_declaration())
+            .map(|p| VariableDeclaration {
+                expression: p.expression.clone().map(Box::new),
+                ..p.as_variable_declaration()
+            })
             .collect();
 
         println!("Parameter Types = ");
@@ -1088,7 +1345,7 @@ impl Environment {
             argument_index += 1;
         }
 
-        while index < required_parameters.len() && argument_index < arguments.len() {
+        while index < parameters.len() && argument_index < arguments.len() {
             if arguments[argument_index].identifier.is_some() {
             } else {
                 let declared_type = declared_types[index].clone();
@@ -1110,20 +1367,9 @@ impl Environment {
                 continue;
             }
 
-            while index < parameters.len() {
-                if arguments[argument_index].identifier.is_some() {
-                    let argument_name = arguments[argument_index]
-                        .identifier
-                        .as_ref()
-                        .unwrap()
-                        .token
-                        .clone();
-                    if argument_name != parameters[index].identifier.token {
-                        index += 1;
-                    }
-                } else {
-                    break;
-                }
+            let argument_name = &arguments[argument_index].identifier.as_ref().unwrap().token;
+            while index < parameters.len() && argument_name != &parameters[index].identifier.token {
+                index += 1;
             }
 
             if index == parameters.len() {
@@ -1375,6 +1621,290 @@ impl Environment {
         return FunctionCallMatchResult::Failure(candidates);
     }
 
+    // Explains a call that does not resolve to exactly one function, listing
+    // every candidate with the reason it was rejected
+    pub fn function_call_diagnostic(
+        &self,
+        f: &FunctionCall,
+        t: &TypeIdentifier,
+        caller_protections: Vec<CallerProtection>,
+        scope: ScopeContext,
+    ) -> Option<FunctionCallDiagnostic> {
+        // Calls with arguments of unknown type cannot be explained
+        let argument_types = self.argument_types(f, t, scope.clone());
+        let is_unknown = |t: &Type| match t {
+            Type::Error => true,
+            Type::InoutType(i) => *i.key_type == Type::Error,
+            _ => false,
+        };
+        if argument_types.iter().any(is_unknown) {
+            return None;
+        }
+
+        let result =
+            self.match_function_call(f.clone(), t, caller_protections.clone(), scope.clone());
+
+        let functions = self
+            .types
+            .get(t)
+            .and_then(|i| i.all_functions().get(&f.identifier.token).cloned())
+            .unwrap_or_default();
+
+        match result {
+            FunctionCallMatchResult::MatchedFunction(_) => {
+                let matching: Vec<FunctionInformation> = functions
+                    .into_iter()
+                    .filter(|function| {
+                        self.function_call_arguments_compatible(
+                            function.clone(),
+                            f.clone(),
+                            t,
+                            scope.clone(),
+                        ) && self.compatible_caller_protections(
+                            caller_protections.clone(),
+                            function.caller_protection.clone(),
+                        )
+                    })
+                    .collect();
+                if matching.len() < 2 {
+                    return None;
+                }
+
+                let candidates = matching
+                    .iter()
+                    .map(|function| (function_signature(function), CandidateRejection::Matched))
+                    .collect();
+                Some(FunctionCallDiagnostic {
+                    function_call: f.clone(),
+                    argument_types,
+                    candidates,
+                })
+            }
+            FunctionCallMatchResult::Failure(c) => {
+                if c.candidates.is_empty() {
+                    if self.is_callable_name(f, t) {
+                        return None;
+                    }
+                    return Some(FunctionCallDiagnostic {
+                        function_call: f.clone(),
+                        argument_types,
+                        candidates: vec![],
+                    });
+                }
+
+                let mut candidates = vec![];
+                for function in &functions {
+                    let rejection =
+                        self.function_rejection(function, f, t, &caller_protections, &scope);
+                    candidates.push((function_signature(function), rejection));
+                }
+
+                if let Some(type_info) = self.types.get(&f.identifier.token) {
+                    for initialiser in &type_info.initialisers {
+                        let rejection = self.contained_types_rejection(
+                            &initialiser.declaration.head.parameters,
+                            &initialiser.caller_protections,
+                            &argument_types,
+                            &caller_protections,
+                        );
+                        let signature = parameters_signature(
+                            "init".to_string(),
+                            &initialiser.declaration.head.parameters,
+                        );
+                        candidates.push((signature, rejection));
+                    }
+                }
+
+                let globals = self
+                    .types
+                    .get(&"Quartz_Global".to_string())
+                    .and_then(|i| i.functions.get(&f.identifier.token).cloned())
+                    .unwrap_or_default();
+                for function in &globals {
+                    let rejection = self.contained_types_rejection(
+                        &function.declaration.head.parameters,
+                        &function.caller_protection,
+                        &argument_types,
+                        &caller_protections,
+                    );
+                    candidates.push((function_signature(function), rejection));
+                }
+
+                Some(FunctionCallDiagnostic {
+                    function_call: f.clone(),
+                    argument_types,
+                    candidates,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    // Whether a function, initialiser or event is declared with the name of the
+    // call. Quartz$ functions belong to the ether runtime, which is generated.
+    fn is_callable_name(&self, f: &FunctionCall, t: &TypeIdentifier) -> bool {
+        let name = &f.identifier.token;
+        if name.starts_with("Quartz$") {
+            return true;
+        }
+        let type_info = match self.types.get(t) {
+            Some(type_info) => type_info,
+            None => return true,
+        };
+        type_info.all_functions().contains_key(name)
+            || type_info.trait_functions().contains_key(name)
+            || self.types.contains_key(name)
+            || self.event_declarations.iter().any(|e| &e.token == name)
+            || self
+                .types
+                .get("Quartz_Global")
+                .is_some_and(|i| i.functions.contains_key(name))
+    }
+
+    fn argument_types(
+        &self,
+        f: &FunctionCall,
+        t: &TypeIdentifier,
+        scope: ScopeContext,
+    ) -> Vec<Type> {
+        f.arguments
+            .iter()
+            .map(|a| {
+                self.get_expression_type(a.expression.clone(), t, vec![], vec![], scope.clone())
+            })
+            .collect()
+    }
+
+    // Follows the rules of check_parameter_compatibility: labelled arguments in
+    // declaration order, where parameters with default values may be skipped
+    fn function_rejection(
+        &self,
+        function: &FunctionInformation,
+        f: &FunctionCall,
+        t: &TypeIdentifier,
+        caller_protections: &[CallerProtection],
+        scope: &ScopeContext,
+    ) -> CandidateRejection {
+        let parameters = &function.declaration.head.parameters;
+        let declared_types = Environment::replace_self(function.get_parameter_types(), t);
+        let required = function.required_parameter_identifiers().len();
+
+        if f.arguments.len() > parameters.len() || f.arguments.len() < required {
+            return CandidateRejection::ArgumentCount {
+                expected: parameters.len(),
+                actual: f.arguments.len(),
+            };
+        }
+
+        let mut index = 0;
+        for argument in &f.arguments {
+            let label = argument.identifier.as_ref().map(|i| i.token.clone());
+            while index < parameters.len()
+                && parameters[index].expression.is_some()
+                && label.as_ref() != Some(&parameters[index].identifier.token)
+            {
+                index += 1;
+            }
+
+            if index == parameters.len() {
+                return CandidateRejection::ArgumentLabel {
+                    expected: None,
+                    actual: label,
+                };
+            }
+
+            let parameter = &parameters[index];
+            if label.as_ref() != Some(&parameter.identifier.token) {
+                return CandidateRejection::ArgumentLabel {
+                    expected: Some(parameter.identifier.token.clone()),
+                    actual: label,
+                };
+            }
+
+            let expected = declared_types[index].clone();
+            let actual = self.get_expression_type(
+                argument.expression.clone(),
+                t,
+                vec![],
+                vec![],
+                scope.clone(),
+            );
+            if expected.is_inout_type() && !actual.is_inout_type() {
+                return CandidateRejection::MissingInout {
+                    parameter: parameter.identifier.token.clone(),
+                };
+            }
+
+            if expected != actual {
+                return CandidateRejection::ArgumentType {
+                    parameter: parameter.identifier.token.clone(),
+                    expected,
+                    actual,
+                };
+            }
+            index += 1;
+        }
+
+        if !self.compatible_caller_protections(
+            caller_protections.to_vec(),
+            function.caller_protection.clone(),
+        ) {
+            return CandidateRejection::CallerProtection {
+                required: function.caller_protection.clone(),
+                actual: caller_protections.to_vec(),
+            };
+        }
+        CandidateRejection::Matched
+    }
+
+    // Initialisers and global functions are matched by the types of their
+    // arguments only
+    fn contained_types_rejection(
+        &self,
+        parameters: &[Parameter],
+        required: &[CallerProtection],
+        argument_types: &[Type],
+        caller_protections: &[CallerProtection],
+    ) -> CandidateRejection {
+        let parameter_types: Vec<Type> = parameters
+            .iter()
+            .map(|p| p.type_assignment.clone())
+            .collect();
+        for (argument, actual) in argument_types.iter().enumerate() {
+            if parameter_types.contains(actual) {
+                continue;
+            }
+
+            return match parameters.get(argument) {
+                Some(parameter) => {
+                    if parameter.type_assignment.is_inout_type() && !actual.is_inout_type() {
+                        CandidateRejection::MissingInout {
+                            parameter: parameter.identifier.token.clone(),
+                        }
+                    } else {
+                        CandidateRejection::ArgumentType {
+                            parameter: parameter.identifier.token.clone(),
+                            expected: parameter.type_assignment.clone(),
+                            actual: actual.clone(),
+                        }
+                    }
+                }
+                None => CandidateRejection::ArgumentCount {
+                    expected: parameters.len(),
+                    actual: argument_types.len(),
+                },
+            };
+        }
+
+        if !self.compatible_caller_protections(caller_protections.to_vec(), required.to_vec()) {
+            return CandidateRejection::CallerProtection {
+                required: required.to_vec(),
+                actual: caller_protections.to_vec(),
+            };
+        }
+        CandidateRejection::Matched
+    }
+
     pub fn is_runtime_function_call(function_call: &FunctionCall) -> bool {
         let ident = function_call.identifier.token.clone();
         ident.starts_with("Quartz_")
@@ -1483,7 +2013,15 @@ impl Environment {
             Expression::ArrayLiteral(a) => {
                 return self.get_array_literal_type(a, t, type_states, caller_protections, scope)
             }
-            Expression::DictionaryLiteral(_) => unimplemented!(),
+            Expression::DictionaryLiteral(d) => {
+                return self.get_dictionary_literal_type(
+                    d,
+                    t,
+                    type_states,
+                    caller_protections,
+                    scope,
+                )
+            }
             Expression::SelfExpression => Type::UserDefinedType(Identifier {
                 token: t.clone(),
                 enclosing_type: None,
@@ -1502,7 +2040,7 @@ impl Environment {
                 match identifer_type {
                     Type::ArrayType(a) => *a.key_type,
                     Type::FixedSizedArrayType(a) => *a.key_type,
-                    Type::DictionaryType(d) => *d.key_type,
+                    Type::DictionaryType(d) => *d.value_type,
                     _ => Type::Error,
                 }
             }
@@ -1559,7 +2097,9 @@ impl Environment {
             Literal::AddressLiteral(_) => Type::Address,
             Literal::StringLiteral(_) => Type::String,
             Literal::IntLiteral(_) => Type::Int,
-            Literal::FloatLiteral(_) => Type::Int,
+            Literal::FloatLiteral(f) => Type::FixedPointType(FixedPointType {
+                decimals: FixedPointType::decimals_of(&f),
+            }),
         }
     }
 
@@ -1688,13 +2228,23 @@ impl Environment {
             return rhs_type;
         }
 
-        return self.get_expression_type(
+        let rhs_type = self.get_expression_type(
             *b.rhs_expression,
             t,
-            type_states,
-            caller_protections,
-            scope,
+            type_states.clone(),
+            caller_protections.clone(),
+            scope.clone(),
         );
+
+        // Scaling an Int by a Fixed value gives a Fixed value
+        if b.op.is_arithmetic() && !rhs_type.is_fixed_point_type() {
+            let lhs_type =
+                self.get_expression_type(*b.lhs_expression, t, type_states, caller_protections, scope);
+            if lhs_type.is_fixed_point_type() {
+                return lhs_type;
+            }
+        }
+        rhs_type
     }
 
     fn get_array_literal_type(
@@ -1738,6 +2288,55 @@ impl Environment {
         });
     }
 
+    fn get_dictionary_literal_type(
+        &self,
+        d: DictionaryLiteral,
+        t: &TypeIdentifier,
+        type_states: Vec<TypeState>,
+        caller_protections: Vec<CallerProtection>,
+        scope: ScopeContext,
+    ) -> Type {
+        let mut key_type: Option<Type> = None;
+        let mut value_type: Option<Type> = None;
+
+        for (key, value) in d.elements {
+            let element_key_type = self.get_expression_type(
+                key.clone(),
+                t,
+                type_states.clone(),
+                caller_protections.clone(),
+                scope.clone(),
+            );
+            let element_value_type = self.get_expression_type(
+                value.clone(),
+                t,
+                type_states.clone(),
+                caller_protections.clone(),
+                scope.clone(),
+            );
+
+            if key_type.is_some() && value_type.is_some() {
+                let comparison_key = key_type.clone().unwrap();
+                let comparison_value = value_type.clone().unwrap();
+                if comparison_key != element_key_type || comparison_value != element_value_type {
+                    return Type::Error;
+                }
+            }
+            if key_type.is_none() {
+                key_type = Some(element_key_type);
+                value_type = Some(element_value_type);
+            }
+        }
+
+        //TODO change to Type::Any
+        let key_type = key_type.unwrap_or(Type::Error);
+        let value_type = value_type.unwrap_or(Type::Error);
+        return Type::DictionaryType(DictionaryType {
+            key_type: Box::new(key_type),
+            value_type: Box::new(value_type),
+        });
+    }
+
     fn get_function_call_type(
         &self,
         f: FunctionCall,
@@ -1774,6 +2373,13 @@ impl Environment {
     }
 
     pub fn property_offset(&self, property: String, t: &TypeIdentifier) -> u64 {
+        if self.packed_storage && self.is_contract_declared(t) {
+            let slot = self.storage_slot(property.clone(), t);
+            if slot.is_some() {
+                return slot.unwrap().slot;
+            }
+        }
+
         let mut offset_map: HashMap<String, u64> = HashMap::new();
         let mut offset: u64 = 0;
 
@@ -1799,6 +2405,60 @@ impl Environment {
         }
     }
 
+    pub fn storage_layout(&self, t: &TypeIdentifier) -> Vec<StorageSlot> {
+        let mut layout = vec![];
+        if let Some(root_type) = self.types.get(t) {
+            let mut slot: u64 = 0;
+            let mut byte_offset: u64 = 0;
+            for p in root_type.ordered_properties.clone() {
+                let property = root_type.properties.get(&p);
+                let property = property.unwrap();
+                let property_type = property.property.get_type();
+                let byte_size = self.storage_byte_size(property_type.clone());
+
+                if byte_offset != 0 && (byte_size >= 32 || byte_offset + byte_size > 32) {
+                    slot += 1;
+                    byte_offset = 0;
+                }
+
+                layout.push(StorageSlot {
+                    property: p.clone(),
+                    property_type,
+                    slot,
+                    byte_offset,
+                    byte_size,
+                });
+
+                if byte_size >= 32 {
+                    slot += byte_size / 32;
+                } else {
+                    byte_offset += byte_size;
+                }
+            }
+        }
+        layout
+    }
+
+    pub fn storage_slot(&self, property: String, t: &TypeIdentifier) -> Option<StorageSlot> {
+        if !self.is_contract_declared(t) {
+            return None;
+        }
+        self.storage_layout(t)
+            .into_iter()
+            .find(|s| s.property == property)
+    }
+
+    fn storage_byte_size(&self, input_type: Type) -> u64 {
+        if self.packed_storage {
+            match input_type {
+                Type::Bool => return 1,
+                Type::Address => return 20,
+                _ => {}
+            }
+        }
+        self.type_size(input_type) * 32
+    }
+
     pub fn replace_self(list: Vec<Type>, enclosing: &TypeIdentifier) -> Vec<Type> {
         let result: Vec<Type> = list
             .into_iter()
@@ -1810,6 +2470,7 @@ impl Environment {
     fn external_trait_init() -> SpecialSignatureDeclaration {
         SpecialSignatureDeclaration {
             special_token: "init".to_string(),
+            line_info: Default::default(),
             attributes: vec![],
             modifiers: vec![],
             mutates: vec![],
@@ -1841,10 +2502,11 @@ impl Environment {
                 let size = a.size.clone();
                 key_size * size
             }
-            Type::DictionaryType(_) => unimplemented!(),
+            Type::DictionaryType(_) => 1,
+            Type::FixedPointType(_) => 1,
             Type::UserDefinedType(i) => {
                 if self.is_enum_declared(&i.token) {
-                    unimplemented!()
+                    return 1;
                 }
 
                 let mut acc = 0;
@@ -1864,3 +2526,200 @@ impl Environment {
         }
     }
 }
+
+fn parameters_match(parameters: &[Parameter], expected: &[Parameter]) -> bool {
+    parameters.len() == expected.len()
+        && parameters.iter().zip(expected.iter()).all(|(p, e)| {
+            p.identifier.token == e.identifier.token
+                && p.type_assignment.name() == e.type_assignment.name()
+        })
+}
+
+// Why a candidate was not chosen for a function call
+#[derive(Debug, Clone)]
+pub enum CandidateRejection {
+    ArgumentCount {
+        expected: usize,
+        actual: usize,
+    },
+    ArgumentLabel {
+        expected: Option<String>,
+        actual: Option<String>,
+    },
+    ArgumentType {
+        parameter: String,
+        expected: Type,
+        actual: Type,
+    },
+    MissingInout {
+        parameter: String,
+    },
+    CallerProtection {
+        required: Vec<CallerProtection>,
+        actual: Vec<CallerProtection>,
+    },
+    Matched,
+}
+
+impl fmt::Display for CandidateRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CandidateRejection::ArgumentCount { expected, actual } => {
+                write!(
+                    f,
+                    "expects {} arguments but {} were given",
+                    expected, actual
+                )
+            }
+            CandidateRejection::ArgumentLabel { expected, actual } => {
+                let actual = match actual {
+                    Some(label) => format!("label {}", label),
+                    None => "no label".to_string(),
+                };
+                match expected {
+                    Some(expected) => write!(f, "expects label {} but found {}", expected, actual),
+                    None => write!(f, "has no parameter for {}", actual),
+                }
+            }
+            CandidateRejection::ArgumentType {
+                parameter,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "parameter {} expects {} but found {}",
+                parameter, expected, actual
+            ),
+            CandidateRejection::MissingInout { parameter } => {
+                write!(f, "parameter {} is inout, pass it with &", parameter)
+            }
+            CandidateRejection::CallerProtection { required, actual } => write!(
+                f,
+                "requires caller protection ({}) but is called from ({})",
+                protection_names(required),
+                protection_names(actual)
+            ),
+            CandidateRejection::Matched => write!(f, "matches"),
+        }
+    }
+}
+
+// A function call that matches no candidate, or more than one
+#[derive(Debug, Clone)]
+pub struct FunctionCallDiagnostic {
+    pub function_call: FunctionCall,
+    pub argument_types: Vec<Type>,
+    pub candidates: Vec<(String, CandidateRejection)>,
+}
+
+impl FunctionCallDiagnostic {
+    pub fn is_ambiguous(&self) -> bool {
+        !self.candidates.is_empty()
+            && self
+                .candidates
+                .iter()
+                .all(|(_, r)| matches!(r, CandidateRejection::Matched))
+    }
+}
+
+impl fmt::Display for FunctionCallDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let arguments: Vec<String> = self
+            .function_call
+            .arguments
+            .iter()
+            .zip(&self.argument_types)
+            .map(|(a, t)| match &a.identifier {
+                Some(label) => format!("{}: {}", label.token, t),
+                None => format!("{}", t),
+            })
+            .collect();
+        if self.candidates.is_empty() {
+            return write!(
+                f,
+                "No function named {} on line {}",
+                self.function_call.identifier.token, self.function_call.identifier.line_info.line
+            );
+        }
+        let problem = if self.is_ambiguous() {
+            "Ambiguous call"
+        } else {
+            "No matching function for call"
+        };
+        write!(
+            f,
+            "{} {}({}) on line {}, candidates are:",
+            problem,
+            self.function_call.identifier.token,
+            arguments.join(", "),
+            self.function_call.identifier.line_info.line
+        )?;
+        for (signature, rejection) in &self.candidates {
+            write!(f, "\n    {}: {}", signature, rejection)?;
+        }
+        Ok(())
+    }
+}
+
+fn function_signature(function: &FunctionInformation) -> String {
+    let head = &function.declaration.head;
+    parameters_signature(format!("func {}", head.identifier.token), &head.parameters)
+}
+
+fn parameters_signature(name: String, parameters: &[Parameter]) -> String {
+    let parameters: Vec<String> = parameters
+        .iter()
+        .map(|p| format!("{}: {}", p.identifier.token, p.type_assignment))
+        .collect();
+    format!("{}({})", name, parameters.join(", "))
+}
+
+pub fn protection_names(protections: &[CallerProtection]) -> String {
+    let names: Vec<String> = protections.iter().map(|p| p.name()).collect();
+    names.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::AstProcessor::tests::checked;
+    use crate::AstProcessor::Target;
+
+    // The diagnostic reported for the call in the body of a function of Shop
+    fn call_diagnostic(body: &str) -> String {
+        let program = format!(
+            "
+            contract Shop {{
+              var count: Int = 0
+            }}
+            Shop :: caller <- (any) {{
+              public init() {{}}
+
+              public func call() mutates (count) {{
+                {}
+              }}
+
+              func stock(amount: Int) {{}}
+
+              func swap(value: inout Int) {{}}
+            }}",
+            body
+        );
+        checked(&program, &Target::Ether).err().unwrap()
+    }
+
+    #[test]
+    fn test_call_diagnostics() {
+        assert_eq!(call_diagnostic("order()"), "No function named order on line 9");
+        assert_eq!(
+            call_diagnostic("stock(amount: true)"),
+            "No matching function for call stock(amount: Bool) on line 9, candidates are:
+    func stock(amount: Int): parameter amount expects Int but found Bool"
+        );
+        assert_eq!(
+            call_diagnostic("swap(value: count)"),
+            "No matching function for call swap(value: Int) on line 9, candidates are:
+    func swap(value: inout Int): parameter value is inout, pass it with &"
+        );
+    }
+}
@@ -1,16 +1,26 @@
 mod AST;
 mod AstProcessor;
+mod CallGraph;
+mod CallerProtectionChecker;
+mod InitialisationChecker;
+mod Linter;
 mod MoveCodeGen;
+mod MutationChecker;
 mod Parser;
+mod ResourceChecker;
 mod SemanticAnalysis;
 mod SolidityCodeGen;
+mod TargetStripper;
 mod TypeAssigner;
 mod TypeChecker;
 mod context;
 mod environment;
 mod visitor;
 use crate::AstProcessor::Target;
+use crate::Linter::LintLevel;
+use crate::MoveCodeGen::MoveSource::CoinModule;
 use nom_locate::LocatedSpan;
+use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
@@ -27,23 +37,110 @@ fn main() {
     let target = &args[1];
     let target = if target == "libra" {
         Target::Move
+    } else if target == "move" {
+        Target::MoveSource
     } else if target == "ether" {
         Target::Ether
+    } else if target == "yul" {
+        Target::Yul
+    } else if target == "evm" {
+        Target::Evm
     } else {
-        panic!("Incorrect Target Argument specified, expecting \"ether\" or \"libra\"");
+        panic!(
+            "Incorrect Target Argument specified, expecting \"ether\", \"yul\", \"evm\", \"libra\" or \"move\""
+        );
     };
 
     let filename = &args[2];
 
+    let packed_storage = args.iter().skip(3).any(|a| a == "--packed-storage");
+
+    let gas_report = args.iter().skip(3).any(|a| a == "--gas-report");
+
+    let coin_module = args
+        .iter()
+        .skip(3)
+        .position(|a| a == "--coin-module")
+        .map(|i| match args.get(i + 4) {
+            Some(m) => m.clone(),
+            None => panic!("Expecting a module path after --coin-module"),
+        })
+        .unwrap_or_default();
+
+    let account_module = args
+        .iter()
+        .skip(3)
+        .position(|a| a == "--account-module")
+        .map(|i| match args.get(i + 4) {
+            Some(m) => m.clone(),
+            None => panic!("Expecting a module path after --account-module"),
+        })
+        .unwrap_or_default();
+
+    let coin_module = match CoinModule::from(&coin_module, &account_module) {
+        Ok(coin_module) => coin_module,
+        Err(error) => {
+            println!("Error: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let call_graph_format = args
+        .iter()
+        .skip(3)
+        .position(|a| a == "--call-graph")
+        .map(|i| match args.get(i + 4) {
+            Some(f) if f == "dot" || f == "json" => f.clone(),
+            _ => panic!("Expecting dot or json after --call-graph"),
+        })
+        .unwrap_or_default();
+
+    // --allow, --warn and --deny set the level of a lint for the whole program
+    let mut lint_levels = HashMap::new();
+    for (index, arg) in args.iter().enumerate().skip(3) {
+        let level = match arg.strip_prefix("--").and_then(LintLevel::from_name) {
+            Some(level) => level,
+            None => continue,
+        };
+        let lint = match args.get(index + 1) {
+            Some(lint) => lint.clone(),
+            None => panic!("Expecting a lint name after {}", arg),
+        };
+        if !Linter::is_lint_name(&lint) {
+            panic!("Unknown lint {} after {}", lint, arg);
+        }
+        lint_levels.insert(lint, level);
+    }
+
     let mut file =
         File::open(filename).expect(&*format!("Unable to open file at path {} ", filename));
 
     let mut contents = String::new();
     file.read_to_string(&mut contents)
         .expect("Unable to read the file");
-    let mut program = contents.clone();
+    let program = contents.clone();
+
+    let stdlib = stdlib(&target);
+    let (module, mut environment) = Parser::parse_program(&stdlib, &program, &target);
+    environment.packed_storage = packed_storage;
+    environment.gas_report = gas_report;
+    environment.call_graph_format = call_graph_format;
+    environment.coin_module = coin_module;
+    environment.lint_levels = lint_levels;
 
-    if let Target::Move = target {
+    if let Some(module) = module {
+        if AstProcessor::process_ast(module, environment, target).is_err() {
+            std::process::exit(1);
+        }
+    } else {
+        println!("Parse Error");
+    }
+}
+
+// The standard library of the target, preceded by the Currency trait that each
+// target's coin conforms to
+fn stdlib(target: &Target) -> String {
+    let stdlib = if target.is_move() {
         let mut file =
             File::open("src/stdlib/libra/libra.quartz").expect("Unable to open libra stdlib file ");
         let mut libra = String::new();
@@ -56,12 +153,7 @@ fn main() {
         file.read_to_string(&mut global)
             .expect("Unable to read the stdlib global file");
 
-        program = format!(
-            "{libra} \n {global} \n {program}",
-            libra = libra,
-            global = global,
-            program = program
-        )
+        format!("{libra} \n {global}", libra = libra, global = global)
     } else {
         let mut file =
             File::open("src/stdlib/ether/wei.quartz").expect("Unable to open libra stdlib file ");
@@ -75,21 +167,18 @@ fn main() {
         file.read_to_string(&mut global)
             .expect("Unable to read the stdlib global file");
 
-        program = format!(
-            "{ether} \n {global} \n {program}",
-            ether = ether,
-            global = global,
-            program = program
-        )
-    }
-    let (module, environment) = Parser::parse_program(&program);
+        format!("{ether} \n {global}", ether = ether, global = global)
+    };
 
-    if module.is_none() {
-        println!("Parse Error");
-    }
+    let mut file = File::open("src/stdlib/currency.quartz")
+        .expect("Unable to open currency stdlib file ");
+    let mut currency = String::new();
+    file.read_to_string(&mut currency)
+        .expect("Unable to read the stdlib currency file");
 
-    if module.is_some() {
-        let module = module.unwrap();
-        let process_result = AstProcessor::process_ast(module, environment, target);
-    }
+    format!(
+        "{currency} \n {stdlib}",
+        currency = currency,
+        stdlib = stdlib
+    )
 }